@@ -6,3 +6,24 @@ pub(crate) use gen_passwords::*;
 
 mod hashes_from_file;
 pub(crate) use hashes_from_file::*;
+
+mod train_markov;
+pub(crate) use train_markov::*;
+
+mod partition;
+pub(crate) use partition::*;
+
+mod checkpoint;
+pub(crate) use checkpoint::*;
+
+mod policy;
+pub(crate) use policy::*;
+
+mod progress;
+pub(crate) use progress::*;
+
+mod serve;
+pub(crate) use serve::*;
+
+mod distributed;
+pub(crate) use distributed::*;
@@ -0,0 +1,56 @@
+use anyhow::Result;
+use clap::Args;
+use hashassin_core::checkpoint::Checkpointer;
+use hashassin_core::PipelineError;
+use std::time::Duration;
+
+/// Options to periodically save the progress of a job, and to resume it after it was killed.
+#[derive(Args, Debug)]
+pub(crate) struct CheckpointOpts {
+    /// File to periodically save progress to, so that the job can be resumed with `--resume`.
+    #[clap(long)]
+    checkpoint: Option<String>,
+
+    /// Number of seconds between two saves of the checkpoint file.
+    #[clap(long, default_value = "60", requires = "checkpoint")]
+    checkpoint_interval: u64,
+
+    /// Resume from the checkpoint file if it exists, output files are truncated to the last checkpoint.
+    #[clap(long, requires = "checkpoint")]
+    resume: bool,
+}
+
+impl CheckpointOpts {
+    /// Checkpointer for a job processing `chunk_size` passwords at once, `None` without `--checkpoint`.
+    /// `job` describes the parameters that decide what the job writes, resuming requires the same.
+    pub(crate) fn checkpointer(&self, chunk_size: u64, job: &str) -> Result<Option<Checkpointer>> {
+        self.checkpoint
+            .as_deref()
+            .map(|path| {
+                Checkpointer::new(
+                    path,
+                    Duration::from_secs(self.checkpoint_interval),
+                    self.resume,
+                    chunk_size,
+                    job,
+                )
+            })
+            .transpose()
+    }
+
+    /// Tells how to pick up a job that was cancelled or failed where it stopped
+    pub(crate) fn print_resume_hint(&self, result: &Result<()>) {
+        let (Some(path), Err(e)) = (&self.checkpoint, result) else {
+            return;
+        };
+
+        if matches!(
+            e.downcast_ref::<PipelineError>(),
+            Some(PipelineError::CancelledError(_) | PipelineError::InvalidCandidateError(..))
+        ) {
+            eprintln!(
+                "Progress was saved to `{path}`, run the same command with `--resume` to continue"
+            );
+        }
+    }
+}
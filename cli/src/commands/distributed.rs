@@ -0,0 +1,158 @@
+use anyhow::{Context, Result};
+use clap::Args;
+use hashassin_core::{
+    cancel::CancellationToken,
+    distributed::{Coordinator, WorkSpec, Worker},
+    output::OutputOptions,
+    passwords::{CharSet, PasswordGenerator},
+    sink::FileSink,
+    stats::Stats,
+    PipelineError,
+};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::num::NonZeroUsize;
+use std::sync::Arc;
+use std::time::Duration;
+
+use super::{
+    unique_algorithms, CliAlgorithm, CliCompression, CliEncoding, CliOutputFormat, ProgressOpts,
+};
+
+#[derive(Args, Debug)]
+pub(crate) struct CoordinatorOpts {
+    /// Address workers connect to, e.g. `0.0.0.0:7879` to accept workers from other machines.
+    #[clap(long, default_value = "127.0.0.1:7879")]
+    listen: SocketAddr,
+
+    /// Minimum number of characters of the passwords
+    #[clap(long, default_value = "4")]
+    min_chars: usize,
+
+    /// Maximum number of characters of the passwords
+    #[clap(long, default_value = "4")]
+    max_chars: usize,
+
+    /// Characters of the passwords instead of letters and digits, e.g. `abcäöü€`.
+    #[clap(long)]
+    charset: Option<String>,
+
+    /// What hashing algorithms the workers use, e.g. `md5,sha2`.
+    #[clap(long, value_enum, value_delimiter = ',', required = true)]
+    algorithm: Vec<CliAlgorithm>,
+
+    /// How passwords are turned into bytes before hashing.
+    #[clap(long, value_enum, default_value = "utf8")]
+    encoding: CliEncoding,
+
+    /// Output file the hashes of every worker are written to, in the order units are done.
+    #[clap(long)]
+    out_path: String,
+
+    /// How to compress the output file.
+    #[clap(long, value_enum, default_value = "auto")]
+    compress: CliCompression,
+
+    /// How every hash is written to the output file.
+    #[clap(long, value_enum, default_value = "raw")]
+    output_format: CliOutputFormat,
+
+    /// Number of passwords handed to a worker at once.
+    #[clap(long, default_value = "100000")]
+    unit_size: NonZeroUsize,
+
+    /// Seconds a worker has to hash a unit before it is considered dead and its unit is handed
+    /// to another worker.
+    #[clap(long, default_value = "600")]
+    unit_timeout: u64,
+
+    /// Show progress?
+    #[clap(long)]
+    progress: bool,
+
+    #[clap(flatten)]
+    progress_output: ProgressOpts,
+}
+
+#[derive(Args, Debug)]
+pub(crate) struct WorkerOpts {
+    /// Address of the coordinator, as given to its `--listen`.
+    #[clap(long)]
+    connect: SocketAddr,
+
+    /// Number of threads to hash every unit on
+    #[clap(long, default_value = "4")]
+    threads: NonZeroUsize,
+}
+
+/// Hands the keyspace out to the workers that connect until every password is hashed.
+/// See [`hashassin_core::distributed`] for the protocol.
+pub(crate) fn coordinator(
+    opts: &CoordinatorOpts,
+    stats: &Arc<Stats>,
+    cancel: &CancellationToken,
+) -> Result<()> {
+    // the generator checks the lengths and drops duplicate characters before workers see them
+    let generator = match &opts.charset {
+        Some(charset) => PasswordGenerator::with_chars(
+            opts.min_chars,
+            opts.max_chars,
+            charset.chars().collect(),
+        )?,
+        None => PasswordGenerator::new(
+            opts.min_chars,
+            opts.max_chars,
+            CharSet::LowerAlpha | CharSet::Numeric | CharSet::UpperAlpha,
+        )?,
+    };
+    let total = generator
+        .keyspace()
+        .and_then(|keyspace| u64::try_from(keyspace).ok());
+    let spec = WorkSpec {
+        min_chars: opts.min_chars,
+        max_chars: opts.max_chars,
+        chars: generator.chars().to_vec(),
+        algorithms: unique_algorithms(&opts.algorithm),
+        encoding: opts.encoding.into(),
+    };
+
+    let output = OutputOptions {
+        format: opts.output_format.into(),
+        compression: opts.compress.for_path(&opts.out_path),
+    };
+    let coordinator = Coordinator::new(spec, FileSink::new(&opts.out_path, output))
+        .unit_size(opts.unit_size)
+        .unit_timeout(Duration::from_secs(opts.unit_timeout))
+        .stats(stats.clone());
+
+    let listener = TcpListener::bind(opts.listen)
+        .with_context(|| format!("Cannot listen on {}", opts.listen))?;
+    eprintln!("Waiting for workers on {}", opts.listen);
+
+    let progress = opts.progress_output.progress(opts.progress)?;
+    let job_cancel = cancel.clone();
+    let h = std::thread::spawn(move || coordinator.run(listener, &job_cancel));
+
+    if let Some(progress) = progress {
+        progress.show(stats, total, 0, || h.is_finished());
+    }
+
+    h.join().map_err(|_| PipelineError::WorkerPanicError)?
+}
+
+/// Hashes the units of the coordinator at `--connect` until it is done
+pub(crate) fn worker(
+    opts: &WorkerOpts,
+    stats: &Arc<Stats>,
+    cancel: &CancellationToken,
+) -> Result<()> {
+    let stream = TcpStream::connect(opts.connect)
+        .with_context(|| format!("Cannot connect to the coordinator at {}", opts.connect))?;
+
+    let units = Worker::new()
+        .threads(opts.threads)
+        .stats(stats.clone())
+        .run(stream, cancel)?;
+    eprintln!("Hashed {units} units, the coordinator is done");
+
+    Ok(())
+}
@@ -1,7 +1,23 @@
 use anyhow::Result;
-use clap::Args;
+use clap::{Args, ValueEnum};
+use hashassin_core::bytes::ByteGenerator;
+use hashassin_core::cancel::CancellationToken;
+use hashassin_core::checkpoint::Checkpointer;
+use hashassin_core::combinator::{CombinatorGenerator, HybridGenerator, HybridMode};
+use hashassin_core::encoding::parse_byte_alphabet;
+use hashassin_core::keyspace::SeekableGenerator;
+use hashassin_core::markov::{MarkovGenerator, MarkovStats};
+use hashassin_core::mask::MaskGenerator;
+use hashassin_core::output::Compression;
 use hashassin_core::passwords::{CharSet, PasswordGenerator};
-use hashassin_core::save_passwords_to_disk;
+use hashassin_core::policy::{PolicyFilterExt, PolicyGenerator};
+use hashassin_core::prince::PrinceGenerator;
+use hashassin_core::stats::Stats;
+use hashassin_core::utils::read_lines;
+use hashassin_core::{save_passwords_to_disk, PipelineError};
+use std::sync::Arc;
+
+use super::{CheckpointOpts, PartitionOpts, PolicyOpts, ProgressOpts};
 
 #[derive(Args, Debug)]
 pub(crate) struct GenPasswordsOpts {
@@ -9,6 +25,10 @@ pub(crate) struct GenPasswordsOpts {
     #[clap(long)]
     out_path: String,
 
+    /// How passwords should be generated.
+    #[clap(long, value_enum, default_value = "brute-force")]
+    mode: GenMode,
+
     /// Minimum number of characters generated passwords should be
     #[clap(long, default_value = "4")]
     min_chars: usize,
@@ -16,16 +36,348 @@ pub(crate) struct GenPasswordsOpts {
     /// Maximum number of characters generated passwords should be
     #[clap(long, default_value = "4")]
     max_chars: usize,
+
+    /// Characters used in brute-force and markov mode instead of letters and digits,
+    /// e.g. `abcäöü€`.
+    #[clap(long)]
+    charset: Option<String>,
+
+    /// Bytes used in bytes mode, as hex bytes and ranges, e.g. `00-1f,7f`.
+    #[clap(long, default_value = "00-ff")]
+    byte_alphabet: String,
+
+    /// Wordlist to combine, used as the left wordlist in combinator mode.
+    #[clap(long, required_if_eq_any([
+        ("mode", "combinator"),
+        ("mode", "prince"),
+        ("mode", "hybrid-wordlist-mask"),
+        ("mode", "hybrid-mask-wordlist"),
+    ]))]
+    wordlist: Option<String>,
+
+    /// Right wordlist used in combinator mode.
+    #[clap(long, required_if_eq("mode", "combinator"))]
+    right_wordlist: Option<String>,
+
+    /// Placed between the left and right words in combinator mode.
+    #[clap(long, default_value = "")]
+    separator: String,
+
+    /// Mask used in hybrid modes, e.g. `?d?d?s`.
+    #[clap(long, required_if_eq_any([
+        ("mode", "hybrid-wordlist-mask"),
+        ("mode", "hybrid-mask-wordlist"),
+    ]))]
+    mask: Option<String>,
+
+    /// Statistics file written by `train-markov`, used in markov mode.
+    #[clap(long, required_if_eq("mode", "markov"))]
+    markov_stats: Option<String>,
+
+    /// Maximum number of words chained together in prince mode.
+    #[clap(long, default_value = "8")]
+    max_elements: usize,
+
+    #[clap(flatten)]
+    partition: PartitionOpts,
+
+    /// Show progress?
+    #[clap(long)]
+    progress: bool,
+
+    #[clap(flatten)]
+    progress_output: ProgressOpts,
+
+    /// Write (and compress) passwords in a separate thread while the next ones are generated.
+    #[clap(long)]
+    writer_thread: bool,
+
+    /// How to compress the output file, wordlists can be compressed too.
+    #[clap(long, value_enum, default_value = "auto")]
+    compress: CliCompression,
+
+    #[clap(flatten)]
+    checkpoint: CheckpointOpts,
+
+    #[clap(flatten)]
+    policy: PolicyOpts,
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, ValueEnum, Debug)]
+pub enum GenMode {
+    /// Every combination of characters between min and max chars
+    BruteForce,
+
+    /// Same as brute force, but most likely characters first according to markov statistics
+    Markov,
+
+    /// Every word of the wordlist followed by every word of the right wordlist
+    Combinator,
+
+    /// Every word of the wordlist followed by the mask
+    HybridWordlistMask,
+
+    /// The mask followed by every word of the wordlist
+    HybridMaskWordlist,
+
+    /// Chains of words of the wordlist between min and max chars, shortest first and the most
+    /// likely chains of every length first
+    Prince,
+
+    /// Every combination of bytes of the byte alphabet between min and max chars.
+    /// Candidates that are not printable ASCII are written as `$HEX[...]`
+    Bytes,
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, ValueEnum, Debug)]
+pub enum CliCompression {
+    /// From the extension of the output file: `.gz` for gzip, `.zst` for zstd, otherwise none
+    Auto,
+
+    /// Not compressed
+    None,
+
+    /// Gzip
+    Gzip,
+
+    /// Zstandard
+    Zstd,
+}
+
+impl CliCompression {
+    /// Compression to use for the file at `path`
+    pub(crate) fn for_path(self, path: &str) -> Compression {
+        match self {
+            CliCompression::Auto => Compression::from_path(path),
+            CliCompression::None => Compression::None,
+            CliCompression::Gzip => Compression::Gzip,
+            CliCompression::Zstd => Compression::Zstd,
+        }
+    }
+}
+
+pub(crate) fn gen_passwords(
+    opts: &GenPasswordsOpts,
+    stats: &Arc<Stats>,
+    cancel: &CancellationToken,
+) -> Result<()> {
+    // clap makes sure the options required by every mode are present.
+    match opts.mode {
+        GenMode::BruteForce => {
+            let chars = opts
+                .charset
+                .as_ref()
+                .map(|charset| charset.chars().collect());
+            let char_set = CharSet::LowerAlpha | CharSet::Numeric | CharSet::UpperAlpha;
+
+            // the policy generator skips passwords that do not follow the policy by itself
+            match (opts.policy.policy(), chars) {
+                (Some(policy), Some(chars)) => save_range(
+                    opts,
+                    PolicyGenerator::with_chars(opts.min_chars, opts.max_chars, chars, policy)?,
+                    stats,
+                    cancel,
+                )?,
+                (Some(policy), None) => save_range(
+                    opts,
+                    PolicyGenerator::new(opts.min_chars, opts.max_chars, char_set, policy)?,
+                    stats,
+                    cancel,
+                )?,
+                (None, Some(chars)) => save(
+                    opts,
+                    PasswordGenerator::with_chars(opts.min_chars, opts.max_chars, chars)?,
+                    stats,
+                    cancel,
+                )?,
+                (None, None) => save(
+                    opts,
+                    PasswordGenerator::new(opts.min_chars, opts.max_chars, char_set)?,
+                    stats,
+                    cancel,
+                )?,
+            }
+        }
+        GenMode::Markov => {
+            let markov_stats = MarkovStats::load(opts.markov_stats.as_deref().unwrap_or_default())?;
+            let generator = match &opts.charset {
+                Some(charset) => MarkovGenerator::with_chars(
+                    &markov_stats,
+                    opts.min_chars,
+                    opts.max_chars,
+                    charset.chars().collect(),
+                )?,
+                None => MarkovGenerator::new(
+                    &markov_stats,
+                    opts.min_chars,
+                    opts.max_chars,
+                    CharSet::LowerAlpha | CharSet::Numeric | CharSet::UpperAlpha,
+                )?,
+            };
+
+            save(opts, generator, stats, cancel)?;
+        }
+        GenMode::Combinator => {
+            let left = read_lines(opts.wordlist.as_deref().unwrap_or_default())?;
+            let right = read_lines(opts.right_wordlist.as_deref().unwrap_or_default())?;
+            let generator = CombinatorGenerator::new(left, right, &opts.separator);
+
+            save(opts, generator, stats, cancel)?;
+        }
+        GenMode::HybridWordlistMask | GenMode::HybridMaskWordlist => {
+            let words = read_lines(opts.wordlist.as_deref().unwrap_or_default())?;
+            let mask = MaskGenerator::new(opts.mask.as_deref().unwrap_or_default())?;
+            let mode = if opts.mode == GenMode::HybridWordlistMask {
+                HybridMode::WordlistMask
+            } else {
+                HybridMode::MaskWordlist
+            };
+            let generator = HybridGenerator::new(words, mask, mode);
+
+            save(opts, generator, stats, cancel)?;
+        }
+        GenMode::Prince => {
+            let words = read_lines(opts.wordlist.as_deref().unwrap_or_default())?;
+            let generator =
+                PrinceGenerator::new(words, opts.min_chars, opts.max_chars, opts.max_elements)?;
+
+            save(opts, generator, stats, cancel)?;
+        }
+        GenMode::Bytes => {
+            let alphabet = parse_byte_alphabet(&opts.byte_alphabet)?;
+            let generator = ByteGenerator::new(opts.min_chars, opts.max_chars, alphabet)?;
+
+            save(opts, generator.hex_encoded(), stats, cancel)?;
+        }
+    }
+
+    Ok(())
 }
 
-pub(crate) fn gen_passwords(opts: &GenPasswordsOpts) -> Result<()> {
-    let generator = PasswordGenerator::new(
+/// Parameters that decide which passwords are written, a checkpoint only resumes the same job
+fn job(opts: &GenPasswordsOpts) -> String {
+    let generator = (
+        opts.mode,
         opts.min_chars,
         opts.max_chars,
-        CharSet::LowerAlpha | CharSet::Numeric | CharSet::UpperAlpha,
-    )?;
+        &opts.charset,
+        &opts.byte_alphabet,
+        &opts.wordlist,
+        &opts.right_wordlist,
+        &opts.separator,
+        &opts.mask,
+        &opts.markov_stats,
+        opts.max_elements,
+    );
+    let output = (
+        &opts.partition,
+        &opts.policy,
+        opts.compress.for_path(&opts.out_path),
+    );
 
-    save_passwords_to_disk(&opts.out_path, generator, None)?;
+    format!("gen-passwords {:?} {:?}", generator, output)
+}
 
-    Ok(())
+/// Writes the passwords of the part of the keyspace selected on the command line that follow
+/// the policy to disk
+fn save<G: SeekableGenerator + Send>(
+    opts: &GenPasswordsOpts,
+    generator: G,
+    stats: &Arc<Stats>,
+    cancel: &CancellationToken,
+) -> Result<()> {
+    let Some(policy) = opts.policy.policy() else {
+        return save_range(opts, generator, stats, cancel);
+    };
+
+    let checkpointer = opts.checkpoint.checkpointer(1, &job(opts))?;
+    let range = opts.partition.range(generator.keyspace())?;
+
+    // the checkpoint counts the passwords that follow the policy, which can only be found again
+    // by filtering from the start of the range
+    let position = checkpointer
+        .as_ref()
+        .map_or(0, |checkpointer| checkpointer.checkpoint().position);
+    let passwords = generator
+        .in_range(range)
+        .with_policy(policy)
+        .skip(position.try_into()?);
+
+    // how many passwords follow the policy is unknown until they are all generated
+    write(opts, passwords, None, checkpointer, stats, cancel)
+}
+
+/// Writes every password of the part of the keyspace selected on the command line to disk
+fn save_range<G: SeekableGenerator + Send>(
+    opts: &GenPasswordsOpts,
+    generator: G,
+    stats: &Arc<Stats>,
+    cancel: &CancellationToken,
+) -> Result<()> {
+    let checkpointer = opts.checkpoint.checkpointer(1, &job(opts))?;
+    let keyspace = generator.keyspace();
+    let mut range = opts.partition.range(keyspace)?;
+    let total = keyspace
+        .map(|keyspace| range.bounds(keyspace))
+        .and_then(|(start, end)| u64::try_from(end - start).ok());
+
+    // passwords are written one after the other, so everything before the position is on disk
+    if let Some(checkpointer) = &checkpointer {
+        range = range.within(checkpointer.checkpoint().position, None);
+    }
+
+    write(
+        opts,
+        generator.in_range(range),
+        total,
+        checkpointer,
+        stats,
+        cancel,
+    )
+}
+
+/// Writes passwords to the output file, showing progress with `--progress`.
+/// `total` is the number of passwords of the whole job, if known.
+fn write<I>(
+    opts: &GenPasswordsOpts,
+    passwords: I,
+    total: Option<u64>,
+    checkpointer: Option<Checkpointer>,
+    stats: &Arc<Stats>,
+    cancel: &CancellationToken,
+) -> Result<()>
+where
+    I: Iterator<Item = String> + Send,
+{
+    let compression = opts.compress.for_path(&opts.out_path);
+    let progress = opts.progress_output.progress(opts.progress)?;
+    // the checkpoint's position is the number of passwords already written
+    let done_before = checkpointer
+        .as_ref()
+        .map_or(0, |checkpointer| checkpointer.checkpoint().position)
+        .try_into()?;
+
+    let result = std::thread::scope(|s| {
+        let job_stats = stats.clone();
+        let h = s.spawn(move || {
+            save_passwords_to_disk(
+                &opts.out_path,
+                passwords,
+                job_stats,
+                checkpointer,
+                compression,
+                opts.writer_thread,
+                cancel,
+            )
+        });
+
+        if let Some(progress) = progress {
+            progress.show(stats, total, done_before, || h.is_finished());
+        }
+
+        h.join().map_err(|_| PipelineError::WorkerPanicError)?
+    });
+    opts.checkpoint.print_resume_hint(&result);
+
+    result
 }
@@ -1,16 +1,22 @@
 use crate::errors::HashFileError;
 use anyhow::Result;
 use clap::{Args, ValueEnum};
-use crossbeam_channel::{Receiver, Sender};
-use hashassin_core::{utils::file_exists, HashAlgorithm, ProgressEvent};
-use indicatif::{ProgressBar, ProgressDrawTarget, ProgressStyle};
+use hashassin_core::{
+    cancel::CancellationToken,
+    encoding::Encoding,
+    format::OutputFormat,
+    output::OutputOptions,
+    pipeline::{CandidateHasher, Pipeline},
+    sink::FileSink,
+    source::FileSource,
+    stats::Stats,
+    utils::{count_lines, file_exists},
+    HashAlgorithm, PipelineError,
+};
 use std::num::NonZeroUsize;
+use std::sync::Arc;
 
-use crate::new_bar_extended;
-
-const PROGRESS_BAR_TEMPLATE: &str =
-    "[{elapsed_precise}] {bar:40.cyan/blue} {pos:>7}/{len:7} {linear_per_sec} [{linear_eta}] {msg}";
-const PROGRESS_BAR_CHARS: &str = "##-";
+use super::{CheckpointOpts, CliCompression, PartitionOpts, ProgressOpts};
 
 #[derive(Args, Debug)]
 pub(crate) struct HashesFromFileOpts {
@@ -26,17 +32,52 @@ pub(crate) struct HashesFromFileOpts {
     #[clap(long)]
     progress: bool,
 
+    #[clap(flatten)]
+    progress_output: ProgressOpts,
+
     /// Number of md5s to read in before sending to a worker thread.
     #[clap(long, default_value = "1000")]
-    chunk_size: usize,
+    chunk_size: NonZeroUsize,
+
+    /// Maximum number of chunks read from the input file but not written to the output file yet.
+    /// Reading waits when hashing or writing cannot keep up, which bounds memory use.
+    #[clap(long, default_value = "16")]
+    max_inflight_chunks: NonZeroUsize,
 
     /// Output file to write md5s to
     #[clap(long)]
     out_path: String,
 
-    /// What hashing algorithm to use.
-    #[clap(long, value_enum)]
-    algorithm: CliAlgorithm,
+    /// How to compress the output file, the input file is decompressed automatically.
+    #[clap(long, value_enum, default_value = "auto")]
+    compress: CliCompression,
+
+    /// How every hash is written to the output file.
+    #[clap(long, value_enum, default_value = "raw")]
+    output_format: CliOutputFormat,
+
+    /// Write hashes as soon as a chunk is done instead of in the order of the input file.
+    /// Faster with many threads, but the output no longer lines up with the input.
+    #[clap(long)]
+    unordered: bool,
+
+    /// What hashing algorithms to use, e.g. `md5,sha2`. Every line is read once and hashed with
+    /// each algorithm into `<out-path>` with the algorithm name added before the extension,
+    /// e.g. `hashes.md5.txt`, or into a single file with one record per line for `jsonl`.
+    #[clap(long, value_enum, value_delimiter = ',', required = true)]
+    algorithm: Vec<CliAlgorithm>,
+
+    /// How passwords are turned into bytes before hashing. `$HEX[...]` lines, and lines that are
+    /// not valid UTF-8, are always hashed as raw bytes.
+    #[clap(long, value_enum, default_value = "utf8")]
+    encoding: CliEncoding,
+
+    /// Lines of the input file to hash, every line is a password.
+    #[clap(flatten)]
+    partition: PartitionOpts,
+
+    #[clap(flatten)]
+    checkpoint: CheckpointOpts,
 }
 
 #[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, ValueEnum, Debug)]
@@ -55,103 +96,168 @@ pub enum CliAlgorithm {
     Blake2s256,
 }
 
-fn update_progress(
-    progress_bar: &ProgressBar,
-    receiver: Option<Receiver<ProgressEvent>>,
-) -> Result<(), HashFileError> {
-    let receiver = match receiver {
-        Some(r) => r,
-        None => return Err(HashFileError::ReceiverError),
-    };
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, ValueEnum, Debug)]
+pub enum CliEncoding {
+    /// UTF-8
+    Utf8,
+
+    /// UTF-16 little endian, used by Windows
+    Utf16le,
+
+    /// ISO-8859-1
+    Latin1,
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, ValueEnum, Debug)]
+pub enum CliOutputFormat {
+    /// Digests back to back, without delimiter
+    Raw,
+
+    /// Hex encoded digest, one per line
+    Hex,
+
+    /// `hash:password`, one per line
+    Potfile,
+
+    /// `hash,password` with a header line
+    Csv,
+
+    /// One JSON object per line, with the algorithm, hash and password
+    Jsonl,
+}
+
+impl From<CliAlgorithm> for HashAlgorithm {
+    fn from(algorithm: CliAlgorithm) -> Self {
+        match algorithm {
+            CliAlgorithm::Md5 => HashAlgorithm::Md5,
+            CliAlgorithm::Sha2 => HashAlgorithm::Sha2,
+            CliAlgorithm::Sha512 => HashAlgorithm::Sha512,
+            CliAlgorithm::Ripemd160 => HashAlgorithm::Ripemd160,
+            CliAlgorithm::Ripemd320 => HashAlgorithm::Ripemd320,
+            CliAlgorithm::Blake2b512 => HashAlgorithm::Blake2b512,
+            CliAlgorithm::Blake2s256 => HashAlgorithm::Blake2s256,
+        }
+    }
+}
+
+impl From<CliEncoding> for Encoding {
+    fn from(encoding: CliEncoding) -> Self {
+        match encoding {
+            CliEncoding::Utf8 => Encoding::Utf8,
+            CliEncoding::Utf16le => Encoding::Utf16Le,
+            CliEncoding::Latin1 => Encoding::Latin1,
+        }
+    }
+}
 
-    while let Ok(event) = receiver.recv() {
-        match event {
-            ProgressEvent::NewInput => progress_bar.inc_length(1),
-            ProgressEvent::InputMd5Hashed => progress_bar.inc(1),
-            ProgressEvent::NewInputs(n) => progress_bar.inc_length(n),
+impl From<CliOutputFormat> for OutputFormat {
+    fn from(format: CliOutputFormat) -> Self {
+        match format {
+            CliOutputFormat::Raw => OutputFormat::Raw,
+            CliOutputFormat::Hex => OutputFormat::Hex,
+            CliOutputFormat::Potfile => OutputFormat::Potfile,
+            CliOutputFormat::Csv => OutputFormat::Csv,
+            CliOutputFormat::Jsonl => OutputFormat::Jsonl,
+        }
+    }
+}
+
+/// Algorithms picked on the command line, in order and without duplicates
+pub(crate) fn unique_algorithms(algorithms: &[CliAlgorithm]) -> Vec<HashAlgorithm> {
+    let mut unique = Vec::new();
+    for &algorithm in algorithms {
+        let algorithm = HashAlgorithm::from(algorithm);
+        if !unique.contains(&algorithm) {
+            unique.push(algorithm);
         }
     }
 
-    Ok(())
+    unique
 }
 
-pub(crate) fn hashes_from_file(opts: &HashesFromFileOpts) -> Result<(), HashFileError> {
+pub(crate) fn hashes_from_file(
+    opts: &HashesFromFileOpts,
+    stats: &Arc<Stats>,
+    cancel: &CancellationToken,
+) -> Result<()> {
     let in_path = opts.in_path.clone();
     let num_threads = opts.threads;
     let chunk_size = opts.chunk_size;
+    let max_inflight_chunks = opts.max_inflight_chunks;
+    let cancel = cancel.clone();
     let out_path = opts.out_path.clone();
 
     if !file_exists(&in_path) {
-        return Err(HashFileError::FileDoesNotExistError(in_path));
+        return Err(HashFileError::FileDoesNotExistError(in_path).into());
     }
 
-    let algorithm = match opts.algorithm {
-        CliAlgorithm::Md5 => HashAlgorithm::Md5,
-        CliAlgorithm::Sha2 => HashAlgorithm::Sha2,
-        CliAlgorithm::Sha512 => HashAlgorithm::Sha512,
-        CliAlgorithm::Ripemd160 => HashAlgorithm::Ripemd160,
-        CliAlgorithm::Ripemd320 => HashAlgorithm::Ripemd320,
-        CliAlgorithm::Blake2b512 => HashAlgorithm::Blake2b512,
-        CliAlgorithm::Blake2s256 => HashAlgorithm::Blake2s256,
+    // counting lines means reading the whole file, so only do it when splitting into parts
+    let keyspace = if opts.partition.needs_keyspace() {
+        Some(count_lines(&in_path)?)
+    } else {
+        None
+    };
+    let range = opts.partition.range(keyspace)?;
+    // the number of lines is only known when the file was counted
+    let total = keyspace
+        .map(|keyspace| range.bounds(keyspace))
+        .and_then(|(start, end)| u64::try_from(end - start).ok());
+
+    let algorithms = unique_algorithms(&opts.algorithm);
+    let encoding = opts.encoding.into();
+
+    let output = OutputOptions {
+        format: opts.output_format.into(),
+        compression: opts.compress.for_path(&out_path),
     };
 
-    let (h, progress_receiver) =
-        if let Some((progress_sender, progress_receiver)) = pb_channel(opts) {
-            let h = std::thread::spawn(move || {
-                hashassin_core::compute_from_file(
-                    &in_path,
-                    &out_path,
-                    num_threads,
-                    Some(progress_sender),
-                    chunk_size,
-                    algorithm,
-                );
-            });
-
-            (h, Some(progress_receiver))
-        } else {
-            let h = std::thread::spawn(move || {
-                hashassin_core::compute_from_file(
-                    &in_path,
-                    &out_path,
-                    num_threads,
-                    None,
-                    chunk_size,
-                    algorithm,
-                );
-            });
-
-            (h, None)
-        };
-
-    if opts.progress {
-        let pb = ProgressBar::new(0);
-        pb.set_style(
-            new_bar_extended()
-                .template(PROGRESS_BAR_TEMPLATE)
-                .unwrap_or(ProgressStyle::default_bar())
-                .progress_chars(PROGRESS_BAR_CHARS),
-        );
-
-        pb.set_draw_target(ProgressDrawTarget::stderr_with_hz(4));
-
-        update_progress(&pb, progress_receiver)?;
-
-        pb.finish();
-    }
+    let job = format!(
+        "hashes-from-file {:?}",
+        (
+            &in_path,
+            &algorithms,
+            encoding,
+            output,
+            opts.unordered,
+            &opts.partition
+        )
+    );
+    let checkpointer = opts
+        .checkpoint
+        .checkpointer(chunk_size.get().try_into()?, &job)?;
 
-    //TODO handle this unwrap
-    h.join().unwrap();
+    // lines before the checkpoint's position are done, the pipeline starts right after it.
+    // chunks past it that were done out of order are skipped, but count as done too.
+    let (position, done_before) = checkpointer.as_ref().map_or((0, 0), |checkpointer| {
+        let checkpoint = checkpointer.checkpoint();
+        (checkpoint.position, checkpoint.done())
+    });
+    let source = FileSource::open(&in_path, range.within(position, None))?;
 
-    Ok(())
-}
+    let pipeline = Pipeline::new(
+        source,
+        CandidateHasher::new(algorithms, encoding),
+        FileSink::new(&out_path, output),
+    )
+    .threads(num_threads)
+    .chunk_size(chunk_size)
+    .max_inflight_chunks(max_inflight_chunks)
+    .ordered(!opts.unordered)
+    // line numbers in errors count from the start of the file
+    .first_index(range.skip + 1)
+    .checkpointer(checkpointer)
+    .stats(stats.clone());
 
-fn pb_channel(
-    opts: &HashesFromFileOpts,
-) -> Option<(Sender<ProgressEvent>, Receiver<ProgressEvent>)> {
-    if opts.progress {
-        Some(crossbeam_channel::unbounded())
-    } else {
-        None
+    let progress = opts.progress_output.progress(opts.progress)?;
+    let done_before = done_before.try_into()?;
+    let h = std::thread::spawn(move || pipeline.run(&cancel));
+
+    if let Some(progress) = progress {
+        progress.show(stats, total, done_before, || h.is_finished());
     }
+
+    let result = h.join().map_err(|_| PipelineError::WorkerPanicError)?;
+    opts.checkpoint.print_resume_hint(&result);
+
+    result
 }
@@ -0,0 +1,63 @@
+use anyhow::{anyhow, Result};
+use clap::Args;
+use hashassin_core::keyspace::KeyspaceRange;
+use std::str::FromStr;
+
+/// Options to only process a slice of the keyspace, e.g. to split work across machines.
+#[derive(Args, Debug)]
+pub(crate) struct PartitionOpts {
+    /// Only process part N of M of the keyspace, e.g. `2/8`.
+    #[clap(long)]
+    part: Option<Part>,
+
+    /// Number of passwords to skip, from the start of the part if `--part` is given.
+    #[clap(long, default_value = "0")]
+    skip: u128,
+
+    /// Maximum number of passwords to process.
+    #[clap(long)]
+    limit: Option<u128>,
+}
+
+impl PartitionOpts {
+    /// Whether the size of the keyspace is needed to compute the range
+    pub(crate) fn needs_keyspace(&self) -> bool {
+        self.part.is_some()
+    }
+
+    /// Range of the keyspace to process, `keyspace` is only used when `--part` is given.
+    pub(crate) fn range(&self, keyspace: Option<u128>) -> Result<KeyspaceRange> {
+        let range = match self.part {
+            Some(Part { part, parts }) => {
+                let keyspace = keyspace
+                    .ok_or_else(|| anyhow!("the keyspace is too large to be split into parts"))?;
+                KeyspaceRange::part(part, parts, keyspace)?
+            }
+            None => KeyspaceRange::default(),
+        };
+
+        Ok(range.within(self.skip, self.limit))
+    }
+}
+
+/// Part N of M, as given on the command line
+#[derive(Copy, Clone, Debug)]
+struct Part {
+    part: u64,
+    parts: u64,
+}
+
+impl FromStr for Part {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (part, parts) = s
+            .split_once('/')
+            .ok_or_else(|| format!("expected N/M, received `{s}`"))?;
+
+        Ok(Part {
+            part: part.trim().parse().map_err(|e| format!("{e}"))?,
+            parts: parts.trim().parse().map_err(|e| format!("{e}"))?,
+        })
+    }
+}
@@ -0,0 +1,57 @@
+use clap::Args;
+use hashassin_core::passwords::CharSet;
+use hashassin_core::policy::Policy;
+
+/// Options to only keep passwords that follow the password policy of the target.
+/// `$HEX[...]` passwords, e.g. of bytes mode, are checked byte by byte.
+#[derive(Args, Debug)]
+pub(crate) struct PolicyOpts {
+    /// Only keep passwords with at least one lower case letter.
+    #[clap(long)]
+    require_lower: bool,
+
+    /// Only keep passwords with at least one upper case letter.
+    #[clap(long)]
+    require_upper: bool,
+
+    /// Only keep passwords with at least one digit.
+    #[clap(long)]
+    require_digit: bool,
+
+    /// Only keep passwords with at least one special character.
+    #[clap(long)]
+    require_special: bool,
+
+    /// Only keep passwords with at least this many characters.
+    #[clap(long)]
+    require_min_chars: Option<usize>,
+
+    /// Only keep passwords with at most this many characters.
+    #[clap(long)]
+    require_max_chars: Option<usize>,
+}
+
+impl PolicyOpts {
+    /// Policy selected on the command line, `None` if every password is allowed
+    pub(crate) fn policy(&self) -> Option<Policy> {
+        let mut required = CharSet::none();
+        for (flag, class) in [
+            (self.require_lower, CharSet::LowerAlpha),
+            (self.require_upper, CharSet::UpperAlpha),
+            (self.require_digit, CharSet::Numeric),
+            (self.require_special, CharSet::Special),
+        ] {
+            if flag {
+                required |= class;
+            }
+        }
+
+        let policy = Policy::new(
+            self.require_min_chars.unwrap_or(0),
+            self.require_max_chars.unwrap_or(usize::MAX),
+            required,
+        );
+
+        (policy != Policy::default()).then_some(policy)
+    }
+}
@@ -0,0 +1,149 @@
+use crate::new_bar_extended;
+use anyhow::{Context, Result};
+use clap::{Args, ValueEnum};
+use hashassin_core::stats::{ProgressReport, Stats, StatsSnapshot};
+use indicatif::{ProgressBar, ProgressDrawTarget, ProgressStyle};
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::time::{Duration, Instant};
+
+const PROGRESS_BAR_TEMPLATE: &str =
+    "[{elapsed_precise}] {bar:40.cyan/blue} {pos:>7}/{len:7} {linear_per_sec} [{linear_eta}] {msg}";
+const PROGRESS_BAR_CHARS: &str = "##-";
+/// How often the progress bar is updated
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(250);
+/// How often a JSON progress line is written
+const JSON_PROGRESS_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Options to show the progress of a job to people or to scripts.
+#[derive(Args, Debug)]
+pub(crate) struct ProgressOpts {
+    /// How progress is shown, setting it shows progress even without `--progress`.
+    #[clap(long, value_enum)]
+    progress_format: Option<ProgressFormat>,
+
+    /// File descriptor to write JSON progress to instead of stderr, e.g. `3` with
+    /// `3>progress.jsonl`. The progress bar is still drawn on stderr with `--progress`.
+    #[clap(long)]
+    progress_fd: Option<u32>,
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, ValueEnum, Debug)]
+pub enum ProgressFormat {
+    /// A progress bar on stderr
+    Bar,
+
+    /// One JSON object per second with the timestamp, processed and total candidates, rate, ETA
+    /// and matches
+    Json,
+}
+
+/// Where the progress of a job is shown, a progress bar on stderr and JSON written to a file
+/// descriptor can be shown at the same time
+pub(crate) struct Progress {
+    bar: Option<ProgressBar>,
+    json: Option<Box<dyn Write + Send>>,
+}
+
+impl ProgressOpts {
+    /// How to show progress, `None` if progress is not shown.
+    /// `progress` tells if progress was asked for by the command's own flag.
+    pub(crate) fn progress(&self, progress: bool) -> Result<Option<Progress>> {
+        let json: Option<Box<dyn Write + Send>> = match (self.progress_format, self.progress_fd) {
+            (_, Some(fd)) => {
+                // the descriptor is opened again rather than adopted, which needs no unsafe code
+                let out = OpenOptions::new()
+                    .append(true)
+                    .open(format!("/dev/fd/{fd}"))
+                    .with_context(|| format!("Cannot write progress to file descriptor {fd}"))?;
+                Some(Box::new(out))
+            }
+            (Some(ProgressFormat::Json), None) => Some(Box::new(std::io::stderr())),
+            _ => None,
+        };
+
+        // the bar is not drawn over JSON written to stderr
+        let bar = match self.progress_format {
+            Some(ProgressFormat::Bar) => true,
+            Some(ProgressFormat::Json) => progress && self.progress_fd.is_some(),
+            None => progress,
+        };
+        let bar = bar.then(new_progress_bar);
+
+        if bar.is_none() && json.is_none() {
+            return Ok(None);
+        }
+
+        Ok(Some(Progress { bar, json }))
+    }
+}
+
+impl Progress {
+    /// Shows the progress of a job until `done` returns true
+    /// Requires:
+    /// - stats: Counters of the job
+    /// - total: Candidates of the whole job, if known
+    /// - done_before: Candidates processed before the job was resumed from a checkpoint,
+    ///   including chunks that were completed out of order
+    /// - done: Tells if the job stopped
+    pub(crate) fn show(
+        self,
+        stats: &Stats,
+        total: Option<u64>,
+        done_before: u64,
+        done: impl Fn() -> bool,
+    ) {
+        let Progress { bar, mut json } = self;
+        let interval = match bar {
+            Some(_) => PROGRESS_INTERVAL,
+            None => JSON_PROGRESS_INTERVAL,
+        };
+        let start = Instant::now();
+        let mut last_json: Option<Instant> = None;
+
+        stats.watch(interval, &done, |snapshot| {
+            if let Some(progress_bar) = &bar {
+                progress_bar.set_length(total.unwrap_or(done_before + snapshot.generated));
+                progress_bar.set_position(done_before + snapshot.written + snapshot.skipped);
+                progress_bar.set_message(queue_depth_message(snapshot));
+            }
+
+            // the bar is updated more often, JSON is still written once per interval and when
+            // the job stopped
+            let json_due = last_json.is_none_or(|last| last.elapsed() >= JSON_PROGRESS_INTERVAL);
+            if let Some(out) = json.as_mut().filter(|_| json_due || done()) {
+                let report = ProgressReport::new(snapshot, start.elapsed(), total, done_before);
+                let line = serde_json::to_string(&report).expect("a report is always valid JSON");
+
+                // whoever reads the progress going away must not stop the job
+                let _ = writeln!(out, "{line}");
+                last_json = Some(Instant::now());
+            }
+        });
+
+        if let Some(progress_bar) = bar {
+            progress_bar.finish();
+        }
+    }
+}
+
+fn new_progress_bar() -> ProgressBar {
+    let pb = ProgressBar::new(0);
+    pb.set_style(
+        new_bar_extended()
+            .template(PROGRESS_BAR_TEMPLATE)
+            .unwrap_or(ProgressStyle::default_bar())
+            .progress_chars(PROGRESS_BAR_CHARS),
+    );
+    pb.set_draw_target(ProgressDrawTarget::stderr_with_hz(4));
+
+    pb
+}
+
+/// Progress bar message showing how full the pipeline is
+fn queue_depth_message(snapshot: &StatsSnapshot) -> String {
+    format!(
+        "chunks queued: {}, in flight: {}",
+        snapshot.queued, snapshot.in_flight
+    )
+}
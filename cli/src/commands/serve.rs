@@ -0,0 +1,112 @@
+use crate::{accept, http};
+use anyhow::{Context, Result};
+use clap::Args;
+use hashassin_core::{
+    api::Api,
+    cancel::CancellationToken,
+    jobs::Jobs,
+    keyspace::KeyspaceRange,
+    pipeline::{CandidateHasher, Pipeline},
+    server::Server,
+    sink::TableSink,
+    source::FileSource,
+    stats::Stats,
+    table::Table,
+};
+use std::io::{BufReader, BufWriter};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::num::NonZeroUsize;
+use std::sync::Arc;
+
+use super::{unique_algorithms, CliAlgorithm, CliEncoding};
+
+#[derive(Args, Debug)]
+pub(crate) struct ServeOpts {
+    /// Address to listen on.
+    #[clap(long, default_value = "127.0.0.1:7878")]
+    addr: SocketAddr,
+
+    /// Wordlist to hash before listening, `LOOKUP` finds the passwords of its hashes.
+    /// Can be given several times.
+    #[clap(long)]
+    wordlist: Vec<String>,
+
+    /// What hashing algorithms the wordlists are hashed with, e.g. `md5,sha2`.
+    #[clap(long, value_enum, value_delimiter = ',', default_value = "md5")]
+    algorithm: Vec<CliAlgorithm>,
+
+    /// How wordlist lines are turned into bytes before hashing, `HASH` requests are always UTF-8.
+    #[clap(long, value_enum, default_value = "utf8")]
+    encoding: CliEncoding,
+
+    /// Number of threads to use to hash the wordlists, and every job submitted with `--http`
+    #[clap(long, default_value = "4")]
+    threads: NonZeroUsize,
+
+    /// Answer JSON requests over HTTP instead of the line protocol, see
+    /// [`hashassin_core::api`]. Jobs read and write files on this machine with the rights of this
+    /// process, only listen where trusted clients can connect.
+    #[clap(long)]
+    http: bool,
+}
+
+/// Answers `HASH`, `LOOKUP` and `STATS` requests, one per line, or JSON requests over HTTP with
+/// `--http`, until Ctrl-C is pressed. See [`hashassin_core::server`] and [`hashassin_core::api`]
+/// for the protocols.
+pub(crate) fn serve(
+    opts: &ServeOpts,
+    stats: &Arc<Stats>,
+    cancel: &CancellationToken,
+) -> Result<()> {
+    let algorithms = unique_algorithms(&opts.algorithm);
+
+    let mut table = Table::new();
+    for wordlist in &opts.wordlist {
+        let source = FileSource::open(wordlist, KeyspaceRange::new(0, None))?;
+        let hasher = CandidateHasher::new(algorithms.clone(), opts.encoding.into());
+        let wordlist_table = Pipeline::new(source, hasher, TableSink::new())
+            .threads(opts.threads)
+            .stats(stats.clone())
+            .run(cancel)?;
+
+        table.merge(wordlist_table);
+    }
+
+    let listener =
+        TcpListener::bind(opts.addr).with_context(|| format!("Cannot listen on {}", opts.addr))?;
+    eprintln!("Serving {} hashes on {}", table.len(), opts.addr);
+
+    let server = Server::new(table);
+    if opts.http {
+        let api = Arc::new(Api::new(server, Jobs::new(opts.threads)));
+        let handler_api = api.clone();
+        accept(listener, move |stream| {
+            handle_request(&handler_api, &stream)
+        });
+
+        cancel.wait();
+        // running jobs write what they hashed so far and close their output files
+        api.shutdown();
+    } else {
+        let server = Arc::new(server);
+        accept(listener, move |stream| handle_connection(&server, stream));
+
+        cancel.wait();
+    }
+
+    // connections that are still open are dropped when the process exits
+    Ok(())
+}
+
+fn handle_connection(server: &Server, stream: TcpStream) -> Result<()> {
+    let reader = BufReader::new(stream.try_clone()?);
+
+    server.handle_connection(reader, BufWriter::new(stream))
+}
+
+fn handle_request(api: &Api, stream: &TcpStream) -> Result<()> {
+    let request = http::read_request(stream)?;
+    let response = api.handle(&request.method, &request.path, &request.body);
+
+    http::write_response(stream, response.status, "application/json", &response.body)
+}
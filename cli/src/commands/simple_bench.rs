@@ -1,15 +1,20 @@
 use anyhow::{anyhow, Result};
 use clap::Args;
 use hashassin_core::{
+    cancel::CancellationToken,
+    encoding::Encoding,
+    keyspace::SeekableGenerator,
     passwords::{CharSet, PasswordGenerator},
-    ProgressEvent, HashAlgorithm,
+    pipeline::{CandidateHasher, Executor, Pipeline},
+    sink::DiscardSink,
+    source::{GeneratorSource, VecSource},
+    stats::Stats,
+    PipelineError, GENERATOR_CHUNK_SIZE,
 };
-use indicatif::{ProgressBar, ProgressDrawTarget};
 use std::num::NonZeroUsize;
+use std::sync::Arc;
 
-use crate::new_bar_extended;
-
-use super::CliAlgorithm;
+use super::{CheckpointOpts, CliAlgorithm, PartitionOpts, ProgressOpts};
 
 #[derive(Args, Debug)]
 pub(crate) struct SimpleBenchOpts {
@@ -17,19 +22,24 @@ pub(crate) struct SimpleBenchOpts {
     // #[clap(long)]
     // input: String,
     /// Comma separated list of inputs.
-    #[clap(long, env = "INPUTS", use_value_delimiter = true)]
+    #[clap(long, env = "INPUTS", use_value_delimiter = true, conflicts_with = "checkpoint")]
     inputs: Option<Vec<String>>,
 
     /// The number of threads to use for computing hashes
     #[clap(long, default_value = "4", env = "NUM_THREADS")]
     threads: NonZeroUsize,
 
+    /// Maximum number of chunks of passwords waiting for or being hashed by the worker threads.
+    /// Generating passwords waits when hashing cannot keep up.
+    #[clap(long, default_value = "16")]
+    max_inflight_chunks: NonZeroUsize,
+
     /// Should we collect() results from the password generator instead of using it as an iterator?
-    #[clap(long)]
+    #[clap(long, conflicts_with = "checkpoint")]
     collect: bool,
 
     /// Use rayon
-    #[clap(long, conflicts_with = "collect")]
+    #[clap(long, conflicts_with_all = ["collect", "checkpoint"])]
     rayon: bool,
 
     /// Minimum password length
@@ -43,39 +53,48 @@ pub(crate) struct SimpleBenchOpts {
     /// What hashing algorithm to use.
     #[clap(long, value_enum, default_value = "md5")]
     algorithm: CliAlgorithm,
-}
 
-pub(crate) fn simple_bench(opts: &SimpleBenchOpts) -> Result<()> {
-    let num_threads = opts.threads;
+    #[clap(flatten)]
+    partition: PartitionOpts,
 
-    let (tx, progress_receiver) = crossbeam_channel::unbounded();
+    /// Only supported when hashing passwords from the generator with threads.
+    #[clap(flatten)]
+    checkpoint: CheckpointOpts,
 
-    let mut handles = Vec::new();
+    /// A progress bar is drawn unless another format is picked.
+    #[clap(flatten)]
+    progress_output: ProgressOpts,
+}
 
-    // we will fill the progress bar as data comes in.
-    let pb = ProgressBar::new(0);
-    // ProgressBar::new(0);
+pub(crate) fn simple_bench(
+    opts: &SimpleBenchOpts,
+    stats: &Arc<Stats>,
+    cancel: &CancellationToken,
+) -> Result<()> {
+    let num_threads = opts.threads;
+    let max_inflight_chunks = opts.max_inflight_chunks;
+    let cancel = cancel.clone();
+    let progress = opts.progress_output.progress(true)?;
 
-    let algorithm = match opts.algorithm {
-        CliAlgorithm::Md5 => HashAlgorithm::Md5,
-        CliAlgorithm::Sha2 => HashAlgorithm::Sha2,
-        CliAlgorithm::Sha512 => HashAlgorithm::Sha512,
-        CliAlgorithm::Ripemd160 => HashAlgorithm::Ripemd160,
-        CliAlgorithm::Ripemd320 => HashAlgorithm::Ripemd320,
-        CliAlgorithm::Blake2b512 => HashAlgorithm::Blake2b512,
-        CliAlgorithm::Blake2s256 => HashAlgorithm::Blake2s256,
-    };
+    let hasher = CandidateHasher::new(vec![opts.algorithm.into()], Encoding::Utf8);
 
-    // the inputs were passed via cli
-    if opts.inputs.is_some() {
+    // the inputs were passed via cli, `total` is the number of passwords if it is known before
+    // hashing them and `done_before` the number hashed before resuming from a checkpoint
+    let (pipeline, total, done_before) = if opts.inputs.is_some() {
         let passwords = opts.inputs.clone().unwrap();
-        // pb = ProgressBar::new(passwords.len().try_into()?);
-
-        let h = std::thread::spawn(move || {
-            hashassin_core::compute_with_threads(passwords, num_threads, tx,algorithm);
-        });
-
-        handles.push(h);
+        let (start, end) = opts
+            .partition
+            .range(Some(passwords.len() as u128))?
+            .bounds(passwords.len() as u128);
+        let passwords = passwords[start as usize..end as usize].to_vec();
+        let total = passwords.len().try_into()?;
+
+        // there are only a few inputs, every one of them is a chunk so that they are spread
+        // over the threads
+        let pipeline = Pipeline::new(VecSource::new(passwords), hasher, DiscardSink::new())
+            .chunk_size(NonZeroUsize::MIN);
+
+        (pipeline, Some(total), 0)
     } else {
         // we will be using our generator
         let generator = PasswordGenerator::new(
@@ -85,54 +104,70 @@ pub(crate) fn simple_bench(opts: &SimpleBenchOpts) -> Result<()> {
         )
         .map_err(|_| anyhow!("error when creating new password generator"))?;
 
+        let keyspace = generator.keyspace();
+        let range = opts.partition.range(keyspace)?;
+        let job = format!(
+            "simple-bench {:?}",
+            (opts.min_length, opts.max_length, opts.algorithm, &opts.partition)
+        );
+        let checkpointer = opts
+            .checkpoint
+            .checkpointer(GENERATOR_CHUNK_SIZE.try_into()?, &job)?;
+
+        // everything before the checkpoint's position is already hashed
+        let (generator_range, done) = match &checkpointer {
+            Some(checkpointer) => {
+                let checkpoint = checkpointer.checkpoint();
+                (
+                    range.within(checkpoint.position, None),
+                    checkpoint.done(),
+                )
+            }
+            None => (range, 0),
+        };
+        let generator = generator.in_range(generator_range);
+
+        // if there are more passwords than fit in a u64, we will fill the
+        // progress bar as data comes in instead.
+        let total = keyspace
+            .map(|keyspace| range.bounds(keyspace))
+            .and_then(|(start, end)| u64::try_from(end - start).ok());
+
         // we have an option to `collect()` things from the generator.
         // this will (theoretically) allow us to isolate any performance
         // issues related to the generator itself.
-        let h = if opts.collect {
+        let pipeline = if opts.collect {
             let passwords = generator.collect::<Vec<_>>();
-            std::thread::spawn(move || {
-                hashassin_core::compute_with_threads(passwords, num_threads, tx,algorithm);
-            })
-        } else if opts.rayon {
-            std::thread::spawn(move || {
-                hashassin_core::compute_with_rayon(generator, num_threads, tx,algorithm);
-            })
+            Pipeline::new(VecSource::new(passwords), hasher, DiscardSink::new())
         } else {
-            std::thread::spawn(move || {
-                hashassin_core::compute_with_threads_with_password_generator(
-                    generator,
-                    num_threads,
-                    tx,
-                    algorithm
-                );
-            })
+            Pipeline::new(GeneratorSource::new(generator), hasher, DiscardSink::new())
+        };
+        let executor = if opts.rayon {
+            Executor::Rayon
+        } else {
+            Executor::ThreadPool
         };
 
-        handles.push(h);
-    }
+        let pipeline = pipeline
+            .executor(executor)
+            .chunk_size(GENERATOR_CHUNK_SIZE.try_into()?)
+            .checkpointer(checkpointer);
 
-    pb.set_style(new_bar_extended().template(
-            "[{elapsed_precise}] {bar:40.cyan/blue} {pos:>7}/{len:7} {linear_per_sec} [{linear_eta}] {msg}"
-        )
-        .unwrap()
-        .progress_chars("##-"),
-    );
-
-    pb.set_draw_target(ProgressDrawTarget::stderr_with_hz(4));
-
-    while let Ok(event) = progress_receiver.recv() {
-        match event {
-            ProgressEvent::NewInput => pb.inc_length(1),
-            ProgressEvent::InputMd5Hashed => pb.inc(1),
-            ProgressEvent::NewInputs(n) => pb.inc_length(n),
-        }
-    }
+        (pipeline, total, done.try_into()?)
+    };
+
+    let pipeline = pipeline
+        .threads(num_threads)
+        .max_inflight_chunks(max_inflight_chunks)
+        .stats(stats.clone());
+    let h = std::thread::spawn(move || pipeline.run(&cancel));
 
-    for handle in handles {
-        handle.join().unwrap();
+    if let Some(progress) = progress {
+        progress.show(stats, total, done_before, || h.is_finished());
     }
 
-    pb.finish();
+    let result = h.join().map_err(|_| PipelineError::WorkerPanicError)?;
+    opts.checkpoint.print_resume_hint(&result);
 
-    Ok(())
+    result
 }
@@ -0,0 +1,24 @@
+use anyhow::Result;
+use clap::Args;
+use hashassin_core::markov::MarkovStats;
+use hashassin_core::utils::read_lines;
+
+#[derive(Args, Debug)]
+pub(crate) struct TrainMarkovOpts {
+    /// Path to the wordlist to collect statistics from.
+    #[clap(long)]
+    wordlist: String,
+
+    /// Path to file to write the statistics to.
+    #[clap(long)]
+    out_path: String,
+}
+
+pub(crate) fn train_markov(opts: &TrainMarkovOpts) -> Result<()> {
+    let words = read_lines(&opts.wordlist)?;
+    let stats = MarkovStats::train(words);
+
+    stats.save(&opts.out_path)?;
+
+    Ok(())
+}
@@ -1,9 +1,25 @@
+// every error enum names its variants `...Error`
+#![allow(clippy::enum_variant_names)]
+
 use thiserror::Error;
 
 #[derive(Error, Debug)]
 pub enum HashFileError {
     #[error("The file does not exist in the given path: {0}")]
     FileDoesNotExistError(String),
-    #[error("Receiver error")]
-    ReceiverError,
+}
+
+#[derive(Error, Debug)]
+pub enum HttpError {
+    #[error("Malformed HTTP request: `{0}`")]
+    MalformedRequestError(String),
+
+    #[error("The request body is too large: {0} bytes")]
+    BodyTooLargeError(usize),
+
+    #[error("The request line and headers are larger than {0} bytes")]
+    HeadTooLargeError(usize),
+
+    #[error("The request has more than {0} headers")]
+    TooManyHeadersError(usize),
 }
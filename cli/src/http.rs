@@ -0,0 +1,138 @@
+//! Just enough HTTP/1.1 for the metrics endpoint and `serve --http`, one request per connection.
+
+use crate::errors::HttpError;
+use anyhow::Result;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::TcpStream;
+use std::time::{Duration, Instant};
+
+/// How long a whole request may take to arrive, so that a stuck or trickling client does not
+/// hold on to its connection
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Largest request line and headers read, together
+const MAX_HEAD_LEN: usize = 16 * 1024;
+
+/// Most headers read, a request only needs a few
+const MAX_HEADERS: usize = 64;
+
+/// Largest request body read, jobs and lookups are far smaller
+const MAX_BODY_LEN: usize = 1024 * 1024;
+
+pub(crate) struct Request {
+    pub(crate) method: String,
+    pub(crate) path: String,
+    pub(crate) body: Vec<u8>,
+}
+
+/// Reads the request line, the headers and the body of the request, if it has a `Content-Length`
+pub(crate) fn read_request(stream: &TcpStream) -> Result<Request> {
+    let mut reader = BufReader::new(DeadlineReader {
+        stream,
+        deadline: Instant::now() + REQUEST_TIMEOUT,
+    });
+    let mut head_left = MAX_HEAD_LEN;
+
+    let request_line = read_head_line(&mut reader, &mut head_left)?;
+    let (method, path) = match request_line.split_whitespace().collect::<Vec<_>>()[..] {
+        [method, path, _version] => (method.to_owned(), path.to_owned()),
+        _ => return Err(HttpError::MalformedRequestError(request_line.trim().to_owned()).into()),
+    };
+
+    // closing the connection before reading the headers resets it, even when they are not needed
+    let mut content_length = 0;
+    let mut headers = 0;
+    loop {
+        let header = read_head_line(&mut reader, &mut head_left)?;
+        if header.trim().is_empty() {
+            break;
+        }
+
+        headers += 1;
+        if headers > MAX_HEADERS {
+            return Err(HttpError::TooManyHeadersError(MAX_HEADERS).into());
+        }
+
+        if let Some((name, value)) = header.split_once(':') {
+            if name.trim().eq_ignore_ascii_case("content-length") {
+                content_length = value
+                    .trim()
+                    .parse()
+                    .map_err(|_| HttpError::MalformedRequestError(header.trim().to_owned()))?;
+            }
+        }
+    }
+
+    if content_length > MAX_BODY_LEN {
+        return Err(HttpError::BodyTooLargeError(content_length).into());
+    }
+    let mut body = vec![0; content_length];
+    reader.read_exact(&mut body)?;
+
+    Ok(Request { method, path, body })
+}
+
+/// Reads a line of the request line or headers, `head_left` is how many bytes of them are left
+/// before they are larger than [`MAX_HEAD_LEN`]
+fn read_head_line(reader: &mut impl BufRead, head_left: &mut usize) -> Result<String> {
+    let mut line = String::new();
+    let read = reader.take(*head_left as u64).read_line(&mut line)?;
+    *head_left -= read;
+
+    if *head_left == 0 && !line.ends_with('\n') {
+        return Err(HttpError::HeadTooLargeError(MAX_HEAD_LEN).into());
+    }
+
+    Ok(line)
+}
+
+/// Reads from a stream until a deadline, however slowly the bytes arrive
+struct DeadlineReader<'a> {
+    stream: &'a TcpStream,
+    deadline: Instant,
+}
+
+impl Read for DeadlineReader<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let left = self.deadline.saturating_duration_since(Instant::now());
+        if left.is_zero() {
+            return Err(std::io::ErrorKind::TimedOut.into());
+        }
+
+        self.stream.set_read_timeout(Some(left))?;
+        let mut stream = self.stream;
+        stream.read(buf)
+    }
+}
+
+/// Writes a whole response, the connection is closed afterwards
+pub(crate) fn write_response(
+    mut stream: &TcpStream,
+    status: u16,
+    content_type: &str,
+    body: &str,
+) -> Result<()> {
+    write!(
+        stream,
+        "HTTP/1.1 {status} {}\r\n\
+         Content-Type: {content_type}\r\n\
+         Content-Length: {}\r\n\
+         Connection: close\r\n\r\n{body}",
+        reason_phrase(status),
+        body.len()
+    )?;
+    stream.flush()?;
+
+    Ok(())
+}
+
+fn reason_phrase(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        201 => "Created",
+        400 => "Bad Request",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        _ => "Internal Server Error",
+    }
+}
@@ -1,19 +1,36 @@
 //! A command line interface for various gigafizz related operations.
 
-use crate::commands::{gen_passwords, hashes_from_file, simple_bench};
-use anyhow::Result;
+use crate::commands::{
+    coordinator, gen_passwords, hashes_from_file, serve, simple_bench, train_markov, worker,
+};
+use anyhow::{Context, Result};
 use clap::{Parser, Subcommand};
-use commands::{GenPasswordsOpts, HashesFromFileOpts, SimpleBenchOpts};
+use commands::{
+    CoordinatorOpts, GenPasswordsOpts, HashesFromFileOpts, ServeOpts, SimpleBenchOpts,
+    TrainMarkovOpts, WorkerOpts,
+};
 use dotenvy::dotenv;
+use hashassin_core::cancel::CancellationToken;
+use hashassin_core::stats::Stats;
+use hashassin_core::{db, metrics, PipelineError};
 use indicatif::{FormattedDuration, HumanBytes, HumanFloatCount, ProgressState, ProgressStyle};
-use tracing::trace;
-use std::net::{TcpListener, TcpStream};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Instant;
+use tracing::{trace, warn};
 mod commands;
 mod errors;
+mod http;
 
 #[derive(Parser, Debug)]
 #[clap(version)]
 struct Opts {
+    /// Address to serve the counters of the job on in the OpenMetrics text format, at `/metrics`,
+    /// e.g. `127.0.0.1:9100`.
+    #[clap(long, global = true)]
+    metrics_addr: Option<SocketAddr>,
+
     #[clap(subcommand)]
     command: Command,
 }
@@ -28,6 +45,18 @@ enum Command {
 
     /// Generate hashes from passwords in a file
     HashesFromFile(HashesFromFileOpts),
+
+    /// Collect markov statistics from a wordlist for the markov generation mode
+    TrainMarkov(TrainMarkovOpts),
+
+    /// Answer hashing and lookup requests from other tools over TCP
+    Serve(ServeOpts),
+
+    /// Hand out the keyspace of a brute-force job to workers over TCP and write their hashes
+    Coordinator(CoordinatorOpts),
+
+    /// Hash the work units handed out by a coordinator
+    Worker(WorkerOpts),
 }
 
 fn main() -> Result<()> {
@@ -41,21 +70,116 @@ fn main() -> Result<()> {
 
     trace!("opts: {:?}", opts);
 
-    match opts.command {
-        Command::SimpleBench(opts) => simple_bench(&opts)?,
-        Command::GenPasswords(opts) => gen_passwords(&opts)?,
-        Command::HashesFromFile(opts) => hashes_from_file(&opts)?,
+    // the first Ctrl-C stops the job cleanly, the second one right away. commands that do not
+    // take a cancellation token have nothing to stop cleanly, so they exit on the first one.
+    let cancel = CancellationToken::new();
+    let handler = cancel.clone();
+    let cancellable = !matches!(opts.command, Command::TrainMarkov(_));
+    ctrlc::set_handler(move || {
+        if !cancellable || handler.is_cancelled() {
+            std::process::exit(INTERRUPTED_EXIT_CODE);
+        }
+
+        eprintln!("Stopping, press Ctrl-C again to exit right away");
+        handler.cancel();
+    })?;
+
+    let stats = Arc::new(Stats::new());
+    if let Some(addr) = opts.metrics_addr {
+        serve_metrics(addr, stats.clone())?;
     }
 
-    Ok(())
+    let result = match opts.command {
+        Command::SimpleBench(opts) => simple_bench(&opts, &stats, &cancel),
+        Command::GenPasswords(opts) => gen_passwords(&opts, &stats, &cancel),
+        Command::HashesFromFile(opts) => hashes_from_file(&opts, &stats, &cancel),
+        Command::TrainMarkov(opts) => train_markov(&opts),
+        Command::Serve(opts) => serve(&opts, &stats, &cancel),
+        Command::Coordinator(opts) => coordinator(&opts, &stats, &cancel),
+        Command::Worker(opts) => worker(&opts, &stats, &cancel),
+    };
+
+    if let Some(PipelineError::CancelledError(done)) =
+        result.as_ref().err().and_then(|e| e.downcast_ref())
+    {
+        eprintln!("Interrupted after {done} passwords, everything done so far was written");
+        std::process::exit(INTERRUPTED_EXIT_CODE);
+    }
+
+    result
 }
 
+/// Exit code of a job stopped with Ctrl-C, the usual 128 + SIGINT
+const INTERRUPTED_EXIT_CODE: i32 = 130;
+
+/// Serves the counters of the job on `addr` from a background thread, until the job is done
+fn serve_metrics(addr: SocketAddr, stats: Arc<Stats>) -> Result<()> {
+    let listener =
+        TcpListener::bind(addr).with_context(|| format!("Cannot serve metrics on {addr}"))?;
+    let started = Instant::now();
+
+    // a failed request must not stop the job, nor the next requests
+    accept(listener, move |stream| handle_connection(&stream, &stats, started));
 
-fn handle_connection(stream: &mut TcpStream) -> std::io::Result<()> {
-    // TODO: Implement handling of incoming requests
     Ok(())
 }
 
+/// Most connections [`accept`] answers at once, further ones are closed right away
+const MAX_CONNECTIONS: usize = 64;
+
+/// Accepts connections from a background thread, and answers each one with `handle` in a
+/// thread of its own, so that a slow client does not block others
+pub(crate) fn accept<F>(listener: TcpListener, handle: F)
+where
+    F: Fn(TcpStream) -> Result<()> + Clone + Send + 'static,
+{
+    let open = Arc::new(AtomicUsize::new(0));
+
+    std::thread::spawn(move || {
+        for stream in listener.incoming() {
+            let stream = match stream {
+                Ok(stream) => stream,
+                Err(e) => {
+                    warn!("cannot accept connection: {e}");
+                    continue;
+                }
+            };
+
+            // every connection takes a thread, a client must not be able to start any number
+            if open.fetch_add(1, Ordering::Relaxed) >= MAX_CONNECTIONS {
+                open.fetch_sub(1, Ordering::Relaxed);
+                warn!("more than {MAX_CONNECTIONS} connections, closing the new one");
+                continue;
+            }
+
+            let handle = handle.clone();
+            let open = open.clone();
+            std::thread::spawn(move || {
+                if let Err(e) = handle(stream) {
+                    warn!("connection failed: {e:#}");
+                }
+                open.fetch_sub(1, Ordering::Relaxed);
+            });
+        }
+    });
+}
+
+/// Answers a single HTTP request, with the counters of the job for `GET /metrics`
+fn handle_connection(stream: &TcpStream, stats: &Stats, started: Instant) -> Result<()> {
+    let request = http::read_request(stream)?;
+
+    match (request.method.as_str(), request.path.as_str()) {
+        ("GET", "/metrics") => http::write_response(
+            stream,
+            200,
+            metrics::CONTENT_TYPE,
+            &metrics::render(&stats.snapshot(), &db::cache_stats(), started.elapsed()),
+        ),
+        ("GET", _) => http::write_response(stream, 404, "text/plain", "Not found\n"),
+        _ => http::write_response(stream, 405, "text/plain", "Method not allowed\n"),
+    }
+}
+
 /// Allow for [`ProgressBar`] to output linear estimates instead of just the past N steps.
 /// This is relevant for pbs that we don't know the length before hand or that there are
 /// a bunch of individual steps.
@@ -52,70 +52,56 @@ pub fn bench_iter_batched(c: &mut Criterion) {
     for pw in ["1", "10", "100", "1000", "10000", "100000"] {
         group.bench_with_input(BenchmarkId::new("md5", pw.len()), &pw, |b, &pw| {
             b.iter_batched(
-                || pw.clone(),
-                |pw| {
-                    hash_input::<Md5>(hashassin_core::HashAlgorithm::Md5,black_box(pw));
-                },
+                || pw,
+                |pw| hash_input::<Md5>(hashassin_core::HashAlgorithm::Md5,black_box(pw)),
                 BatchSize::SmallInput,
             );
         });
 
         group.bench_with_input(BenchmarkId::new("sha256", pw.len()), &pw, |b, &pw| {
             b.iter_batched(
-                || pw.clone(),
-                |pw| {
-                    hash_input::<Sha256>(hashassin_core::HashAlgorithm::Sha2,black_box(pw));
-                },
+                || pw,
+                |pw| hash_input::<Sha256>(hashassin_core::HashAlgorithm::Sha2,black_box(pw)),
                 BatchSize::SmallInput,
             );
         });
 
         group.bench_with_input(BenchmarkId::new("Sha512", pw.len()), &pw, |b, &pw| {
             b.iter_batched(
-                || pw.clone(),
-                |pw| {
-                    hash_input::<Sha512>(hashassin_core::HashAlgorithm::Sha512,black_box(pw));
-                },
+                || pw,
+                |pw| hash_input::<Sha512>(hashassin_core::HashAlgorithm::Sha512,black_box(pw)),
                 BatchSize::SmallInput,
             );
         });
 
         group.bench_with_input(BenchmarkId::new("Ripemd160", pw.len()), &pw, |b, &pw| {
             b.iter_batched(
-                || pw.clone(),
-                |pw| {
-                    hash_input::<Ripemd160>(hashassin_core::HashAlgorithm::Ripemd160,black_box(pw));
-                },
+                || pw,
+                |pw| hash_input::<Ripemd160>(hashassin_core::HashAlgorithm::Ripemd160,black_box(pw)),
                 BatchSize::SmallInput,
             );
         });
 
         group.bench_with_input(BenchmarkId::new("Ripemd320", pw.len()), &pw, |b, &pw| {
             b.iter_batched(
-                || pw.clone(),
-                |pw| {
-                    hash_input::<Ripemd320>(hashassin_core::HashAlgorithm::Ripemd320,black_box(pw));
-                },
+                || pw,
+                |pw| hash_input::<Ripemd320>(hashassin_core::HashAlgorithm::Ripemd320,black_box(pw)),
                 BatchSize::SmallInput,
             );
         });
 
         group.bench_with_input(BenchmarkId::new("Blake2b512", pw.len()), &pw, |b, &pw| {
             b.iter_batched(
-                || pw.clone(),
-                |pw| {
-                    hash_input::<Blake2b512>(hashassin_core::HashAlgorithm::Blake2b512,black_box(pw));
-                },
+                || pw,
+                |pw| hash_input::<Blake2b512>(hashassin_core::HashAlgorithm::Blake2b512,black_box(pw)),
                 BatchSize::SmallInput,
             );
         });
 
         group.bench_with_input(BenchmarkId::new("Blake2s256", pw.len()), &pw, |b, &pw| {
             b.iter_batched(
-                || pw.clone(),
-                |pw| {
-                    hash_input::<Blake2s256>(hashassin_core::HashAlgorithm::Blake2s256,black_box(pw));
-                },
+                || pw,
+                |pw| hash_input::<Blake2s256>(hashassin_core::HashAlgorithm::Blake2s256,black_box(pw)),
                 BatchSize::SmallInput,
             );
         });
@@ -0,0 +1,212 @@
+//! Stopping every thread of a hashing pipeline as soon as one of them fails, or only its reader
+//! when it is cancelled.
+use crate::cancel::CancellationToken;
+use crate::errors::PipelineError;
+use anyhow::Result;
+use crossbeam_channel::{never, select, Receiver, Sender};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+
+/// Shared by the threads of a pipeline. The first thread to fail records its error, every other
+/// thread stops at the next chunk. Errors of threads that fail because another one stopped,
+/// e.g. a closed channel, are dropped so the caller gets the error that caused it.
+///
+/// When the caller's [`CancellationToken`] is cancelled, only the reader stops. Chunks that were
+/// read already are still hashed and written, so that everything done so far ends up in the output.
+///
+/// Threads blocked on a channel are woken up too when they use [`Abort::send`] and
+/// [`Abort::recv`], as a bounded channel may never have room again once a thread stopped.
+pub(crate) struct Abort {
+    /// Cancelled when a thread fails
+    failed: CancellationToken,
+    /// Cancelled by the caller
+    cancel: CancellationToken,
+    error: Mutex<Option<anyhow::Error>>,
+    /// Number of passwords done, reported when the pipeline is cancelled
+    done: AtomicU64,
+}
+
+impl Abort {
+    pub(crate) fn new(cancel: &CancellationToken) -> Arc<Self> {
+        Arc::new(Self {
+            failed: CancellationToken::new(),
+            cancel: cancel.clone(),
+            error: Mutex::new(None),
+            done: AtomicU64::new(0),
+        })
+    }
+
+    /// Whether a thread failed or the pipeline was cancelled, and no more chunks should be read
+    pub(crate) fn is_aborted(&self) -> bool {
+        self.failed.is_cancelled() || self.cancel.is_cancelled()
+    }
+
+    /// Whether a thread failed, and every thread should stop
+    pub(crate) fn is_failed(&self) -> bool {
+        self.failed.is_cancelled()
+    }
+
+    /// Stops the pipeline, `error` is only kept if it is the first one
+    pub(crate) fn fail(&self, error: anyhow::Error) {
+        let mut first_error = self.error.lock().unwrap_or_else(|e| e.into_inner());
+
+        if !self.failed.is_cancelled() {
+            *first_error = Some(error);
+            self.failed.cancel();
+        }
+    }
+
+    /// Records that `n` more passwords are done
+    pub(crate) fn completed(&self, n: u64) {
+        self.done.fetch_add(n, Ordering::Relaxed);
+    }
+
+    /// Sends `msg` on `tx`, waiting for room in the channel unless a thread failed.
+    /// Returns `false` if a thread failed.
+    pub(crate) fn send<T>(&self, tx: &Sender<T>, msg: T) -> Result<bool> {
+        self.send_until(tx, msg, &never())
+    }
+
+    /// Like [`Abort::send`], but also gives up once the pipeline is cancelled.
+    /// Used by the reader, which is the only thread that stops when cancelled.
+    pub(crate) fn send_unless_cancelled<T>(&self, tx: &Sender<T>, msg: T) -> Result<bool> {
+        self.send_until(tx, msg, self.cancel.signal())
+    }
+
+    fn send_until<T>(&self, tx: &Sender<T>, msg: T, cancelled: &Receiver<()>) -> Result<bool> {
+        let sent = select! {
+            send(tx, msg) -> sent => sent.is_ok(),
+            recv(self.failed.signal()) -> _ => false,
+            recv(cancelled) -> _ => false,
+        };
+
+        // the receiving threads are only gone before the end if the pipeline was aborted
+        if !sent && !self.is_aborted() {
+            return Err(anyhow::anyhow!("a thread of the pipeline stopped"));
+        }
+
+        Ok(sent)
+    }
+
+    /// Receives from `rx`, returns `None` once the channel is empty and disconnected,
+    /// or as soon as a thread failed. Cancelling the pipeline does not stop receiving,
+    /// the chunks that were read are drained.
+    pub(crate) fn recv<T>(&self, rx: &Receiver<T>) -> Option<T> {
+        if self.is_failed() {
+            return None;
+        }
+
+        select! {
+            recv(rx) -> msg => msg.ok(),
+            recv(self.failed.signal()) -> _ => None,
+        }
+    }
+
+    /// Spawns a thread of the pipeline, the pipeline is stopped if `f` fails
+    pub(crate) fn spawn<F>(self: &Arc<Self>, f: F) -> JoinHandle<()>
+    where
+        F: FnOnce() -> Result<()> + Send + 'static,
+    {
+        let abort = self.clone();
+
+        std::thread::spawn(move || {
+            if let Err(error) = f() {
+                abort.fail(error);
+            }
+        })
+    }
+
+    /// Waits for threads spawned with [`Abort::spawn`], a panic stops the pipeline
+    pub(crate) fn join(&self, handles: impl IntoIterator<Item = JoinHandle<()>>) {
+        for h in handles {
+            if h.join().is_err() {
+                self.fail(PipelineError::WorkerPanicError.into());
+            }
+        }
+    }
+
+    /// The error that stopped the pipeline, if any, or [`PipelineError::CancelledError`]
+    /// if it was cancelled
+    pub(crate) fn result(&self) -> Result<()> {
+        match self.error.lock().unwrap_or_else(|e| e.into_inner()).take() {
+            Some(error) => Err(error),
+            None if self.cancel.is_cancelled() => {
+                let done = self.done.load(Ordering::Relaxed);
+                Err(PipelineError::CancelledError(done).into())
+            }
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::abort::Abort;
+    use crate::cancel::CancellationToken;
+    use crate::errors::PipelineError;
+    use anyhow::anyhow;
+
+    #[test]
+    fn test_abort_wakes_up_blocked_threads() {
+        let abort = Abort::new(&CancellationToken::new());
+        let (tx, rx) = crossbeam_channel::bounded::<()>(0);
+
+        let sender = {
+            let abort = abort.clone();
+            std::thread::spawn(move || abort.send(&tx, ()).unwrap())
+        };
+        let receiver = {
+            let abort = abort.clone();
+            let (_tx, rx) = crossbeam_channel::bounded::<()>(0);
+            std::thread::spawn(move || abort.recv(&rx))
+        };
+
+        abort.fail(anyhow!("stop"));
+        assert!(!sender.join().unwrap());
+        assert_eq!(receiver.join().unwrap(), None);
+        drop(rx);
+    }
+
+    #[test]
+    fn test_first_error_is_kept() {
+        let abort = Abort::new(&CancellationToken::new());
+        let h = abort.spawn(|| Err(anyhow!("first")));
+        abort.join([h]);
+        assert!(abort.is_aborted());
+
+        // e.g. a send on a channel that was closed because of the first error
+        abort.fail(anyhow!("second"));
+        let h = abort.spawn(|| panic!("third"));
+        abort.join([h]);
+
+        assert_eq!(abort.result().unwrap_err().to_string(), "first");
+    }
+
+    #[test]
+    fn test_cancelled_reports_progress() {
+        let cancel = CancellationToken::new();
+        let abort = Abort::new(&cancel);
+        let (tx, _rx) = crossbeam_channel::bounded::<()>(0);
+
+        let reader = {
+            let abort = abort.clone();
+            std::thread::spawn(move || abort.send_unless_cancelled(&tx, ()).unwrap())
+        };
+
+        abort.completed(42);
+        cancel.cancel();
+        assert!(!reader.join().unwrap());
+        assert!(abort.is_aborted());
+        assert!(!abort.is_failed());
+
+        // chunks that were read are still drained
+        let (tx, rx) = crossbeam_channel::bounded(1);
+        assert!(abort.send(&tx, ()).unwrap());
+        assert_eq!(abort.recv(&rx), Some(()));
+        assert!(matches!(
+            abort.result().unwrap_err().downcast::<PipelineError>(),
+            Ok(PipelineError::CancelledError(42))
+        ));
+    }
+}
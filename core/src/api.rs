@@ -0,0 +1,226 @@
+//! The JSON API of `serve --http`, apart from reading and writing HTTP.
+//!
+//! - `POST /hash` with `{"algorithm": "md5", "input": "abc"}` answers `{"hash": "<hex digest>"}`
+//! - `POST /lookup` with `{"algorithm": "md5", "hash": "<hex digest>"}` answers
+//!   `{"password": "abc"}`, or `{"password": null}` if the hash is not in the loaded table
+//! - `POST /jobs` with a [`JobSpec`] starts a job and answers its [`JobStatus`]
+//! - `GET /jobs/{id}` answers the [`JobStatus`] of a job, with its progress
+//! - `DELETE /jobs/{id}` stops a job if it is still running, forgets it and answers its last
+//!   [`JobStatus`]
+//!
+//! Errors are answered with `{"error": "<message>"}`.
+use crate::jobs::{JobSpec, JobStatus, Jobs};
+use crate::server::{parse_digest, Server};
+use crate::utils::vec_to_hex_string;
+use crate::HashAlgorithm;
+use serde::{Deserialize, Serialize};
+
+/// Answers the requests of every connection, share it between connections with an `Arc`
+pub struct Api {
+    server: Server,
+    jobs: Jobs,
+}
+
+/// What to answer a request with
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Response {
+    /// HTTP status code
+    pub status: u16,
+    /// JSON body
+    pub body: String,
+}
+
+#[derive(Deserialize)]
+struct HashRequest {
+    algorithm: HashAlgorithm,
+    input: String,
+}
+
+#[derive(Serialize)]
+struct HashResponse {
+    hash: String,
+}
+
+#[derive(Deserialize)]
+struct LookupRequest {
+    algorithm: HashAlgorithm,
+    hash: String,
+}
+
+#[derive(Serialize)]
+struct LookupResponse<'a> {
+    password: Option<&'a str>,
+}
+
+#[derive(Serialize)]
+struct ErrorResponse {
+    error: String,
+}
+
+impl Response {
+    fn json(status: u16, body: &impl Serialize) -> Self {
+        Self {
+            status,
+            body: serde_json::to_string(body).expect("a response is always valid JSON"),
+        }
+    }
+
+    fn error(status: u16, error: impl std::fmt::Display) -> Self {
+        Self::json(
+            status,
+            &ErrorResponse {
+                error: format!("{error:#}"),
+            },
+        )
+    }
+}
+
+impl Api {
+    /// Creates a new Api
+    /// Requires:
+    /// - server: Hashes and looks up passwords, like the line protocol
+    /// - jobs: Runs the submitted jobs
+    pub fn new(server: Server, jobs: Jobs) -> Self {
+        Self { server, jobs }
+    }
+
+    /// Stops every job that is still running, see [`Jobs::shutdown`]
+    pub fn shutdown(&self) {
+        self.jobs.shutdown();
+    }
+
+    /// Answers a single request, `path` may have a query string, which is ignored
+    pub fn handle(&self, method: &str, path: &str, body: &[u8]) -> Response {
+        let path = path.split_once('?').map_or(path, |(path, _)| path);
+
+        match (method, path) {
+            ("POST", "/hash") => self.hash(body),
+            ("POST", "/lookup") => self.lookup(body),
+            ("POST", "/jobs") => self.submit(body),
+            (_, "/hash" | "/lookup" | "/jobs") => Response::error(405, "Method not allowed"),
+            _ => match path.strip_prefix("/jobs/").map(str::parse::<u64>) {
+                Some(Ok(id)) => self.job(method, id),
+                _ => Response::error(404, "Not found"),
+            },
+        }
+    }
+
+    fn hash(&self, body: &[u8]) -> Response {
+        let request = match serde_json::from_slice::<HashRequest>(body) {
+            Ok(request) => request,
+            Err(e) => return Response::error(400, e),
+        };
+
+        match self.server.hash(request.algorithm, &request.input) {
+            Ok(hash) => Response::json(
+                200,
+                &HashResponse {
+                    hash: vec_to_hex_string(&hash),
+                },
+            ),
+            Err(e) => Response::error(500, e),
+        }
+    }
+
+    fn lookup(&self, body: &[u8]) -> Response {
+        let request = match serde_json::from_slice::<LookupRequest>(body) {
+            Ok(request) => request,
+            Err(e) => return Response::error(400, e),
+        };
+        let digest = match parse_digest(&request.hash) {
+            Ok(digest) => digest,
+            Err(e) => return Response::error(400, e),
+        };
+
+        let password = self.server.lookup(request.algorithm, &digest);
+        Response::json(200, &LookupResponse { password })
+    }
+
+    fn submit(&self, body: &[u8]) -> Response {
+        let spec = match serde_json::from_slice::<JobSpec>(body) {
+            Ok(spec) => spec,
+            Err(e) => return Response::error(400, e),
+        };
+
+        match self.jobs.submit(spec) {
+            Ok(status) => Response::json(201, &status),
+            Err(e) => Response::error(400, e),
+        }
+    }
+
+    fn job(&self, method: &str, id: u64) -> Response {
+        let status: Option<JobStatus> = match method {
+            "GET" => self.jobs.status(id),
+            "DELETE" => self.jobs.remove(id),
+            _ => return Response::error(405, "Method not allowed"),
+        };
+
+        match status {
+            Some(status) => Response::json(200, &status),
+            None => Response::error(404, format!("No job {id}")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::api::Api;
+    use crate::jobs::Jobs;
+    use crate::server::Server;
+    use crate::table::Table;
+    use crate::HashAlgorithm;
+    use hex_literal::hex;
+    use std::num::NonZeroUsize;
+
+    fn api() -> Api {
+        let mut table = Table::new();
+        table.insert(
+            HashAlgorithm::Sha2,
+            hex!("ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad").to_vec(),
+            "abc".to_owned(),
+        );
+
+        Api::new(Server::new(table), Jobs::new(NonZeroUsize::MIN))
+    }
+
+    #[test]
+    fn test_hash_and_lookup() {
+        let api = api();
+
+        let response = api.handle(
+            "POST",
+            "/hash",
+            br#"{"algorithm": "md5", "input": "$HEX[616263]"}"#,
+        );
+        assert_eq!(response.status, 200);
+        assert_eq!(
+            response.body,
+            r#"{"hash":"900150983cd24fb0d6963f7d28e17f72"}"#
+        );
+
+        let request = r#"{"algorithm": "sha2",
+            "hash": "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"}"#;
+        let response = api.handle("POST", "/lookup?pretty", request.as_bytes());
+        assert_eq!(response.body, r#"{"password":"abc"}"#);
+
+        let response = api.handle("POST", "/lookup", br#"{"algorithm": "md5", "hash": "00"}"#);
+        assert_eq!(response.body, r#"{"password":null}"#);
+    }
+
+    #[test]
+    fn test_errors() {
+        let api = api();
+
+        let statuses = [
+            api.handle("POST", "/hash", br#"{"algorithm": "sha3", "input": "a"}"#),
+            api.handle("POST", "/lookup", br#"{"algorithm": "md5", "hash": "0"}"#),
+            api.handle("GET", "/hash", b""),
+            api.handle("GET", "/jobs/1", b""),
+            api.handle("PUT", "/jobs/1", b""),
+            api.handle("GET", "/jobs/one", b""),
+        ]
+        .map(|response| response.status);
+
+        assert_eq!(statuses, [400, 400, 405, 404, 405, 404]);
+    }
+}
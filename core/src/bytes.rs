@@ -0,0 +1,206 @@
+//! Candidate generation over arbitrary bytes instead of characters.
+//!
+//! Useful when the target system hashed raw bytes that are not valid UTF-8, e.g. binary PINs
+//! or passwords in a legacy encoding.
+use crate::encoding::to_hex_notation;
+use crate::errors::GenPasswordsError;
+use crate::keyspace::SeekableGenerator;
+use crate::passwords::{brute_force_keyspace, locate, size_hint};
+
+/// Struct Iterator to iterate over all byte strings of a length range made of an alphabet
+///
+/// Candidates are generated in the same order as [`crate::passwords::PasswordGenerator`]:
+/// shortest first, and the first byte changes the fastest.
+///
+/// Usage:
+/// ```Rust
+/// let alphabet = encoding::parse_byte_alphabet("00-ff")?;
+/// let candidates = bytes::ByteGenerator::new(1, 2, alphabet)?;
+///
+/// for candidate in candidates {
+///     println!("Candidate: {:?}", candidate);
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct ByteGenerator {
+    /// Minimum length of candidates
+    min_length: usize,
+    /// Maximum length of candidates
+    max_length: usize,
+    /// Valid bytes for candidates
+    alphabet: Vec<u8>,
+    /// Iterator property to keep track of the index of the next candidate
+    position: u128,
+}
+
+impl ByteGenerator {
+    /// Creates a new ByteGenerator
+    /// Requires:
+    /// - min_length: Minimum length for candidates
+    /// - max_length: Maximum length for candidates
+    /// - alphabet: Valid bytes for candidates
+    pub fn new(
+        min_length: usize,
+        max_length: usize,
+        alphabet: Vec<u8>,
+    ) -> Result<Self, GenPasswordsError> {
+        if min_length == 0 {
+            return Err(GenPasswordsError::InvalidMinLengthError(min_length));
+        }
+
+        if min_length > max_length {
+            return Err(GenPasswordsError::MinGreaterThanMaxError(
+                min_length, max_length,
+            ));
+        }
+
+        Ok(Self {
+            min_length,
+            max_length,
+            alphabet,
+            position: 0,
+        })
+    }
+
+    /// Number of candidates this generator produces.
+    /// Returns `None` if there are more candidates than fit in a `u128`.
+    pub fn keyspace(&self) -> Option<u128> {
+        brute_force_keyspace(self.alphabet.len(), self.min_length, self.max_length)
+    }
+
+    /// Returns the candidate at `index` without moving the iterator.
+    /// Returns `None` if `index` is past the last candidate.
+    pub fn nth(&self, index: u128) -> Option<Vec<u8>> {
+        let (length, mut offset) =
+            locate(self.alphabet.len(), self.min_length, self.max_length, index)?;
+        let r = self.alphabet.len() as u128;
+
+        Some(
+            (0..length)
+                .map(|_| {
+                    let b = self.alphabet[(offset % r) as usize];
+                    offset /= r;
+                    b
+                })
+                .collect(),
+        )
+    }
+
+    /// Moves the generator so that the next candidate is the one at `index`.
+    pub fn seek(&mut self, index: u128) {
+        self.position = index;
+    }
+
+    /// Writes every candidate as a line of a wordlist, see [`to_hex_notation`]
+    pub fn hex_encoded(self) -> HexEncoded {
+        HexEncoded { generator: self }
+    }
+}
+
+impl Iterator for ByteGenerator {
+    type Item = Vec<u8>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let candidate = ByteGenerator::nth(self, self.position)?;
+        self.position += 1;
+
+        Some(candidate)
+    }
+
+    fn nth(&mut self, n: usize) -> Option<Self::Item> {
+        self.seek(self.position.saturating_add(n as u128));
+        self.next()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        size_hint(
+            self.keyspace()
+                .map(|keyspace| keyspace.saturating_sub(self.position)),
+        )
+    }
+}
+
+/// Candidates of a [`ByteGenerator`] as lines of a wordlist, see [`ByteGenerator::hex_encoded`]
+#[derive(Debug, Clone)]
+pub struct HexEncoded {
+    generator: ByteGenerator,
+}
+
+impl Iterator for HexEncoded {
+    type Item = String;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.generator.next().map(|bytes| to_hex_notation(&bytes))
+    }
+
+    fn nth(&mut self, n: usize) -> Option<Self::Item> {
+        Iterator::nth(&mut self.generator, n).map(|bytes| to_hex_notation(&bytes))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.generator.size_hint()
+    }
+}
+
+impl SeekableGenerator for HexEncoded {
+    fn keyspace(&self) -> Option<u128> {
+        self.generator.keyspace()
+    }
+
+    fn seek(&mut self, index: u128) {
+        self.generator.seek(index)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::bytes::ByteGenerator;
+    use crate::encoding::from_hex_notation;
+    use crate::keyspace::{KeyspaceRange, SeekableGenerator};
+
+    #[test]
+    fn test_byte_generator() {
+        let candidates = ByteGenerator::new(1, 2, vec![0x00, 0xff])
+            .unwrap()
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            candidates,
+            vec![
+                vec![0x00],
+                vec![0xff],
+                vec![0x00, 0x00],
+                vec![0xff, 0x00],
+                vec![0x00, 0xff],
+                vec![0xff, 0xff],
+            ]
+        );
+    }
+
+    #[test]
+    fn test_byte_generator_full_alphabet() {
+        let generator = ByteGenerator::new(1, 2, (0..=255).collect()).unwrap();
+        assert_eq!(generator.keyspace(), Some(256 + 256 * 256));
+        assert_eq!(generator.nth(256), Some(vec![0x00, 0x00]));
+
+        let lines = generator
+            .hex_encoded()
+            .in_range(KeyspaceRange::new(0x61, Some(2)))
+            .collect::<Vec<_>>();
+        assert_eq!(lines, vec!["a", "b"]);
+
+        let lines = ByteGenerator::new(1, 2, (0..=255).collect())
+            .unwrap()
+            .hex_encoded()
+            .collect::<Vec<_>>();
+        for (index, line) in lines.iter().enumerate() {
+            let bytes = from_hex_notation(line).unwrap_or_else(|| line.as_bytes().to_vec());
+            assert_eq!(
+                ByteGenerator::new(1, 2, (0..=255).collect())
+                    .unwrap()
+                    .nth(index as u128),
+                Some(bytes)
+            );
+        }
+    }
+}
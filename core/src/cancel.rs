@@ -0,0 +1,95 @@
+//! Stopping a running job from the outside, e.g. when the user presses Ctrl-C.
+use crossbeam_channel::{Receiver, Sender};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+
+/// Asks a running job to stop. Clones share the same state, so a clone can be handed to a
+/// signal handler while the job checks the original.
+///
+/// A cancelled job stops reading and generating passwords, writes what is already hashed,
+/// saves its checkpoint and returns [`crate::PipelineError::CancelledError`].
+///
+/// Usage:
+/// ```Rust
+/// let cancel = CancellationToken::new();
+/// let handler = cancel.clone();
+/// ctrlc::set_handler(move || handler.cancel())?;
+/// ```
+#[derive(Clone, Debug)]
+pub struct CancellationToken {
+    inner: Arc<Inner>,
+}
+
+#[derive(Debug)]
+struct Inner {
+    cancelled: AtomicBool,
+    /// Dropped when cancelled, which disconnects `signal`
+    trigger: Mutex<Option<Sender<()>>>,
+    /// Never receives anything, only becomes disconnected
+    signal: Receiver<()>,
+}
+
+impl CancellationToken {
+    /// Creates a new CancellationToken that is not cancelled
+    pub fn new() -> Self {
+        let (trigger, signal) = crossbeam_channel::bounded(0);
+
+        Self {
+            inner: Arc::new(Inner {
+                cancelled: AtomicBool::new(false),
+                trigger: Mutex::new(Some(trigger)),
+                signal,
+            }),
+        }
+    }
+
+    /// Asks the job to stop, cancelling more than once has no effect
+    pub fn cancel(&self) {
+        self.inner.cancelled.store(true, Ordering::Relaxed);
+        self.inner
+            .trigger
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .take();
+    }
+
+    /// Whether the job was asked to stop
+    pub fn is_cancelled(&self) -> bool {
+        self.inner.cancelled.load(Ordering::Relaxed)
+    }
+
+    /// Blocks until the job is asked to stop, for jobs that only stop when asked to
+    pub fn wait(&self) {
+        // nothing is ever sent, receiving only returns once the trigger is dropped
+        let _ = self.inner.signal.recv();
+    }
+
+    /// Channel that becomes disconnected once cancelled, to wait for it in a `select!`
+    pub(crate) fn signal(&self) -> &Receiver<()> {
+        &self.inner.signal
+    }
+}
+
+impl Default for CancellationToken {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::cancel::CancellationToken;
+
+    #[test]
+    fn test_cancel_is_shared_by_clones() {
+        let cancel = CancellationToken::new();
+        let handler = cancel.clone();
+        assert!(!cancel.is_cancelled());
+        assert!(cancel.signal().try_recv().unwrap_err().is_empty());
+
+        handler.cancel();
+        handler.cancel();
+        assert!(cancel.is_cancelled());
+        assert!(cancel.signal().try_recv().unwrap_err().is_disconnected());
+    }
+}
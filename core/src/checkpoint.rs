@@ -0,0 +1,300 @@
+//! Checkpoints of long running jobs, so that they can be resumed after being killed.
+//!
+//! A checkpoint records how far into its keyspace (or input file) a job got, which chunks
+//! past that point are already done, and how long the output files were at that moment.
+//! Resuming truncates the output files back to those lengths and skips everything already done.
+use crate::errors::CheckpointError;
+use crate::utils::{file_exists, write_file_atomic};
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::fs::{File, OpenOptions};
+use std::io::{BufReader, Seek, SeekFrom};
+use std::time::{Duration, Instant};
+
+/// Progress of a job at the time it was last saved
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Checkpoint {
+    /// Every password before this index, relative to the start of the job's range, is done
+    pub position: u128,
+    /// Chunks after `position` that are done, as `(start, len)` sorted by start
+    pub completed: Vec<(u128, u128)>,
+    /// Length in bytes of every output file containing everything that is done,
+    /// in the order the job opens them
+    pub output_lens: Vec<u64>,
+    /// Number of passwords in every chunk, resuming requires the same chunk size
+    pub chunk_size: u64,
+    /// Parameters of the job that decide what it writes, resuming requires the same ones
+    #[serde(default)]
+    pub job: String,
+    /// Whether the job ran to completion
+    pub finished: bool,
+}
+
+impl Checkpoint {
+    /// Records that the chunk of `len` passwords starting at `start` is done
+    pub fn complete_chunk(&mut self, start: u128, len: u128) {
+        if start != self.position {
+            let idx = self.completed.partition_point(|(s, _)| *s < start);
+            self.completed.insert(idx, (start, len));
+            return;
+        }
+
+        self.position += len;
+
+        // chunks that were done out of order may now directly follow `position`
+        let contiguous = self
+            .completed
+            .iter()
+            .take_while(|(start, len)| {
+                let follows = *start == self.position;
+                if follows {
+                    self.position += len;
+                }
+                follows
+            })
+            .count();
+        self.completed.drain(..contiguous);
+    }
+
+    /// Whether the chunk starting at `start` is already done
+    pub fn is_completed(&self, start: u128) -> bool {
+        start < self.position || self.completed.iter().any(|(s, _)| *s == start)
+    }
+
+    /// Number of passwords that are done
+    pub fn done(&self) -> u128 {
+        self.position + self.completed.iter().map(|(_, len)| len).sum::<u128>()
+    }
+}
+
+/// Keeps a [`Checkpoint`] up to date and periodically writes it to disk
+///
+/// Usage:
+/// ```Rust
+/// let interval = Duration::from_secs(60);
+/// let mut checkpointer = Checkpointer::new("job.checkpoint", interval, true, 1000, "md5 words.txt")?;
+/// let mut out = checkpointer.open_output(0, "hashes.bin")?;
+///
+/// // ... write a chunk to `out`
+/// checkpointer.complete(start, len);
+/// if checkpointer.is_due() {
+///     out.flush()?;
+///     checkpointer.save(vec![out.metadata()?.len()])?;
+/// }
+/// ```
+#[derive(Debug)]
+pub struct Checkpointer {
+    /// Path of the checkpoint file
+    path: String,
+    /// Minimum time between two saves
+    interval: Duration,
+    /// When the checkpoint was last saved
+    last_save: Instant,
+    /// Whether the job resumes from an existing checkpoint
+    resumed: bool,
+    checkpoint: Checkpoint,
+}
+
+impl Checkpointer {
+    /// Creates a new Checkpointer
+    /// Requires:
+    /// - path: Path of the checkpoint file
+    /// - interval: Minimum time between two saves
+    /// - resume: Load the checkpoint file if it exists instead of starting over
+    /// - chunk_size: Number of passwords in every chunk of the job
+    /// - job: Parameters of the job that decide what it writes, e.g. its mode and range
+    pub fn new(
+        path: &str,
+        interval: Duration,
+        resume: bool,
+        chunk_size: u64,
+        job: &str,
+    ) -> Result<Self> {
+        let resumed = resume && file_exists(path);
+
+        let checkpoint = if resumed {
+            let checkpoint: Checkpoint =
+                serde_json::from_reader(BufReader::new(File::open(path)?))?;
+
+            if checkpoint.chunk_size != chunk_size {
+                return Err(CheckpointError::ChunkSizeMismatchError(
+                    checkpoint.chunk_size,
+                    chunk_size,
+                )
+                .into());
+            }
+
+            if checkpoint.job != job {
+                return Err(
+                    CheckpointError::JobMismatchError(checkpoint.job, job.to_owned()).into(),
+                );
+            }
+
+            checkpoint
+        } else {
+            Checkpoint {
+                chunk_size,
+                job: job.to_owned(),
+                ..Checkpoint::default()
+            }
+        };
+
+        Ok(Self {
+            path: path.to_owned(),
+            interval,
+            last_save: Instant::now(),
+            resumed,
+            checkpoint,
+        })
+    }
+
+    pub fn checkpoint(&self) -> &Checkpoint {
+        &self.checkpoint
+    }
+
+    /// Checks that a resumed job writes as many output files as recorded in the checkpoint
+    pub fn check_outputs(&self, count: usize) -> Result<()> {
+        let recorded = self.checkpoint.output_lens.len();
+        if self.resumed && recorded != count {
+            return Err(CheckpointError::OutputCountMismatchError(recorded, count).into());
+        }
+
+        Ok(())
+    }
+
+    /// Opens output file number `index` of the job. When resuming, the file is truncated to the
+    /// length recorded in the checkpoint and opened for appending, otherwise it is created from
+    /// scratch.
+    pub fn open_output(&self, index: usize, path: &str) -> Result<File> {
+        if !self.resumed {
+            return Ok(File::create(path)?);
+        }
+
+        let output_len = *self.checkpoint.output_lens.get(index).ok_or(
+            CheckpointError::OutputCountMismatchError(self.checkpoint.output_lens.len(), index + 1),
+        )?;
+
+        let mut file = OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(path)?;
+        let len = file.metadata()?.len();
+        if len < output_len {
+            return Err(CheckpointError::OutputTooShortError(output_len, len).into());
+        }
+
+        file.set_len(output_len)?;
+        file.seek(SeekFrom::End(0))?;
+
+        Ok(file)
+    }
+
+    /// Records that the chunk of `len` passwords starting at `start` is done
+    pub fn complete(&mut self, start: u128, len: u128) {
+        self.checkpoint.complete_chunk(start, len);
+    }
+
+    /// Whether it is time to save the checkpoint.
+    /// The output file must be flushed before saving.
+    pub fn is_due(&self) -> bool {
+        self.last_save.elapsed() >= self.interval
+    }
+
+    /// Writes the checkpoint to disk, `output_lens` are the lengths of the output files once
+    /// every chunk that is done has been written to them and flushed
+    pub fn save(&mut self, output_lens: Vec<u64>) -> Result<()> {
+        self.checkpoint.output_lens = output_lens;
+        write_file_atomic(&self.path, &serde_json::to_string(&self.checkpoint)?)?;
+        self.last_save = Instant::now();
+
+        Ok(())
+    }
+
+    /// Marks the job as finished and writes the checkpoint to disk
+    pub fn finish(mut self, output_lens: Vec<u64>) -> Result<()> {
+        self.checkpoint.finished = true;
+        self.save(output_lens)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::checkpoint::{Checkpoint, Checkpointer};
+    use crate::errors::CheckpointError;
+    use std::io::Write;
+    use std::time::Duration;
+
+    const JOB: &str = "md5";
+
+    #[test]
+    fn test_complete_chunks_out_of_order() {
+        let mut checkpoint = Checkpoint::default();
+
+        checkpoint.complete_chunk(20, 10);
+        checkpoint.complete_chunk(10, 10);
+        assert_eq!(checkpoint.position, 0);
+        assert_eq!(checkpoint.completed, vec![(10, 10), (20, 10)]);
+        assert!(checkpoint.is_completed(20));
+        assert!(!checkpoint.is_completed(0));
+        assert_eq!(checkpoint.done(), 20);
+
+        checkpoint.complete_chunk(0, 10);
+        assert_eq!(checkpoint.position, 30);
+        assert!(checkpoint.completed.is_empty());
+
+        checkpoint.complete_chunk(40, 5);
+        assert_eq!(checkpoint.position, 30);
+        assert_eq!(checkpoint.done(), 35);
+    }
+
+    #[test]
+    fn test_resume_truncates_output() {
+        let dir = tempfile::tempdir().unwrap();
+        let checkpoint_path = dir.path().join("job.checkpoint");
+        let checkpoint_path = checkpoint_path.to_str().unwrap();
+        let out_path = dir.path().join("out.txt");
+        let out_path = out_path.to_str().unwrap();
+
+        let mut checkpointer =
+            Checkpointer::new(checkpoint_path, Duration::ZERO, true, 2, JOB).unwrap();
+        let mut out = checkpointer.open_output(0, out_path).unwrap();
+        out.write_all(b"a\nb\n").unwrap();
+        checkpointer.complete(0, 2);
+        assert!(checkpointer.is_due());
+        checkpointer.save(vec![4]).unwrap();
+
+        // written after the checkpoint, lost when resuming
+        out.write_all(b"c\n").unwrap();
+        drop(out);
+
+        assert!(Checkpointer::new(checkpoint_path, Duration::ZERO, true, 3, JOB).is_err());
+        assert!(matches!(
+            Checkpointer::new(checkpoint_path, Duration::ZERO, true, 2, "sha256")
+                .unwrap_err()
+                .downcast_ref::<CheckpointError>(),
+            Some(CheckpointError::JobMismatchError(..))
+        ));
+
+        let checkpointer =
+            Checkpointer::new(checkpoint_path, Duration::ZERO, true, 2, JOB).unwrap();
+        assert_eq!(checkpointer.checkpoint().position, 2);
+        let mut out = checkpointer.open_output(0, out_path).unwrap();
+        out.write_all(b"x\n").unwrap();
+        drop(out);
+        checkpointer.finish(vec![6]).unwrap();
+
+        assert_eq!(std::fs::read_to_string(out_path).unwrap(), "a\nb\nx\n");
+
+        let checkpointer =
+            Checkpointer::new(checkpoint_path, Duration::ZERO, true, 2, JOB).unwrap();
+        assert!(checkpointer.checkpoint().finished);
+
+        // starting over ignores the checkpoint
+        let checkpointer =
+            Checkpointer::new(checkpoint_path, Duration::ZERO, false, 2, JOB).unwrap();
+        assert_eq!(checkpointer.checkpoint().position, 0);
+        checkpointer.open_output(0, out_path).unwrap();
+        assert_eq!(std::fs::read_to_string(out_path).unwrap(), "");
+    }
+}
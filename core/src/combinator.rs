@@ -0,0 +1,281 @@
+//! Generators that build passwords by combining words from wordlists with each other or with masks
+use crate::keyspace::SeekableGenerator;
+use crate::mask::MaskGenerator;
+use crate::passwords::size_hint;
+
+/// Struct Iterator to iterate over every word of a left wordlist concatenated
+/// with every word of a right wordlist
+///
+/// Usage:
+/// ```Rust
+/// let left = vec!["hello".to_string(), "bye".to_string()];
+/// let right = vec!["world".to_string(), "moon".to_string()];
+/// let passwords = combinator::CombinatorGenerator::new(left, right, "-");
+///
+/// // hello-world, hello-moon, bye-world, bye-moon
+/// for password in passwords {
+///     println!("Password: {}", password);
+/// }
+/// ```
+pub struct CombinatorGenerator {
+    /// Words placed at the start of passwords
+    left: Vec<String>,
+    /// Words placed at the end of passwords
+    right: Vec<String>,
+    /// Placed between the left and right words
+    separator: String,
+    /// Iterator property to keep track of the current left word
+    left_idx: usize,
+    /// Iterator property to keep track of the current right word
+    right_idx: usize,
+}
+
+impl CombinatorGenerator {
+    /// Creates a new CombinatorGenerator
+    /// Requires:
+    /// - left: Words placed at the start of passwords
+    /// - right: Words placed at the end of passwords
+    /// - separator: Placed between both words, can be empty
+    pub fn new(left: Vec<String>, right: Vec<String>, separator: &str) -> Self {
+        Self {
+            left,
+            right,
+            separator: separator.to_owned(),
+            left_idx: 0,
+            right_idx: 0,
+        }
+    }
+
+    /// Number of passwords this generator produces
+    pub fn keyspace(&self) -> u128 {
+        self.left.len() as u128 * self.right.len() as u128
+    }
+
+    /// Number of passwords left to generate
+    fn remaining(&self) -> u128 {
+        let left_remaining = self.left.len().saturating_sub(self.left_idx) as u128;
+
+        (left_remaining * self.right.len() as u128).saturating_sub(self.right_idx as u128)
+    }
+}
+
+impl Iterator for CombinatorGenerator {
+    type Item = String;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.right_idx >= self.right.len() {
+            self.right_idx = 0;
+            self.left_idx += 1;
+        }
+
+        let left = self.left.get(self.left_idx)?;
+        let right = self.right.get(self.right_idx)?;
+        self.right_idx += 1;
+
+        Some(format!("{}{}{}", left, self.separator, right))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        size_hint(Some(self.remaining()))
+    }
+}
+
+impl SeekableGenerator for CombinatorGenerator {
+    fn keyspace(&self) -> Option<u128> {
+        Some(CombinatorGenerator::keyspace(self))
+    }
+
+    fn seek(&mut self, index: u128) {
+        if self.right.is_empty() {
+            self.left_idx = self.left.len();
+            return;
+        }
+
+        let right_len = self.right.len() as u128;
+        self.left_idx = usize::try_from(index / right_len).unwrap_or(usize::MAX);
+        self.right_idx = (index % right_len) as usize;
+    }
+}
+
+/// Both wordlists are held in memory, so the number of passwords left is known exactly.
+impl ExactSizeIterator for CombinatorGenerator {}
+
+/// Where the mask of a [`HybridGenerator`] is placed relative to the word
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HybridMode {
+    /// Passwords start with a word and end with the mask, e.g. `password?d?d`
+    WordlistMask,
+    /// Passwords start with the mask and end with a word, e.g. `?d?dpassword`
+    MaskWordlist,
+}
+
+/// Struct Iterator to iterate over every word of a wordlist combined with
+/// every password matching a mask
+///
+/// Usage:
+/// ```Rust
+/// let words = vec!["password".to_string()];
+/// let mask = mask::MaskGenerator::new("?d?d")?;
+/// let passwords = combinator::HybridGenerator::new(words, mask, HybridMode::WordlistMask);
+///
+/// // password00, password10, ..., password99
+/// for password in passwords {
+///     println!("Password: {}", password);
+/// }
+/// ```
+pub struct HybridGenerator {
+    /// Words combined with the mask
+    words: Vec<String>,
+    /// Where the mask is placed relative to the word
+    mode: HybridMode,
+    /// Iterator property to keep track of the current word
+    word_idx: usize,
+    /// Iterator property to keep track of the passwords matching the mask for the current word
+    mask: MaskGenerator,
+}
+
+impl HybridGenerator {
+    /// Creates a new HybridGenerator
+    /// Requires:
+    /// - words: Words combined with the mask
+    /// - mask: Generator for the part of the passwords that is not a word
+    /// - mode: Where the mask is placed relative to the word
+    pub fn new(words: Vec<String>, mut mask: MaskGenerator, mode: HybridMode) -> Self {
+        mask.reset();
+
+        Self {
+            words,
+            mode,
+            word_idx: 0,
+            mask,
+        }
+    }
+
+    /// Number of passwords this generator produces.
+    /// Returns `None` if there are more passwords than fit in a `u128`.
+    pub fn keyspace(&self) -> Option<u128> {
+        self.mask.keyspace()?.checked_mul(self.words.len() as u128)
+    }
+
+    /// Number of passwords left to generate, `None` if it does not fit in a `u128`
+    fn remaining(&self) -> Option<u128> {
+        // seeking past the last word leaves `word_idx` at up to `usize::MAX`
+        if self.word_idx >= self.words.len() {
+            return Some(0);
+        }
+
+        let words_left = (self.words.len() - self.word_idx - 1) as u128;
+        self.mask
+            .keyspace()?
+            .checked_mul(words_left)?
+            .checked_add(self.mask.remaining()?)
+    }
+}
+
+impl Iterator for HybridGenerator {
+    type Item = String;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let word = self.words.get(self.word_idx)?;
+
+            if let Some(masked) = self.mask.next() {
+                return Some(match self.mode {
+                    HybridMode::WordlistMask => format!("{}{}", word, masked),
+                    HybridMode::MaskWordlist => format!("{}{}", masked, word),
+                });
+            }
+
+            self.word_idx += 1;
+            self.mask.reset();
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        size_hint(self.remaining())
+    }
+}
+
+impl SeekableGenerator for HybridGenerator {
+    fn keyspace(&self) -> Option<u128> {
+        HybridGenerator::keyspace(self)
+    }
+
+    fn seek(&mut self, index: u128) {
+        match self.mask.keyspace() {
+            Some(mask_keyspace) => {
+                self.word_idx = usize::try_from(index / mask_keyspace).unwrap_or(usize::MAX);
+                self.mask.seek(index % mask_keyspace);
+            }
+            // the mask alone has more passwords than fit in a `u128`
+            None => {
+                self.word_idx = 0;
+                self.mask.seek(index);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::combinator::{CombinatorGenerator, HybridGenerator, HybridMode};
+    use crate::keyspace::{KeyspaceRange, SeekableGenerator};
+    use crate::mask::MaskGenerator;
+
+    fn words(words: &[&str]) -> Vec<String> {
+        words.iter().map(|w| w.to_string()).collect()
+    }
+
+    #[test]
+    fn test_combinator() {
+        let mut generator =
+            CombinatorGenerator::new(words(&["a", "b"]), words(&["1", "2", "3"]), "-");
+        assert_eq!(generator.keyspace(), 6);
+        generator.next();
+        assert_eq!(generator.len(), 5);
+
+        let passwords = CombinatorGenerator::new(words(&["a", "b"]), words(&["1", "2", "3"]), "-")
+            .collect::<Vec<_>>();
+        let slice = CombinatorGenerator::new(words(&["a", "b"]), words(&["1", "2", "3"]), "-")
+            .in_range(KeyspaceRange::new(2, Some(3)))
+            .collect::<Vec<_>>();
+
+        assert_eq!(slice, passwords[2..5]);
+        assert_eq!(passwords, words(&["a-1", "a-2", "a-3", "b-1", "b-2", "b-3"]));
+
+        let mut passwords = CombinatorGenerator::new(words(&["a", "b"]), vec![], "");
+        assert_eq!(passwords.len(), 0);
+        assert_eq!(passwords.next(), None);
+    }
+
+    #[test]
+    fn test_hybrid() {
+        let mask = MaskGenerator::new("?d").unwrap();
+
+        let mut generator =
+            HybridGenerator::new(words(&["x", "y"]), mask.clone(), HybridMode::WordlistMask);
+        assert_eq!(generator.keyspace(), Some(20));
+        generator.nth(12);
+        assert_eq!(generator.size_hint(), (7, Some(7)));
+        generator.seek(u128::MAX);
+        assert_eq!(generator.size_hint(), (0, Some(0)));
+        assert_eq!(generator.next(), None);
+
+        let passwords =
+            HybridGenerator::new(words(&["x", "y"]), mask.clone(), HybridMode::WordlistMask)
+                .collect::<Vec<_>>();
+        assert_eq!(passwords.len(), 20);
+        assert_eq!(passwords[0], "x0");
+        assert_eq!(passwords[19], "y9");
+
+        let passwords =
+            HybridGenerator::new(words(&["x", "y"]), mask.clone(), HybridMode::MaskWordlist)
+                .collect::<Vec<_>>();
+        assert_eq!(passwords[10], "0y");
+
+        let slice = HybridGenerator::new(words(&["x", "y"]), mask, HybridMode::MaskWordlist)
+            .in_range(KeyspaceRange::new(8, Some(4)))
+            .collect::<Vec<_>>();
+        assert_eq!(slice, passwords[8..12]);
+    }
+}
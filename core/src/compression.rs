@@ -0,0 +1,109 @@
+//! Transparent gzip and zstd compression of password and hash files.
+//!
+//! Output files are compressed according to their extension (or an explicit [`Compression`]),
+//! input files are decompressed according to their first bytes, whatever their name.
+use anyhow::Result;
+use flate2::read::MultiGzDecoder;
+use std::fs::File;
+use std::io::{BufRead, BufReader, Read};
+
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+const ZSTD_MAGIC: [u8; 4] = [0x28, 0xb5, 0x2f, 0xfd];
+
+/// How a file is compressed
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Compression {
+    /// Plain, uncompressed file
+    #[default]
+    None,
+
+    /// Gzip, readable by `zcat` and most tools
+    Gzip,
+
+    /// Zstandard, much faster than gzip for a similar ratio
+    Zstd,
+}
+
+impl Compression {
+    /// Compression matching the extension of `path`: `.gz` for gzip, `.zst` for zstd
+    pub fn from_path(path: &str) -> Self {
+        match path
+            .rsplit_once('.')
+            .map(|(_, ext)| ext.to_ascii_lowercase())
+        {
+            Some(ext) if ext == "gz" || ext == "gzip" => Compression::Gzip,
+            Some(ext) if ext == "zst" || ext == "zstd" => Compression::Zstd,
+            _ => Compression::None,
+        }
+    }
+
+    /// Compression of a file starting with `bytes`
+    fn sniff(bytes: &[u8]) -> Self {
+        if bytes.starts_with(&GZIP_MAGIC) {
+            Compression::Gzip
+        } else if bytes.starts_with(&ZSTD_MAGIC) {
+            Compression::Zstd
+        } else {
+            Compression::None
+        }
+    }
+}
+
+/// Opens a file for reading, decompressing it on the fly if it is gzip or zstd compressed
+pub fn open_input(path: &str) -> Result<Box<dyn BufRead + Send>> {
+    let mut file = BufReader::new(File::open(path)?);
+
+    let reader: Box<dyn BufRead + Send> = match Compression::sniff(file.fill_buf()?) {
+        Compression::None => Box::new(file),
+        Compression::Gzip => Box::new(BufReader::new(MultiGzDecoder::new(file))),
+        Compression::Zstd => Box::new(BufReader::new(zstd::Decoder::with_buffer(file)?)),
+    };
+
+    Ok(reader)
+}
+
+/// Reads a whole, possibly compressed, file into memory
+pub fn read_to_end(path: &str) -> Result<Vec<u8>> {
+    let mut content = vec![];
+    open_input(path)?.read_to_end(&mut content)?;
+
+    Ok(content)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::compression::{read_to_end, Compression};
+    use crate::output::OutputWriter;
+    use std::io::Write;
+
+    #[test]
+    fn test_compression_from_path() {
+        assert_eq!(Compression::from_path("hashes.bin.gz"), Compression::Gzip);
+        assert_eq!(Compression::from_path("words.ZST"), Compression::Zstd);
+        assert_eq!(Compression::from_path("words.txt"), Compression::None);
+        assert_eq!(Compression::from_path("words"), Compression::None);
+    }
+
+    #[test]
+    fn test_compressed_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+
+        for (name, compression) in [
+            ("out.txt", Compression::None),
+            ("out.txt.gz", Compression::Gzip),
+            ("out.txt.zst", Compression::Zstd),
+        ] {
+            let path = dir.path().join(name);
+            let path = path.to_str().unwrap();
+
+            let mut out = OutputWriter::create(path, compression).unwrap();
+            out.write_all(b"a\n").unwrap();
+            // every sync starts a new gzip member or zstd frame
+            out.sync().unwrap();
+            out.write_all(b"b\n").unwrap();
+            out.finish().unwrap();
+
+            assert_eq!(read_to_end(path).unwrap(), b"a\nb\n");
+        }
+    }
+}
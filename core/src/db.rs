@@ -2,17 +2,45 @@ use anyhow::{anyhow, Result};
 
 use std::{
     collections::HashMap,
-    fs::{self, File, OpenOptions},
-    io::{BufReader, Write},
+    fs::{self, File},
+    io::BufReader,
     path::Path,
+    sync::atomic::{AtomicU64, Ordering},
 };
 
-use crate::{utils::write_file, HashAlgorithm};
+use crate::{utils::write_file_atomic, HashAlgorithm};
 
 pub fn file_exists(path: &str) -> bool {
     Path::new(path).exists()
 }
 
+/// Lookups in the cache of every [`DB`] since the process started
+static CACHE_HITS: AtomicU64 = AtomicU64::new(0);
+static CACHE_MISSES: AtomicU64 = AtomicU64::new(0);
+
+/// How often hashes were found in the cache instead of being computed
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CacheStats {
+    pub hits: u64,
+    pub misses: u64,
+}
+
+impl CacheStats {
+    /// Share of the lookups that found their hash, `None` before the first lookup
+    pub fn hit_ratio(&self) -> Option<f64> {
+        let lookups = self.hits + self.misses;
+        (lookups > 0).then(|| self.hits as f64 / lookups as f64)
+    }
+}
+
+/// Lookups in the cache since the process started, over every algorithm
+pub fn cache_stats() -> CacheStats {
+    CacheStats {
+        hits: CACHE_HITS.load(Ordering::Relaxed),
+        misses: CACHE_MISSES.load(Ordering::Relaxed),
+    }
+}
+
 pub struct DB {
     hashes: HashMap<String, HashMap<String, String>>,
 }
@@ -31,7 +59,7 @@ const ALGORITHMS: [&str; 7] = [
 impl DB {
     pub fn new() -> Result<DB, anyhow::Error> {
         if !Path::new(DB_FOLDER_NAME).exists() {
-            fs::create_dir(DB_FOLDER_NAME)?;
+            fs::create_dir_all(DB_FOLDER_NAME)?;
         }
 
         let mut hashes: HashMap<String, HashMap<String, String>> = HashMap::new();
@@ -40,8 +68,7 @@ impl DB {
             let p = format!("./db/{}.json", algo);
 
             if !file_exists(p.as_str()) {
-                let mut file = OpenOptions::new().write(true).create(true).open(&p)?;
-                file.write_all("{}".as_bytes())?;
+                write_file_atomic(&p, "{}")?;
             }
 
             hashes.insert(algo.to_owned().to_owned(), read_json_file(&p)?);
@@ -59,7 +86,7 @@ impl DB {
         hash.insert(key.to_owned(), value.to_owned());
         let json_string = serde_json::to_string(hash)?;
         let file_path = format!("{}/{}.json", DB_FOLDER_NAME, algo);
-        write_file(&file_path, &json_string)?;
+        write_file_atomic(&file_path, &json_string)?;
 
         Ok(())
     }
@@ -70,7 +97,15 @@ impl DB {
             .get(algo)
             .ok_or_else(|| anyhow!("Unsupported algorithm: {}", algo))?;
 
-        hash.get(key).ok_or_else(|| anyhow!("Hash not found"))
+        let value = hash.get(key);
+        let counter = if value.is_some() {
+            &CACHE_HITS
+        } else {
+            &CACHE_MISSES
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+
+        value.ok_or_else(|| anyhow!("Hash not found"))
     }
 }
 
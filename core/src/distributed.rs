@@ -0,0 +1,771 @@
+//! Splitting the keyspace of a [`PasswordGenerator`] across machines: a [`Coordinator`] hands
+//! out work units to the [`Worker`]s connected to it over TCP and writes their hashes to its sink.
+//!
+//! Messages are JSON, one per line. A worker first receives the [`WorkSpec`] of the job, then:
+//! - the coordinator sends `{"unit": {"id": 3, "skip": 300000, "limit": 100000}}`
+//! - the worker answers `{"hashes": {"id": 3, "hashes": [["abc", ["<hex digest>"]], ...]}}`
+//!
+//! until the coordinator sends `"done"`. A worker whose connection drops, or that does not answer
+//! within the unit timeout, is considered dead: its unit is handed out again to the next worker
+//! asking for one. So is a worker answering with the wrong number of passwords, or whose
+//! passwords and hashes are wrong at the few positions of the unit the coordinator checks.
+use crate::cancel::CancellationToken;
+use crate::checkpoint::Checkpointer;
+use crate::encoding::Encoding;
+use crate::errors::{DistributedError, PipelineError};
+use crate::keyspace::{KeyspaceRange, SeekableGenerator};
+use crate::passwords::PasswordGenerator;
+use crate::pipeline::{CandidateHasher, HashedChunk, Pipeline, Sink};
+use crate::server::parse_digest;
+use crate::source::GeneratorSource;
+use crate::stats::Stats;
+use crate::utils::vec_to_hex_string;
+use crate::{hash_candidate_uncached, HashAlgorithm};
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::io::{BufRead, BufReader, BufWriter, ErrorKind, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::num::NonZeroUsize;
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+use std::time::Duration;
+use tracing::warn;
+
+/// Largest message of the coordinator, the job is the largest one and only as large as its
+/// characters
+const MAX_COORDINATOR_MESSAGE_LEN: u64 = 1024 * 1024;
+
+/// How often the coordinator checks for new workers, and whether the job stopped
+const ACCEPT_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Number of passwords of every unit whose hashes the coordinator computes itself, to catch
+/// workers answering with the wrong passwords or hashes
+const CHECKED_PASSWORDS: u128 = 5;
+
+/// What every worker generates and hashes, sent once when it connects
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WorkSpec {
+    /// Minimum length of the passwords
+    pub min_chars: usize,
+    /// Maximum length of the passwords
+    pub max_chars: usize,
+    /// Characters of the passwords
+    pub chars: Vec<char>,
+    /// Algorithms every password is hashed with, without duplicates
+    pub algorithms: Vec<HashAlgorithm>,
+    /// How passwords are turned into bytes before hashing
+    pub encoding: Encoding,
+}
+
+impl WorkSpec {
+    /// Generator of the whole keyspace of the job
+    pub fn generator(&self) -> Result<PasswordGenerator> {
+        Ok(PasswordGenerator::with_chars(
+            self.min_chars,
+            self.max_chars,
+            self.chars.clone(),
+        )?)
+    }
+
+    /// Largest answer of a worker to a unit of `unit_size` passwords: every password with
+    /// its characters escaped and the hex digest of every algorithm, at most 128 characters
+    fn max_reply_len(&self, unit_size: u128) -> u64 {
+        let password_len = self.max_chars as u128 * 6 + 8;
+        let hashes_len = self.algorithms.len() as u128 * (128 + 3);
+
+        u64::try_from(unit_size.saturating_mul(password_len + hashes_len) + 1024)
+            .unwrap_or(u64::MAX)
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum Message {
+    Job(WorkSpec),
+    Unit {
+        id: u64,
+        skip: u128,
+        limit: u128,
+    },
+    /// Every password of the unit with its hashes, in hex and in the order of the algorithms
+    Hashes {
+        id: u64,
+        hashes: Vec<(String, Vec<String>)>,
+    },
+    Done,
+}
+
+/// Hands out the keyspace of a job in work units to workers and writes their hashes to `sink`
+pub struct Coordinator<S> {
+    spec: WorkSpec,
+    sink: S,
+    unit_size: NonZeroUsize,
+    unit_timeout: Duration,
+    stats: Arc<Stats>,
+}
+
+impl<S: Sink + Send + 'static> Coordinator<S> {
+    /// Default number of passwords in every work unit
+    pub const DEFAULT_UNIT_SIZE: usize = 100_000;
+
+    /// Default time a worker has to hash a unit before it is considered dead
+    pub const DEFAULT_UNIT_TIMEOUT: Duration = Duration::from_secs(600);
+
+    /// Creates a new Coordinator
+    /// Requires:
+    /// - spec: What the workers generate and hash
+    /// - sink: Where the hashes go, units are written in the order they are done
+    pub fn new(spec: WorkSpec, sink: S) -> Self {
+        Self {
+            spec,
+            sink,
+            unit_size: NonZeroUsize::new(Self::DEFAULT_UNIT_SIZE).unwrap_or(NonZeroUsize::MIN),
+            unit_timeout: Self::DEFAULT_UNIT_TIMEOUT,
+            stats: Arc::default(),
+        }
+    }
+
+    /// Number of passwords in every work unit
+    pub fn unit_size(mut self, unit_size: NonZeroUsize) -> Self {
+        self.unit_size = unit_size;
+        self
+    }
+
+    /// Time a worker has to hash a unit, after which its unit is handed out again
+    pub fn unit_timeout(mut self, unit_timeout: Duration) -> Self {
+        self.unit_timeout = unit_timeout;
+        self
+    }
+
+    /// Counters updated as units are done, to watch the progress of the job
+    pub fn stats(mut self, stats: Arc<Stats>) -> Self {
+        self.stats = stats;
+        self
+    }
+
+    /// Hands out units to the workers connecting to `listener` until every unit is done, then
+    /// returns what the sink produced. Once `cancel` is cancelled, no more units are handed
+    /// out and what was written so far is kept.
+    pub fn run(self, listener: TcpListener, cancel: &CancellationToken) -> Result<S::Output> {
+        let Coordinator {
+            spec,
+            mut sink,
+            unit_size,
+            unit_timeout,
+            stats,
+        } = self;
+
+        // every worker would fail on the same units otherwise, with the job never done
+        let chars = spec.chars.iter().collect::<String>();
+        spec.encoding.encode(&chars)?;
+
+        let keyspace = spec
+            .generator()?
+            .keyspace()
+            .ok_or(DistributedError::KeyspaceTooLargeError)?;
+        let unit_size = unit_size.get() as u128;
+        let units = u64::try_from(keyspace.div_ceil(unit_size))
+            .map_err(|_| DistributedError::KeyspaceTooLargeError)?;
+
+        sink.open(&spec.algorithms, None)?;
+
+        let shared = Arc::new(Shared {
+            max_reply_len: spec.max_reply_len(unit_size),
+            spec,
+            keyspace,
+            unit_size,
+            unit_timeout,
+            stats,
+            state: Mutex::new(State {
+                sink: Some(sink),
+                next_unit: 0,
+                units,
+                handed_back: VecDeque::new(),
+                assigned: 0,
+                error: None,
+            }),
+            changed: Condvar::new(),
+        });
+
+        // accepting does not wake up when the job stops, so the listener is polled and the
+        // thread checks regularly whether the job stopped, closing the listener once it did
+        listener.set_nonblocking(true)?;
+        let acceptor = std::thread::spawn({
+            let shared = shared.clone();
+            move || loop {
+                match listener.accept() {
+                    Ok((stream, _)) => shared.clone().spawn_worker(stream),
+                    Err(e) if e.kind() == ErrorKind::WouldBlock => {
+                        if shared.lock().sink.is_none() {
+                            return;
+                        }
+                        std::thread::sleep(ACCEPT_POLL_INTERVAL);
+                    }
+                    Err(e) => warn!("cannot accept worker: {e}"),
+                }
+            }
+        });
+
+        let mut state = shared.lock();
+        while state.error.is_none() && !state.is_done() && !cancel.is_cancelled() {
+            // cancelling does not wake the condition variable up, it is checked regularly
+            state = shared
+                .changed
+                .wait_timeout(state, Duration::from_millis(100))
+                .unwrap_or_else(|e| e.into_inner())
+                .0;
+        }
+
+        // workers waiting for a unit are told the job is done once the sink is gone
+        let sink = state.sink.take().expect("only the job takes the sink");
+        let error = state.error.take();
+        let done = state.is_done();
+        drop(state);
+        shared.changed.notify_all();
+
+        if acceptor.join().is_err() {
+            warn!("the thread accepting workers panicked");
+        }
+
+        if let Some(e) = error {
+            sink.close()?;
+            return Err(e);
+        }
+        if !done {
+            sink.close()?;
+            return Err(PipelineError::CancelledError(shared.stats.snapshot().written).into());
+        }
+
+        sink.finish()
+    }
+}
+
+/// What the threads serving the workers share
+struct Shared<S> {
+    spec: WorkSpec,
+    keyspace: u128,
+    unit_size: u128,
+    unit_timeout: Duration,
+    /// Largest answer of a worker read, see [`WorkSpec::max_reply_len`]
+    max_reply_len: u64,
+    stats: Arc<Stats>,
+    state: Mutex<State<S>>,
+    /// Notified when a unit is done or handed back, and when the job stops
+    changed: Condvar,
+}
+
+struct State<S> {
+    /// `None` once the job stopped
+    sink: Option<S>,
+    /// Units before this one were handed out at least once
+    next_unit: u64,
+    units: u64,
+    /// Units of dead workers, handed out again before the next new one
+    handed_back: VecDeque<u64>,
+    /// Number of units a worker is hashing
+    assigned: usize,
+    /// Why the sink failed
+    error: Option<anyhow::Error>,
+}
+
+impl<S> State<S> {
+    fn is_done(&self) -> bool {
+        self.next_unit == self.units && self.handed_back.is_empty() && self.assigned == 0
+    }
+}
+
+impl<S: Sink + Send + 'static> Shared<S> {
+    /// Serves a worker that just connected on its own thread
+    fn spawn_worker(self: Arc<Self>, stream: TcpStream) {
+        std::thread::spawn(move || {
+            let peer = stream.peer_addr().map(|addr| addr.to_string());
+            // accepted streams may inherit the non-blocking mode of the listener
+            let served = stream
+                .set_nonblocking(false)
+                .map_err(anyhow::Error::from)
+                .and_then(|()| self.serve_worker(stream));
+            if let Err(e) = served {
+                let peer = peer.unwrap_or_default();
+                warn!("worker {peer} failed: {e:#}");
+            }
+        });
+    }
+}
+
+impl<S: Sink> Shared<S> {
+    fn lock(&self) -> MutexGuard<'_, State<S>> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Range of the keyspace of the unit
+    fn range(&self, unit: u64) -> KeyspaceRange {
+        let (start, end) = KeyspaceRange::new(unit as u128 * self.unit_size, Some(self.unit_size))
+            .bounds(self.keyspace);
+
+        KeyspaceRange::new(start, Some(end - start))
+    }
+
+    /// Waits for a unit to hand out, `None` once the job stopped or every unit is done
+    fn next_unit(&self) -> Option<u64> {
+        let mut state = self.lock();
+
+        loop {
+            if state.sink.is_none() || state.is_done() {
+                return None;
+            }
+
+            let unit = match state.handed_back.pop_front() {
+                Some(unit) => Some(unit),
+                None if state.next_unit < state.units => {
+                    state.next_unit += 1;
+                    Some(state.next_unit - 1)
+                }
+                // a worker may still die and hand its unit back
+                None => None,
+            };
+            if let Some(unit) = unit {
+                state.assigned += 1;
+                return Some(unit);
+            }
+
+            state = self.changed.wait(state).unwrap_or_else(|e| e.into_inner());
+        }
+    }
+
+    fn hand_back(&self, unit: u64) {
+        let mut state = self.lock();
+        state.assigned -= 1;
+        state.handed_back.push_back(unit);
+        drop(state);
+
+        self.changed.notify_all();
+    }
+
+    fn complete(&self, unit: u64, hashes: Vec<(String, Vec<Vec<u8>>)>) {
+        let range = self.range(unit);
+        let len = range.limit.unwrap_or_default();
+        let chunk = HashedChunk {
+            start: range.skip,
+            len,
+            hashes,
+        };
+
+        let mut state = self.lock();
+        state.assigned -= 1;
+        if let Some(sink) = &mut state.sink {
+            match sink.write_chunk(chunk) {
+                Ok(()) => {
+                    let len = len as u64;
+                    self.stats.add_generated(len);
+                    self.stats.add_hashed(len);
+                    self.stats.add_written(len);
+                }
+                Err(e) => state.error = Some(e),
+            }
+        }
+        drop(state);
+
+        self.changed.notify_all();
+    }
+
+    /// Hands units out to a single worker until there is none left
+    fn serve_worker(&self, stream: TcpStream) -> Result<()> {
+        stream.set_read_timeout(Some(self.unit_timeout))?;
+        let mut reader = BufReader::new(stream.try_clone()?);
+        let mut writer = BufWriter::new(stream);
+
+        send(&mut writer, &Message::Job(self.spec.clone()))?;
+
+        while let Some(unit) = self.next_unit() {
+            match self.work_on(unit, &mut reader, &mut writer) {
+                Ok(hashes) => self.complete(unit, hashes),
+                Err(e) => {
+                    self.hand_back(unit);
+                    return Err(e);
+                }
+            }
+        }
+
+        send(&mut writer, &Message::Done)
+    }
+
+    /// Sends the unit to the worker and waits for its hashes, which must be those of every
+    /// password of the unit
+    fn work_on(
+        &self,
+        unit: u64,
+        reader: &mut impl BufRead,
+        writer: &mut impl Write,
+    ) -> Result<Vec<(String, Vec<Vec<u8>>)>> {
+        let range = self.range(unit);
+        let limit = range.limit.unwrap_or_default();
+        send(
+            writer,
+            &Message::Unit {
+                id: unit,
+                skip: range.skip,
+                limit,
+            },
+        )?;
+
+        let hashes = match receive(reader, self.max_reply_len)? {
+            Some(Message::Hashes { id, hashes }) if id == unit => hashes,
+            message => {
+                let message = format!("{message:?}");
+                return Err(DistributedError::UnexpectedMessageError("worker", message).into());
+            }
+        };
+
+        if hashes.len() as u128 != limit {
+            return Err(DistributedError::PasswordCountError(hashes.len(), limit).into());
+        }
+
+        let algorithms = self.spec.algorithms.len();
+        let hashes = hashes
+            .into_iter()
+            .map(|(password, hashes)| {
+                if hashes.len() != algorithms {
+                    return Err(DistributedError::HashCountError(hashes.len(), algorithms).into());
+                }
+
+                let hashes = hashes
+                    .iter()
+                    .map(|hash| parse_digest(hash))
+                    .collect::<Result<_>>()?;
+                Ok((password, hashes))
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        self.check(range.skip, &hashes)?;
+        Ok(hashes)
+    }
+
+    /// Checks the passwords and hashes of a unit starting at `skip` at its first and last
+    /// position and evenly in between, as hashing all of them again would defeat the purpose
+    fn check(&self, skip: u128, hashes: &[(String, Vec<Vec<u8>>)]) -> Result<()> {
+        let len = hashes.len() as u128;
+        let mut generator = self.spec.generator()?;
+
+        for i in 0..CHECKED_PASSWORDS.min(len) {
+            let index = match CHECKED_PASSWORDS.min(len) {
+                1 => 0,
+                checked => i * (len - 1) / (checked - 1),
+            };
+            let (password, hashes) = &hashes[index as usize];
+
+            generator.seek(skip + index);
+            let expected = self
+                .spec
+                .algorithms
+                .iter()
+                .map(|algorithm| hash_candidate_uncached(*algorithm, password, self.spec.encoding))
+                .collect::<Result<Vec<_>>>()?;
+            if generator.next().as_ref() != Some(password) || expected != *hashes {
+                let password = password.clone();
+                return Err(DistributedError::WrongResultError(skip + index, password).into());
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Hashes the units handed out by a [`Coordinator`]
+pub struct Worker {
+    threads: NonZeroUsize,
+    stats: Arc<Stats>,
+}
+
+impl Worker {
+    /// Creates a new Worker, hashing on one thread per CPU
+    pub fn new() -> Self {
+        Self {
+            threads: std::thread::available_parallelism().unwrap_or(NonZeroUsize::MIN),
+            stats: Arc::default(),
+        }
+    }
+
+    /// Number of threads every unit is hashed on
+    pub fn threads(mut self, threads: NonZeroUsize) -> Self {
+        self.threads = threads;
+        self
+    }
+
+    /// Counters updated as units are hashed, over every unit
+    pub fn stats(mut self, stats: Arc<Stats>) -> Self {
+        self.stats = stats;
+        self
+    }
+
+    /// Hashes the units handed out on `stream` until the coordinator is done, returns the
+    /// number of units hashed. A cancelled worker stops in the middle of its unit, which the
+    /// coordinator hands out again.
+    pub fn run(&self, stream: TcpStream, cancel: &CancellationToken) -> Result<u64> {
+        let mut reader = BufReader::new(stream.try_clone()?);
+        let mut writer = BufWriter::new(stream);
+
+        let spec = match receive(&mut reader, MAX_COORDINATOR_MESSAGE_LEN)? {
+            Some(Message::Job(spec)) => spec,
+            Some(message) => {
+                let message = format!("{message:?}");
+                return Err(
+                    DistributedError::UnexpectedMessageError("coordinator", message).into(),
+                );
+            }
+            None => return Err(DistributedError::CoordinatorGoneError.into()),
+        };
+
+        let mut units = 0;
+        loop {
+            let (id, range) = match receive(&mut reader, MAX_COORDINATOR_MESSAGE_LEN)? {
+                Some(Message::Unit { id, skip, limit }) => {
+                    (id, KeyspaceRange::new(skip, Some(limit)))
+                }
+                Some(Message::Done) => return Ok(units),
+                Some(message) => {
+                    let message = format!("{message:?}");
+                    return Err(
+                        DistributedError::UnexpectedMessageError("coordinator", message).into(),
+                    );
+                }
+                None => return Err(DistributedError::CoordinatorGoneError.into()),
+            };
+
+            let source = GeneratorSource::new(spec.generator()?.in_range(range));
+            let hasher = CandidateHasher::new(spec.algorithms.clone(), spec.encoding);
+            let hashes = Pipeline::new(source, hasher, CollectSink::default())
+                .threads(self.threads)
+                .stats(self.stats.clone())
+                .run(cancel)?
+                .into_iter()
+                .map(|(password, hashes)| {
+                    let hashes = hashes.iter().map(|hash| vec_to_hex_string(hash)).collect();
+                    (password, hashes)
+                })
+                .collect();
+
+            send(&mut writer, &Message::Hashes { id, hashes }).with_context(|| {
+                format!("Cannot send the hashes of unit {id} to the coordinator")
+            })?;
+            units += 1;
+        }
+    }
+}
+
+impl Default for Worker {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Collects the hashes of a unit to send them to the coordinator
+#[derive(Default)]
+struct CollectSink(Vec<(String, Vec<Vec<u8>>)>);
+
+impl Sink for CollectSink {
+    type Output = Vec<(String, Vec<Vec<u8>>)>;
+
+    fn open(&mut self, _: &[HashAlgorithm], _: Option<Checkpointer>) -> Result<()> {
+        Ok(())
+    }
+
+    fn write_chunk(&mut self, chunk: HashedChunk) -> Result<()> {
+        self.0.extend(chunk.hashes);
+        Ok(())
+    }
+
+    fn finish(self) -> Result<Self::Output> {
+        Ok(self.0)
+    }
+
+    fn close(self) -> Result<()> {
+        Ok(())
+    }
+}
+
+fn send(writer: &mut impl Write, message: &Message) -> Result<()> {
+    serde_json::to_writer(&mut *writer, message)?;
+    writer.write_all(b"\n")?;
+    writer.flush()?;
+
+    Ok(())
+}
+
+/// Next message, `None` once the other side closed the connection. Fails on messages longer than
+/// `max_len` bytes instead of reading them whole.
+fn receive(reader: &mut impl BufRead, max_len: u64) -> Result<Option<Message>> {
+    let mut line = String::new();
+    let read = reader.by_ref().take(max_len).read_line(&mut line)?;
+    if read == 0 {
+        return Ok(None);
+    }
+    if read as u64 == max_len && !line.ends_with('\n') {
+        return Err(DistributedError::MessageTooLongError(max_len).into());
+    }
+
+    Ok(Some(serde_json::from_str(&line)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::cancel::CancellationToken;
+    use crate::distributed::{Coordinator, WorkSpec, Worker};
+    use crate::encoding::Encoding;
+    use crate::sink::TableSink;
+    use crate::HashAlgorithm;
+    use hex_literal::hex;
+    use std::io::{BufRead, BufReader, Write};
+    use std::net::{TcpListener, TcpStream};
+    use std::num::NonZeroUsize;
+
+    #[test]
+    fn test_units_of_dead_workers_are_handed_out_again() {
+        let spec = WorkSpec {
+            min_chars: 1,
+            max_chars: 2,
+            chars: ('0'..='9').collect(),
+            algorithms: vec![HashAlgorithm::Md5],
+            encoding: Encoding::Latin1,
+        };
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let cancel = CancellationToken::new();
+
+        let coordinator = std::thread::spawn(move || {
+            Coordinator::new(spec, TableSink::new())
+                .unit_size(NonZeroUsize::new(7).unwrap())
+                .run(listener, &cancel)
+        });
+
+        // a worker that takes a unit and dies without answering
+        let stream = TcpStream::connect(addr).unwrap();
+        let mut reader = BufReader::new(stream.try_clone().unwrap());
+        let mut line = String::new();
+        reader.read_line(&mut line).unwrap();
+        assert!(line.starts_with(r#"{"job":"#));
+        line.clear();
+        reader.read_line(&mut line).unwrap();
+        assert_eq!(line, "{\"unit\":{\"id\":0,\"skip\":0,\"limit\":7}}\n");
+        (&stream).write_all(b"{\"hashes\":").unwrap();
+        drop((reader, stream));
+
+        let workers = (0..2)
+            .map(|_| {
+                std::thread::spawn(move || {
+                    let stream = TcpStream::connect(addr).unwrap();
+                    Worker::new()
+                        .threads(NonZeroUsize::new(2).unwrap())
+                        .run(stream, &CancellationToken::new())
+                        .unwrap()
+                })
+            })
+            .collect::<Vec<_>>();
+        let units = workers
+            .into_iter()
+            .map(|worker| worker.join().unwrap())
+            .sum::<u64>();
+
+        // 110 passwords in units of 7
+        assert_eq!(units, 16);
+        let table = coordinator.join().unwrap().unwrap();
+        assert_eq!(table.len(), 110);
+        // the listener is closed once the job is done
+        assert!(TcpStream::connect(addr).is_err());
+        assert_eq!(
+            table.lookup(
+                HashAlgorithm::Md5,
+                &hex!("cfcd208495d565ef66e7dff9f98764da")
+            ),
+            Some("0")
+        );
+        assert_eq!(
+            table.lookup(
+                HashAlgorithm::Md5,
+                &hex!("ac627ab1ccbdb62ec96e702f07f6425b")
+            ),
+            Some("99")
+        );
+    }
+
+    #[test]
+    fn test_wrong_answers_are_handed_out_again() {
+        let spec = WorkSpec {
+            min_chars: 1,
+            max_chars: 1,
+            chars: ('0'..='9').collect(),
+            algorithms: vec![HashAlgorithm::Md5],
+            encoding: Encoding::Latin1,
+        };
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let cancel = CancellationToken::new();
+
+        let coordinator = std::thread::spawn(move || {
+            Coordinator::new(spec, TableSink::new())
+                .unit_size(NonZeroUsize::new(4).unwrap())
+                .run(listener, &cancel)
+        });
+
+        let zero = "0".repeat(32);
+        let answers = [
+            // no passwords at all
+            "{\"hashes\":{\"id\":0,\"hashes\":[]}}\n".to_owned(),
+            // the right passwords with the wrong hashes
+            format!(
+                "{{\"hashes\":{{\"id\":0,\"hashes\":[[\"0\",[\"{zero}\"]],[\"1\",[\"{zero}\"]],\
+                 [\"2\",[\"{zero}\"]],[\"3\",[\"{zero}\"]]]}}}}\n"
+            ),
+        ];
+        for answer in answers {
+            let stream = TcpStream::connect(addr).unwrap();
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let mut line = String::new();
+            reader.read_line(&mut line).unwrap();
+            line.clear();
+            reader.read_line(&mut line).unwrap();
+            assert_eq!(line, "{\"unit\":{\"id\":0,\"skip\":0,\"limit\":4}}\n");
+            (&stream).write_all(answer.as_bytes()).unwrap();
+
+            // the coordinator hangs up on the worker
+            line.clear();
+            assert_eq!(reader.read_line(&mut line).unwrap(), 0);
+        }
+
+        let stream = TcpStream::connect(addr).unwrap();
+        let units = Worker::new()
+            .threads(NonZeroUsize::new(2).unwrap())
+            .run(stream, &CancellationToken::new())
+            .unwrap();
+
+        assert_eq!(units, 3);
+        let table = coordinator.join().unwrap().unwrap();
+        assert_eq!(table.len(), 10);
+        assert_eq!(
+            table.lookup(
+                HashAlgorithm::Md5,
+                &hex!("cfcd208495d565ef66e7dff9f98764da")
+            ),
+            Some("0")
+        );
+    }
+
+    #[test]
+    fn test_unencodable_chars_fail_the_job() {
+        let spec = WorkSpec {
+            min_chars: 1,
+            max_chars: 2,
+            chars: vec!['a', '€'],
+            algorithms: vec![HashAlgorithm::Md5],
+            encoding: Encoding::Latin1,
+        };
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+
+        let error = Coordinator::new(spec, TableSink::new())
+            .run(listener, &CancellationToken::new())
+            .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Character `€` cannot be encoded in latin-1"
+        );
+    }
+}
@@ -0,0 +1,164 @@
+//! Turning candidates into the exact bytes a target system hashed.
+//!
+//! Candidates are kept as UTF-8 text everywhere else, one per line. Candidates that are not
+//! printable ASCII are written in hashcat's `$HEX[...]` notation, so that arbitrary bytes
+//! survive being written to and read back from a wordlist.
+use crate::errors::EncodingError;
+use crate::utils::{hex_string_to_vec, vec_to_hex_string};
+use serde::{Deserialize, Serialize};
+
+const HEX_PREFIX: &str = "$HEX[";
+const HEX_SUFFIX: &str = "]";
+
+/// How the characters of a candidate are turned into bytes before hashing
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Encoding {
+    /// UTF-8, what most modern systems use
+    #[default]
+    Utf8,
+
+    /// UTF-16 little endian, e.g. NTLM and other Windows hashes
+    Utf16Le,
+
+    /// ISO-8859-1, one byte per character, only characters up to `U+00FF`
+    Latin1,
+}
+
+impl Encoding {
+    /// Encodes `password` into bytes, fails if a character has no representation in this encoding
+    pub fn encode(&self, password: &str) -> Result<Vec<u8>, EncodingError> {
+        match self {
+            Encoding::Utf8 => Ok(password.as_bytes().to_vec()),
+            Encoding::Utf16Le => Ok(password
+                .encode_utf16()
+                .flat_map(|unit| unit.to_le_bytes())
+                .collect()),
+            Encoding::Latin1 => password
+                .chars()
+                .map(|c| {
+                    u8::try_from(c).map_err(|_| EncodingError::UnencodableCharError(c, "latin-1"))
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Bytes to hash for a line of a wordlist: the raw bytes of a `$HEX[...]` line,
+/// otherwise the line encoded with `encoding`.
+pub fn candidate_bytes(line: &str, encoding: Encoding) -> Result<Vec<u8>, EncodingError> {
+    match from_hex_notation(line) {
+        Some(bytes) => Ok(bytes),
+        None => encoding.encode(line),
+    }
+}
+
+/// Decodes a line in `$HEX[...]` notation, `None` if the line is not in that notation
+pub fn from_hex_notation(line: &str) -> Option<Vec<u8>> {
+    let hex = line.strip_prefix(HEX_PREFIX)?.strip_suffix(HEX_SUFFIX)?;
+
+    if hex.len() % 2 != 0 || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+
+    Some(hex_string_to_vec(hex))
+}
+
+/// Turns a candidate into a line of a wordlist, using `$HEX[...]` notation unless every byte
+/// is printable ASCII.
+pub fn to_hex_notation(bytes: &[u8]) -> String {
+    let printable = bytes.iter().all(|b| (b' '..=b'~').contains(b));
+
+    match std::str::from_utf8(bytes) {
+        Ok(line) if printable && from_hex_notation(line).is_none() => line.to_owned(),
+        _ => format!("{HEX_PREFIX}{}{HEX_SUFFIX}", vec_to_hex_string(bytes)),
+    }
+}
+
+/// Parses a byte alphabet made of comma separated hex bytes and ranges, e.g. `00-1f,7f,ff`.
+/// Duplicate bytes are only kept once, in the order they first appear.
+pub fn parse_byte_alphabet(alphabet: &str) -> Result<Vec<u8>, EncodingError> {
+    let invalid = || EncodingError::InvalidByteAlphabetError(alphabet.to_owned());
+    let parse_byte = |hex: &str| {
+        let hex = hex.trim();
+        if hex.len() != 2 {
+            return Err(invalid());
+        }
+        u8::from_str_radix(hex, 16).map_err(|_| invalid())
+    };
+
+    let mut bytes = vec![];
+    for entry in alphabet.split(',') {
+        let (first, last) = match entry.split_once('-') {
+            Some((first, last)) => (parse_byte(first)?, parse_byte(last)?),
+            None => (parse_byte(entry)?, parse_byte(entry)?),
+        };
+
+        if first > last {
+            return Err(invalid());
+        }
+
+        for byte in first..=last {
+            if !bytes.contains(&byte) {
+                bytes.push(byte);
+            }
+        }
+    }
+
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::encoding::{
+        candidate_bytes, from_hex_notation, parse_byte_alphabet, to_hex_notation, Encoding,
+    };
+
+    #[test]
+    fn test_encodings() {
+        assert_eq!(Encoding::Utf8.encode("pä").unwrap(), vec![0x70, 0xc3, 0xa4]);
+        assert_eq!(
+            Encoding::Utf16Le.encode("pä").unwrap(),
+            vec![0x70, 0x00, 0xe4, 0x00]
+        );
+        assert_eq!(Encoding::Latin1.encode("pä").unwrap(), vec![0x70, 0xe4]);
+        assert!(Encoding::Latin1.encode("p€").is_err());
+    }
+
+    #[test]
+    fn test_hex_notation() {
+        assert_eq!(to_hex_notation(b"abc"), "abc");
+        assert_eq!(to_hex_notation(&[0x61, 0x00, 0xff]), "$HEX[6100ff]");
+        assert_eq!(to_hex_notation("ä".as_bytes()), "$HEX[c3a4]");
+        // a candidate that looks like hex notation must not be decoded when read back
+        assert_eq!(to_hex_notation(b"$HEX[61]"), "$HEX[244845585b36315d]");
+
+        assert_eq!(
+            from_hex_notation("$HEX[6100ff]"),
+            Some(vec![0x61, 0x00, 0xff])
+        );
+        assert_eq!(from_hex_notation("$HEX[610]"), None);
+        assert_eq!(from_hex_notation("abc"), None);
+
+        assert_eq!(
+            candidate_bytes("$HEX[ff]", Encoding::Utf16Le).unwrap(),
+            vec![0xff]
+        );
+        assert_eq!(
+            candidate_bytes("a", Encoding::Utf16Le).unwrap(),
+            vec![0x61, 0x00]
+        );
+    }
+
+    #[test]
+    fn test_parse_byte_alphabet() {
+        assert_eq!(
+            parse_byte_alphabet("00-02,ff,01").unwrap(),
+            vec![0x00, 0x01, 0x02, 0xff]
+        );
+        assert_eq!(parse_byte_alphabet("00-ff").unwrap().len(), 256);
+        assert!(parse_byte_alphabet("ff-00").is_err());
+        assert!(parse_byte_alphabet("0").is_err());
+        assert!(parse_byte_alphabet("zz").is_err());
+    }
+}
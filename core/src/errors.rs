@@ -1,3 +1,6 @@
+// every error enum names its variants `...Error`
+#![allow(clippy::enum_variant_names)]
+
 use thiserror::Error;
 
 #[derive(Error, Debug)]
@@ -6,4 +9,87 @@ pub enum GenPasswordsError {
     MinGreaterThanMaxError(usize, usize),
     #[error("Minimum length cannot be 0")]
     InvalidMinLengthError(usize),
+    #[error("Mask cannot be empty")]
+    EmptyMaskError,
+    #[error("Unknown mask placeholder `?{0}`, expected one of `?l`, `?u`, `?d`, `?s`, `?a` or `??`")]
+    InvalidMaskPlaceholderError(char),
+    #[error("Mask cannot end with an incomplete placeholder `?`")]
+    IncompleteMaskError,
+    #[error("Part must be between 1 and the number of parts, Received Part: {0}, Parts: {1}")]
+    InvalidPartError(u64, u64),
+}
+
+#[derive(Error, Debug)]
+pub enum CheckpointError {
+    #[error(
+        "Checkpoint was written with a chunk size of {0}, cannot resume with a chunk size of {1}"
+    )]
+    ChunkSizeMismatchError(u64, u64),
+    #[error("Checkpoint was written by the job `{0}`, cannot resume it as the job `{1}`")]
+    JobMismatchError(String, String),
+    #[error("Output file is shorter than recorded in the checkpoint, Expected: {0} bytes, Found: {1} bytes")]
+    OutputTooShortError(u64, u64),
+    #[error("Checkpoint was written for {0} output files, cannot resume writing {1} output files")]
+    OutputCountMismatchError(usize, usize),
+    #[error("Only jobs writing their hashes to a file or dropping them can be checkpointed")]
+    NotResumableError,
+}
+
+#[derive(Error, Debug)]
+pub enum EncodingError {
+    #[error("Character `{0}` cannot be encoded in {1}")]
+    UnencodableCharError(char, &'static str),
+    #[error("Invalid byte alphabet `{0}`, expected hex bytes or ranges like `00-1f,7f`")]
+    InvalidByteAlphabetError(String),
+}
+
+#[derive(Error, Debug)]
+pub enum PipelineError {
+    #[error("Failed to hash {0} {1} `{2}`")]
+    InvalidCandidateError(
+        &'static str,
+        u128,
+        String,
+        #[source] Box<dyn std::error::Error + Send + Sync>,
+    ),
+    #[error("A thread of the pipeline panicked")]
+    WorkerPanicError,
+    #[error("Cancelled after {0} passwords")]
+    CancelledError(u64),
+}
+
+#[derive(Error, Debug)]
+pub enum ServerError {
+    #[error("Unknown command `{0}`, expected `HASH`, `LOOKUP` or `STATS`")]
+    UnknownCommandError(String),
+    #[error("Unknown algorithm `{0}`")]
+    UnknownAlgorithmError(String),
+    #[error("Missing {0}")]
+    MissingArgumentError(&'static str),
+    #[error("Invalid hex digest `{0}`")]
+    InvalidDigestError(String),
+    #[error("A job needs at least one algorithm")]
+    NoAlgorithmError,
+    #[error("Request is longer than {0} bytes")]
+    RequestTooLongError(usize),
+    #[error("Request is not valid UTF-8, send other bytes as `$HEX[...]`")]
+    InvalidUtf8Error,
+}
+
+#[derive(Error, Debug)]
+pub enum DistributedError {
+    #[error("The keyspace is too large to be split into work units")]
+    KeyspaceTooLargeError,
+    #[error("Unexpected message from the {0}: `{1}`")]
+    UnexpectedMessageError(&'static str, String),
+    #[error("Received {0} hashes per password, expected {1}")]
+    HashCountError(usize, usize),
+    #[error("Received {0} passwords for a unit of {1}")]
+    PasswordCountError(usize, u128),
+    #[error("Received a wrong password or hashes at position {0} of the keyspace: `{1}`")]
+    WrongResultError(u128, String),
+    #[error("Message is longer than {0} bytes")]
+    MessageTooLongError(u64),
+    #[error("The coordinator closed the connection before the job was done")]
+    CoordinatorGoneError,
 }
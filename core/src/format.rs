@@ -0,0 +1,163 @@
+//! Formats of the records written by [`crate::sink::FileSink`], one record per password.
+use crate::utils::vec_to_hex_string;
+use crate::HashAlgorithm;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// How every hash is written to the output file
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputFormat {
+    /// Digest bytes back to back, without any delimiter
+    #[default]
+    Raw,
+
+    /// Hex encoded digest, one per line
+    Hex,
+
+    /// `hash:password`, one per line, like a hashcat potfile
+    Potfile,
+
+    /// `hash,password` with a header line
+    Csv,
+
+    /// One JSON object per line with the algorithm, hash and password
+    Jsonl,
+}
+
+#[derive(Serialize)]
+struct JsonRecord<'a> {
+    algorithm: &'static str,
+    hash: &'a str,
+    password: &'a str,
+}
+
+#[derive(Serialize)]
+struct CombinedJsonRecord<'a> {
+    password: &'a str,
+    hashes: BTreeMap<&'static str, String>,
+}
+
+impl OutputFormat {
+    /// Whether the hashes of every algorithm are written to a single output file,
+    /// instead of one output file per algorithm
+    pub fn is_combined(&self, algorithms: &[HashAlgorithm]) -> bool {
+        algorithms.len() == 1 || *self == OutputFormat::Jsonl
+    }
+
+    /// Written once at the start of the output file
+    pub fn header(&self) -> Option<&'static str> {
+        match self {
+            OutputFormat::Csv => Some("hash,password\n"),
+            _ => None,
+        }
+    }
+
+    /// Appends the record of `password` and its `hash` to `out`
+    pub fn write_record(
+        &self,
+        out: &mut Vec<u8>,
+        algorithm: HashAlgorithm,
+        hash: &[u8],
+        password: &str,
+    ) {
+        let line = match self {
+            OutputFormat::Raw => return out.extend_from_slice(hash),
+            OutputFormat::Hex => vec_to_hex_string(hash),
+            OutputFormat::Potfile => format!("{}:{}", vec_to_hex_string(hash), password),
+            OutputFormat::Csv => format!("{},{}", vec_to_hex_string(hash), csv_field(password)),
+            OutputFormat::Jsonl => serde_json::to_string(&JsonRecord {
+                algorithm: algorithm.name(),
+                hash: &vec_to_hex_string(hash),
+                password,
+            })
+            .expect("a record is always valid JSON"),
+        };
+
+        out.extend_from_slice(line.as_bytes());
+        out.push(b'\n');
+    }
+
+    /// Appends a single JSON record with the hash of `password` for every algorithm to `out`,
+    /// `hashes` are in the same order as `algorithms`
+    pub fn write_combined_record(
+        out: &mut Vec<u8>,
+        algorithms: &[HashAlgorithm],
+        hashes: &[Vec<u8>],
+        password: &str,
+    ) {
+        let record = CombinedJsonRecord {
+            password,
+            hashes: algorithms
+                .iter()
+                .zip(hashes)
+                .map(|(algorithm, hash)| (algorithm.name(), vec_to_hex_string(hash)))
+                .collect(),
+        };
+
+        out.extend_from_slice(
+            serde_json::to_string(&record)
+                .expect("a record is always valid JSON")
+                .as_bytes(),
+        );
+        out.push(b'\n');
+    }
+}
+
+/// Quotes a CSV field if it contains a delimiter, a quote or surrounding spaces
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\r', '\n']) || field.trim() != field {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_owned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::format::OutputFormat;
+    use crate::HashAlgorithm;
+
+    fn record(format: OutputFormat, password: &str) -> String {
+        String::from_utf8(raw_record(format, password)).unwrap()
+    }
+
+    fn raw_record(format: OutputFormat, password: &str) -> Vec<u8> {
+        let mut out = vec![];
+        format.write_record(&mut out, HashAlgorithm::Md5, &[0xab, 0x01], password);
+        out
+    }
+
+    #[test]
+    fn test_output_formats() {
+        assert_eq!(raw_record(OutputFormat::Raw, "pw"), [0xab, 0x01]);
+        assert_eq!(record(OutputFormat::Hex, "pw"), "ab01\n");
+        assert_eq!(record(OutputFormat::Potfile, "p:w"), "ab01:p:w\n");
+        assert_eq!(record(OutputFormat::Csv, "pw"), "ab01,pw\n");
+        assert_eq!(
+            record(OutputFormat::Csv, "p,\"w\""),
+            "ab01,\"p,\"\"w\"\"\"\n"
+        );
+        assert_eq!(
+            record(OutputFormat::Jsonl, "p\"w"),
+            "{\"algorithm\":\"md5\",\"hash\":\"ab01\",\"password\":\"p\\\"w\"}\n"
+        );
+        assert_eq!(OutputFormat::Csv.header(), Some("hash,password\n"));
+        assert_eq!(OutputFormat::Hex.header(), None);
+    }
+
+    #[test]
+    fn test_combined_record() {
+        let algorithms = [HashAlgorithm::Sha2, HashAlgorithm::Md5];
+        let mut out = vec![];
+        OutputFormat::write_combined_record(&mut out, &algorithms, &[vec![0xcd], vec![0xab]], "pw");
+
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "{\"password\":\"pw\",\"hashes\":{\"md5\":\"ab\",\"sha2\":\"cd\"}}\n"
+        );
+        assert!(OutputFormat::Jsonl.is_combined(&algorithms));
+        assert!(!OutputFormat::Hex.is_combined(&algorithms));
+        assert!(OutputFormat::Hex.is_combined(&algorithms[..1]));
+    }
+}
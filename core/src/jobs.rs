@@ -0,0 +1,464 @@
+//! Jobs submitted over the network, every job runs a [`Pipeline`] in a thread of its own.
+//!
+//! A job is known by the id it gets when submitted, its status can be asked for until it is
+//! removed. Removing a job that is still running stops it first. Only the last
+//! [`DEFAULT_MAX_FINISHED_JOBS`] jobs to end are kept, older finished jobs are forgotten.
+use crate::cancel::CancellationToken;
+use crate::encoding::Encoding;
+use crate::errors::{PipelineError, ServerError};
+use crate::format::OutputFormat;
+use crate::keyspace::KeyspaceRange;
+use crate::mask::MaskGenerator;
+use crate::output::{Compression, OutputOptions};
+use crate::passwords::{CharSet, PasswordGenerator};
+use crate::pipeline::{CandidateHasher, Pipeline};
+use crate::sink::FileSink;
+use crate::source::{FileSource, GeneratorSource};
+use crate::stats::{ProgressReport, Stats};
+use crate::{HashAlgorithm, GENERATOR_CHUNK_SIZE};
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::num::NonZeroUsize;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+/// Finished jobs kept by default, see [`Jobs::max_finished_jobs`]
+pub const DEFAULT_MAX_FINISHED_JOBS: usize = 1000;
+
+/// What a job does. Every job hashes its candidates into `out_path`, with every algorithm.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum JobSpec {
+    /// Hashes every line of the wordlist at `in_path`
+    HashFile {
+        in_path: String,
+        out_path: String,
+        algorithms: Vec<HashAlgorithm>,
+        #[serde(default)]
+        encoding: Encoding,
+        #[serde(default)]
+        format: OutputFormat,
+    },
+
+    /// Hashes every password matching `mask` if there is one, otherwise every combination of
+    /// letters and digits between `min_chars` and `max_chars`
+    Generate {
+        mask: Option<String>,
+        #[serde(default = "default_min_chars")]
+        min_chars: usize,
+        #[serde(default = "default_max_chars")]
+        max_chars: usize,
+        out_path: String,
+        algorithms: Vec<HashAlgorithm>,
+        #[serde(default)]
+        encoding: Encoding,
+        #[serde(default)]
+        format: OutputFormat,
+    },
+}
+
+fn default_min_chars() -> usize {
+    1
+}
+
+fn default_max_chars() -> usize {
+    4
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum JobState {
+    Running,
+    Done,
+    Failed,
+    Cancelled,
+}
+
+/// Where a job is at
+#[derive(Debug, Clone, Serialize)]
+pub struct JobStatus {
+    pub id: u64,
+    pub state: JobState,
+    /// Why the job failed
+    pub error: Option<String>,
+    pub progress: ProgressReport,
+}
+
+struct Job {
+    cancel: CancellationToken,
+    stats: Arc<Stats>,
+    started: Instant,
+    total: Option<u64>,
+    /// `None` while the job is running
+    end: Mutex<Option<JobEnd>>,
+    thread: Mutex<Option<JoinHandle<()>>>,
+}
+
+struct JobEnd {
+    state: JobState,
+    error: Option<String>,
+    elapsed: Duration,
+}
+
+impl Job {
+    /// When the job ended, `None` while it is running
+    fn ended(&self) -> Option<Instant> {
+        let end = self.end.lock().unwrap_or_else(|e| e.into_inner());
+        end.as_ref().map(|end| self.started + end.elapsed)
+    }
+
+    /// Stops the job if it is still running and waits for its thread
+    fn stop(&self) {
+        self.cancel.cancel();
+        let thread = self.thread.lock().unwrap_or_else(|e| e.into_inner()).take();
+        if let Some(thread) = thread {
+            // the job records how it ended itself, a panic leaves it running forever
+            if thread.join().is_err() {
+                *self.end.lock().unwrap_or_else(|e| e.into_inner()) = Some(JobEnd {
+                    state: JobState::Failed,
+                    error: Some(PipelineError::WorkerPanicError.to_string()),
+                    elapsed: self.started.elapsed(),
+                });
+            }
+        }
+    }
+
+    fn status(&self, id: u64) -> JobStatus {
+        let end = self.end.lock().unwrap_or_else(|e| e.into_inner());
+        let (state, error, elapsed) = match &*end {
+            Some(end) => (end.state, end.error.clone(), end.elapsed),
+            None => (JobState::Running, None, self.started.elapsed()),
+        };
+
+        JobStatus {
+            id,
+            state,
+            error,
+            progress: ProgressReport::new(&self.stats.snapshot(), elapsed, self.total, 0),
+        }
+    }
+}
+
+/// Every job submitted and not removed yet
+pub struct Jobs {
+    threads: NonZeroUsize,
+    max_finished_jobs: usize,
+    next_id: AtomicU64,
+    jobs: Mutex<HashMap<u64, Arc<Job>>>,
+}
+
+impl Jobs {
+    /// Creates a new Jobs
+    /// Requires:
+    /// - threads: Number of threads every job hashes on
+    pub fn new(threads: NonZeroUsize) -> Self {
+        Self {
+            threads,
+            max_finished_jobs: DEFAULT_MAX_FINISHED_JOBS,
+            next_id: AtomicU64::new(1),
+            jobs: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Number of finished jobs whose status is kept. When a job is submitted, the finished jobs
+    /// that ended first are forgotten beyond it, so that the jobs of a long running server do not
+    /// pile up.
+    pub fn max_finished_jobs(mut self, max_finished_jobs: usize) -> Self {
+        self.max_finished_jobs = max_finished_jobs;
+        self
+    }
+
+    /// Starts a job and returns its status. Nothing is started if the job cannot start,
+    /// e.g. when its wordlist does not exist.
+    pub fn submit(&self, spec: JobSpec) -> Result<JobStatus> {
+        let stats = Arc::new(Stats::new());
+        let (pipeline, total) = self.pipeline(spec)?;
+        let pipeline = pipeline.stats(stats.clone());
+
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let job = Arc::new(Job {
+            cancel: CancellationToken::new(),
+            stats,
+            started: Instant::now(),
+            total,
+            end: Mutex::new(None),
+            thread: Mutex::new(None),
+        });
+
+        let thread = std::thread::spawn({
+            let job = job.clone();
+            move || {
+                let (state, error) = match pipeline.run(&job.cancel) {
+                    Ok(()) => (JobState::Done, None),
+                    Err(e) => match e.downcast_ref() {
+                        Some(PipelineError::CancelledError(_)) => (JobState::Cancelled, None),
+                        _ => (JobState::Failed, Some(format!("{e:#}"))),
+                    },
+                };
+
+                *job.end.lock().unwrap_or_else(|e| e.into_inner()) = Some(JobEnd {
+                    state,
+                    error,
+                    elapsed: job.started.elapsed(),
+                });
+            }
+        });
+        *job.thread.lock().unwrap_or_else(|e| e.into_inner()) = Some(thread);
+
+        let status = job.status(id);
+        let mut jobs = self.lock();
+        jobs.insert(id, job);
+        forget_finished(&mut jobs, self.max_finished_jobs);
+
+        Ok(status)
+    }
+
+    /// Status of the job with the given id, `None` if there is no such job
+    pub fn status(&self, id: u64) -> Option<JobStatus> {
+        let job = self.lock().get(&id).cloned()?;
+
+        Some(job.status(id))
+    }
+
+    /// Stops the job with the given id if it is still running, waits for it and forgets it.
+    /// Returns its last status, `None` if there is no such job.
+    pub fn remove(&self, id: u64) -> Option<JobStatus> {
+        let job = self.lock().remove(&id)?;
+        job.stop();
+
+        Some(job.status(id))
+    }
+
+    /// Stops every job that is still running, waits for them and forgets every job.
+    /// Stopped jobs write what they hashed so far and close their output files.
+    pub fn shutdown(&self) {
+        let jobs = self.lock().drain().map(|(_, job)| job).collect::<Vec<_>>();
+
+        // every job is asked to stop before waiting for the first one, so that they stop together
+        for job in &jobs {
+            job.cancel.cancel();
+        }
+        for job in jobs {
+            job.stop();
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<u64, Arc<Job>>> {
+        self.jobs.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Pipeline running a job, and its number of candidates if known
+    fn pipeline(&self, spec: JobSpec) -> Result<(Pipeline<FileSink>, Option<u64>)> {
+        let (pipeline, total) = match spec {
+            JobSpec::HashFile {
+                in_path,
+                out_path,
+                algorithms,
+                encoding,
+                format,
+            } => {
+                let source = FileSource::open(&in_path, KeyspaceRange::new(0, None))?;
+                let hasher = candidate_hasher(algorithms, encoding)?;
+                let sink = file_sink(&out_path, format);
+
+                (Pipeline::new(source, hasher, sink), None)
+            }
+            JobSpec::Generate {
+                mask,
+                min_chars,
+                max_chars,
+                out_path,
+                algorithms,
+                encoding,
+                format,
+            } => {
+                let hasher = candidate_hasher(algorithms, encoding)?;
+                let sink = file_sink(&out_path, format);
+
+                let (pipeline, keyspace) = match mask {
+                    Some(mask) => {
+                        let generator = MaskGenerator::new(&mask)?;
+                        let keyspace = generator.keyspace();
+                        let source = GeneratorSource::new(generator);
+                        (Pipeline::new(source, hasher, sink), keyspace)
+                    }
+                    None => {
+                        let char_set = CharSet::LowerAlpha | CharSet::Numeric | CharSet::UpperAlpha;
+                        let generator = PasswordGenerator::new(min_chars, max_chars, char_set)?;
+                        let keyspace = generator.keyspace();
+                        let source = GeneratorSource::new(generator);
+                        (Pipeline::new(source, hasher, sink), keyspace)
+                    }
+                };
+                let chunk_size =
+                    NonZeroUsize::new(GENERATOR_CHUNK_SIZE).unwrap_or(NonZeroUsize::MIN);
+
+                (
+                    pipeline.chunk_size(chunk_size),
+                    keyspace.and_then(|keyspace| u64::try_from(keyspace).ok()),
+                )
+            }
+        };
+
+        Ok((pipeline.threads(self.threads), total))
+    }
+}
+
+/// Forgets the finished jobs that ended first, so that at most `max` finished jobs are left
+fn forget_finished(jobs: &mut HashMap<u64, Arc<Job>>, max: usize) {
+    let mut finished = jobs
+        .iter()
+        .filter_map(|(id, job)| Some((job.ended()?, *id)))
+        .collect::<Vec<_>>();
+    if finished.len() <= max {
+        return;
+    }
+
+    finished.sort_unstable();
+    for (_, id) in &finished[..finished.len() - max] {
+        jobs.remove(id);
+    }
+}
+
+fn candidate_hasher(algorithms: Vec<HashAlgorithm>, encoding: Encoding) -> Result<CandidateHasher> {
+    if algorithms.is_empty() {
+        return Err(ServerError::NoAlgorithmError.into());
+    }
+
+    let mut unique = Vec::new();
+    for algorithm in algorithms {
+        if !unique.contains(&algorithm) {
+            unique.push(algorithm);
+        }
+    }
+
+    Ok(CandidateHasher::new(unique, encoding))
+}
+
+fn file_sink(out_path: &str, format: OutputFormat) -> FileSink {
+    let options = OutputOptions {
+        format,
+        compression: Compression::from_path(out_path),
+    };
+
+    FileSink::new(out_path, options)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::jobs::{JobSpec, JobState, Jobs};
+    use flate2::read::MultiGzDecoder;
+    use std::io::Read;
+    use std::num::NonZeroUsize;
+    use std::time::Duration;
+
+    #[test]
+    fn test_generate_job() {
+        let dir = tempfile::tempdir().unwrap();
+        let out_path = dir.path().join("hashes.txt");
+
+        let spec = serde_json::json!({
+            "kind": "generate",
+            "mask": "?d?d",
+            "out_path": out_path,
+            "algorithms": ["md5", "md5"],
+            "encoding": "latin1",
+            "format": "hex",
+        });
+        let spec = serde_json::from_value::<JobSpec>(spec).unwrap();
+
+        let jobs = Jobs::new(NonZeroUsize::new(2).unwrap());
+        let id = jobs.submit(spec).unwrap().id;
+
+        let mut status = jobs.status(id).unwrap();
+        while status.state == JobState::Running {
+            std::thread::sleep(Duration::from_millis(10));
+            status = jobs.status(id).unwrap();
+        }
+
+        assert_eq!(status.state, JobState::Done);
+        assert_eq!(status.progress.processed, 100);
+        assert_eq!(status.progress.total, Some(100));
+        let hashes = std::fs::read_to_string(&out_path).unwrap();
+        assert_eq!(hashes.lines().count(), 100);
+
+        assert!(jobs.remove(id).is_some());
+        assert!(jobs.status(id).is_none());
+    }
+
+    #[test]
+    fn test_job_that_cannot_start() {
+        let spec = JobSpec::HashFile {
+            in_path: "does/not/exist.txt".to_owned(),
+            out_path: "unused.txt".to_owned(),
+            algorithms: Vec::new(),
+            encoding: Default::default(),
+            format: Default::default(),
+        };
+
+        let jobs = Jobs::new(NonZeroUsize::MIN);
+        assert!(jobs.submit(spec).is_err());
+        assert!(jobs.status(1).is_none());
+    }
+
+    fn generate(mask: &str, out_path: &std::path::Path) -> JobSpec {
+        let spec = serde_json::json!({
+            "kind": "generate",
+            "mask": mask,
+            "out_path": out_path,
+            "algorithms": ["md5"],
+            "encoding": "latin1",
+            "format": "hex",
+        });
+
+        serde_json::from_value(spec).unwrap()
+    }
+
+    #[test]
+    fn test_shutdown_closes_output_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let out_path = dir.path().join("hashes.txt.gz");
+
+        let jobs = Jobs::new(NonZeroUsize::new(2).unwrap());
+        let id = jobs.submit(generate("?a?a?a?a?a", &out_path)).unwrap().id;
+        while jobs.status(id).unwrap().progress.processed == 0 {
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        jobs.shutdown();
+        assert!(jobs.status(id).is_none());
+
+        // the gzip stream is finished, and every hash written so far is in it
+        let mut hashes = String::new();
+        MultiGzDecoder::new(std::fs::File::open(&out_path).unwrap())
+            .read_to_string(&mut hashes)
+            .unwrap();
+        assert!(!hashes.is_empty());
+        assert!(hashes.ends_with('\n'));
+    }
+
+    #[test]
+    fn test_finished_jobs_are_forgotten() {
+        let dir = tempfile::tempdir().unwrap();
+
+        let jobs = Jobs::new(NonZeroUsize::MIN).max_finished_jobs(1);
+        let mut ids = Vec::new();
+        for i in 0..3 {
+            let id = jobs
+                .submit(generate("?d", &dir.path().join(format!("{i}.txt"))))
+                .unwrap()
+                .id;
+            while jobs.status(id).unwrap().state == JobState::Running {
+                std::thread::sleep(Duration::from_millis(10));
+            }
+            ids.push(id);
+        }
+
+        // the last job ended after the last submission, the one before it is kept as well
+        assert!(jobs.status(ids[0]).is_none());
+        assert!(jobs.status(ids[1]).is_some());
+        assert!(jobs.status(ids[2]).is_some());
+    }
+}
@@ -0,0 +1,172 @@
+//! Splitting the keyspace of candidate generators into disjoint slices, so that work can be
+//! distributed across machines.
+use crate::errors::GenPasswordsError;
+
+/// A candidate generator that can jump to any index of its keyspace without generating every
+/// password in between.
+pub trait SeekableGenerator: Iterator<Item = String> {
+    /// Number of passwords this generator produces from start to finish.
+    /// Returns `None` if there are more passwords than fit in a `u128`.
+    fn keyspace(&self) -> Option<u128>;
+
+    /// Moves the generator so that the next password is the one at `index`.
+    fn seek(&mut self, index: u128);
+
+    /// Only generates the passwords in `range` of the keyspace.
+    fn in_range(mut self, range: KeyspaceRange) -> KeyspaceSlice<Self>
+    where
+        Self: Sized,
+    {
+        self.seek(range.skip);
+
+        KeyspaceSlice {
+            generator: self,
+            remaining: range.limit,
+        }
+    }
+}
+
+/// A contiguous slice of a keyspace: `limit` passwords starting at index `skip`.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct KeyspaceRange {
+    /// Index of the first password of the slice
+    pub skip: u128,
+    /// Maximum number of passwords in the slice, `None` to go until the end of the keyspace
+    pub limit: Option<u128>,
+}
+
+impl KeyspaceRange {
+    /// Creates a new KeyspaceRange
+    /// Requires:
+    /// - skip: Index of the first password
+    /// - limit: Maximum number of passwords, `None` for no limit
+    pub fn new(skip: u128, limit: Option<u128>) -> Self {
+        Self { skip, limit }
+    }
+
+    /// Splits a keyspace into `parts` slices of (almost) equal size and returns the
+    /// `part`th one, counting from 1. Every index of the keyspace is in exactly one part.
+    pub fn part(part: u64, parts: u64, keyspace: u128) -> Result<Self, GenPasswordsError> {
+        if part == 0 || part > parts {
+            return Err(GenPasswordsError::InvalidPartError(part, parts));
+        }
+
+        let (part, parts) = (part as u128 - 1, parts as u128);
+        let size = keyspace / parts;
+        let remainder = keyspace % parts;
+
+        // the first `remainder` parts get one extra password each
+        Ok(Self {
+            skip: part * size + part.min(remainder),
+            limit: Some(size + u128::from(part < remainder)),
+        })
+    }
+
+    /// Applies another skip and limit relative to the start of this range.
+    pub fn within(self, skip: u128, limit: Option<u128>) -> Self {
+        let remaining = self.limit.map(|l| l.saturating_sub(skip));
+
+        Self {
+            skip: self.skip.saturating_add(skip),
+            limit: match (remaining, limit) {
+                (Some(remaining), Some(limit)) => Some(remaining.min(limit)),
+                (remaining, limit) => remaining.or(limit),
+            },
+        }
+    }
+
+    /// Returns the first index and the index past the last password of this range,
+    /// within a keyspace of `keyspace` passwords.
+    pub fn bounds(&self, keyspace: u128) -> (u128, u128) {
+        let start = self.skip.min(keyspace);
+        let end = match self.limit {
+            Some(limit) => start.saturating_add(limit).min(keyspace),
+            None => keyspace,
+        };
+
+        (start, end)
+    }
+}
+
+/// Iterator over a [`KeyspaceRange`] of a [`SeekableGenerator`], see [`SeekableGenerator::in_range`].
+pub struct KeyspaceSlice<G> {
+    generator: G,
+    /// Number of passwords left in the slice, `None` to go until the end of the keyspace
+    remaining: Option<u128>,
+}
+
+impl<G: SeekableGenerator> Iterator for KeyspaceSlice<G> {
+    type Item = String;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match &mut self.remaining {
+            Some(0) => None,
+            Some(remaining) => {
+                *remaining -= 1;
+                self.generator.next()
+            }
+            None => self.generator.next(),
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let (lower, upper) = self.generator.size_hint();
+        let remaining = match self.remaining {
+            Some(remaining) => usize::try_from(remaining).ok(),
+            None => return (lower, upper),
+        };
+
+        let upper = match (upper, remaining) {
+            (Some(upper), Some(remaining)) => Some(upper.min(remaining)),
+            (upper, remaining) => upper.or(remaining),
+        };
+
+        (lower.min(remaining.unwrap_or(usize::MAX)), upper)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::keyspace::{KeyspaceRange, SeekableGenerator};
+    use crate::passwords::{CharSet, PasswordGenerator};
+
+    #[test]
+    fn test_parts_cover_keyspace() {
+        let all = PasswordGenerator::new(1, 2, CharSet::Numeric)
+            .unwrap()
+            .collect::<Vec<_>>();
+
+        let mut parts = vec![];
+        for part in 1..=7 {
+            let range = KeyspaceRange::part(part, 7, all.len() as u128).unwrap();
+            parts.extend(
+                PasswordGenerator::new(1, 2, CharSet::Numeric)
+                    .unwrap()
+                    .in_range(range),
+            );
+        }
+
+        assert_eq!(parts, all);
+        assert!(KeyspaceRange::part(0, 7, 110).is_err());
+        assert!(KeyspaceRange::part(8, 7, 110).is_err());
+    }
+
+    #[test]
+    fn test_skip_limit() {
+        let all = PasswordGenerator::new(1, 2, CharSet::Numeric)
+            .unwrap()
+            .collect::<Vec<_>>();
+
+        let range = KeyspaceRange::part(2, 2, 110).unwrap().within(5, Some(10));
+        assert_eq!(range, KeyspaceRange::new(60, Some(10)));
+
+        let slice = PasswordGenerator::new(1, 2, CharSet::Numeric)
+            .unwrap()
+            .in_range(range);
+        assert_eq!(slice.size_hint(), (10, Some(10)));
+        assert_eq!(slice.collect::<Vec<_>>(), all[60..70]);
+
+        assert_eq!(KeyspaceRange::new(100, Some(50)).bounds(110), (100, 110));
+        assert_eq!(KeyspaceRange::new(200, None).bounds(110), (110, 110));
+    }
+}
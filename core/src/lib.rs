@@ -1,26 +1,51 @@
 //! This crate gives us some basic tools for creating a rainbow table.
-use crossbeam_channel::Sender;
 
 use db::DB;
 use digest::Digest;
 use itertools::Itertools;
 use md5::Md5;
-use passwords::PasswordGenerator;
-use rayon::prelude::{IntoParallelIterator, ParallelBridge, ParallelIterator};
+use checkpoint::Checkpointer;
+use encoding::{candidate_bytes, from_hex_notation, Encoding};
+use cancel::CancellationToken;
+pub use errors::PipelineError;
+use output::{CheckpointedWriter, Compression};
+use serde::{Deserialize, Serialize};
 use sha2::Sha256;
-use std::io::{BufRead, BufReader, Write};
-use std::thread::JoinHandle;
-use std::{fs::File, io::BufWriter, num::NonZeroUsize};
-use tracing::trace;
-use utils::{hex_string_to_vec, vec_to_hex_string, write_file};
+use stats::Stats;
+use std::sync::Arc;
+use utils::{hex_string_to_vec, vec_to_hex_string};
+mod abort;
 mod errors;
 use anyhow::{Ok, Result};
 use blake2::{Blake2b512, Blake2s256};
 use ripemd::{Ripemd160, Ripemd320};
 use sha2::Sha512;
 
+pub mod api;
+pub mod bytes;
+pub mod cancel;
+pub mod checkpoint;
+pub mod combinator;
+pub mod compression;
 pub mod db;
+pub mod distributed;
+pub mod encoding;
+pub mod format;
+pub mod jobs;
+pub mod keyspace;
+pub mod mask;
+pub mod metrics;
+pub mod markov;
+pub mod output;
 pub mod passwords;
+pub mod pipeline;
+pub mod policy;
+pub mod prince;
+pub mod server;
+pub mod sink;
+pub mod source;
+pub mod stats;
+pub mod table;
 pub mod utils;
 
 pub fn hash_input<D: Digest>(algo: HashAlgorithm, input: &str) -> Result<Vec<u8>, anyhow::Error> {
@@ -78,11 +103,7 @@ pub fn hash_input<D: Digest>(algo: HashAlgorithm, input: &str) -> Result<Vec<u8>
         }
     }
 
-    let output: Vec<u8> = D::new()
-        .chain_update(input.as_bytes())
-        .finalize()
-        .as_slice()
-        .into();
+    let output = hash_bytes::<D>(input.as_bytes());
 
     match algo {
         HashAlgorithm::Md5 => rainbow_table_db.set(
@@ -125,7 +146,57 @@ pub fn hash_input<D: Digest>(algo: HashAlgorithm, input: &str) -> Result<Vec<u8>
     Ok(output)
 }
 
-#[derive(Debug, Clone, Copy)]
+/// Hashes raw bytes, without going through the cache of [`hash_input`]
+pub fn hash_bytes<D: Digest>(input: &[u8]) -> Vec<u8> {
+    D::new().chain_update(input).finalize().as_slice().into()
+}
+
+/// Hashes a line of a wordlist the way the target system did: `$HEX[...]` lines are hashed
+/// as raw bytes, other lines are encoded with `encoding` first.
+/// Only UTF-8 candidates go through the cache of [`hash_input`].
+pub fn hash_candidate(
+    algorithm: HashAlgorithm,
+    candidate: &str,
+    encoding: Encoding,
+) -> Result<Vec<u8>> {
+    if encoding == Encoding::Utf8 && from_hex_notation(candidate).is_none() {
+        return match algorithm {
+            HashAlgorithm::Md5 => hash_input::<Md5>(algorithm, candidate),
+            HashAlgorithm::Sha2 => hash_input::<Sha256>(algorithm, candidate),
+            HashAlgorithm::Sha512 => hash_input::<Sha512>(algorithm, candidate),
+            HashAlgorithm::Ripemd160 => hash_input::<Ripemd160>(algorithm, candidate),
+            HashAlgorithm::Ripemd320 => hash_input::<Ripemd320>(algorithm, candidate),
+            HashAlgorithm::Blake2b512 => hash_input::<Blake2b512>(algorithm, candidate),
+            HashAlgorithm::Blake2s256 => hash_input::<Blake2s256>(algorithm, candidate),
+        };
+    }
+
+    hash_candidate_uncached(algorithm, candidate, encoding)
+}
+
+/// Hashes a candidate like [`hash_candidate`], but never through the cache of [`hash_input`],
+/// e.g. for input from the network that would grow the cache without bound
+pub fn hash_candidate_uncached(
+    algorithm: HashAlgorithm,
+    candidate: &str,
+    encoding: Encoding,
+) -> Result<Vec<u8>> {
+    let bytes = candidate_bytes(candidate, encoding)?;
+
+    Ok(match algorithm {
+        HashAlgorithm::Md5 => hash_bytes::<Md5>(&bytes),
+        HashAlgorithm::Sha2 => hash_bytes::<Sha256>(&bytes),
+        HashAlgorithm::Sha512 => hash_bytes::<Sha512>(&bytes),
+        HashAlgorithm::Ripemd160 => hash_bytes::<Ripemd160>(&bytes),
+        HashAlgorithm::Ripemd320 => hash_bytes::<Ripemd320>(&bytes),
+        HashAlgorithm::Blake2b512 => hash_bytes::<Blake2b512>(&bytes),
+        HashAlgorithm::Blake2s256 => hash_bytes::<Blake2s256>(&bytes),
+    })
+}
+
+/// Named like in the cache and in output files when serialized, see [`HashAlgorithm::name`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum HashAlgorithm {
     /// md5 hasher
     Md5,
@@ -150,399 +221,148 @@ impl HashAlgorithm {
     pub const RIPEMD_320_STR: &'static str = "ripemd320";
     pub const BLAKE_2B_512_STR: &'static str = "blake2b512";
     pub const BLAKE_2S_256_STR: &'static str = "blake2s256";
-}
-
-pub fn add(left: usize, right: usize) -> usize {
-    left + right
-}
-
-pub enum ProgressEvent {
-    /// We have a new input that needs to be hashed
-    NewInput,
-
-    /// We have completed an md5 hash of an input
-    InputMd5Hashed,
-
-    /// We have received N new inputs that need to be hashed
-    NewInputs(u64),
-}
-
-/// Use rayon to do our parallelism
-pub fn compute_with_rayon(
-    generator: PasswordGenerator,
-    num_threads: NonZeroUsize,
-    progress_sender: Sender<ProgressEvent>,
-    algorithm: HashAlgorithm,
-) -> Result<()> {
-    rayon::ThreadPoolBuilder::new()
-        .num_threads(num_threads.into())
-        .build_global()?;
-
-    let compute = |password: String| -> Result<()> {
-        progress_sender.send(ProgressEvent::NewInput)?;
-        let _x = match algorithm {
-            HashAlgorithm::Md5 => hash_input::<Md5>(HashAlgorithm::Md5, &password),
-            HashAlgorithm::Sha2 => hash_input::<Sha256>(HashAlgorithm::Sha2, &password),
-            HashAlgorithm::Sha512 => {
-                hash_input::<Sha512>(HashAlgorithm::Sha512, &password)
-            }
-            HashAlgorithm::Ripemd160 => {
-                hash_input::<Ripemd160>(HashAlgorithm::Ripemd160, &password)
-            }
-            HashAlgorithm::Ripemd320 => {
-                hash_input::<Ripemd320>(HashAlgorithm::Ripemd320, &password)
-            }
-            HashAlgorithm::Blake2b512 => {
-                hash_input::<Blake2b512>(HashAlgorithm::Blake2b512, &password)
-            }
-            HashAlgorithm::Blake2s256 => {
-                hash_input::<Blake2s256>(HashAlgorithm::Blake2s256, &password)
-            }
-        };
-        
-        // we want to increment the progress bar here
-        progress_sender.send(ProgressEvent::InputMd5Hashed)?;
-        Ok(())
-    };
-
-    let generator = generator.par_bridge();
 
-    generator.into_par_iter().try_for_each(compute)?;
-
-    Ok(())
-}
-
-/// Save generated passwords to disk
-pub fn save_passwords_to_disk(
-    file_path: &str,
-    generator: PasswordGenerator,
-    progress_sender: Option<Sender<ProgressEvent>>,
-) -> Result<()> {
-    let mut content = String::from("");
-
-    for password in generator {
-        if let Some(ps) = &progress_sender {
-            ps.send(ProgressEvent::NewInput)?;
+    /// Name of the algorithm, as used in the cache and in output files
+    pub fn name(&self) -> &'static str {
+        match self {
+            HashAlgorithm::Md5 => Self::MD5_STR,
+            HashAlgorithm::Sha2 => Self::SHA2_STR,
+            HashAlgorithm::Sha512 => Self::SHA_512_STR,
+            HashAlgorithm::Ripemd160 => Self::RIPEMD_160_STR,
+            HashAlgorithm::Ripemd320 => Self::RIPEMD_320_STR,
+            HashAlgorithm::Blake2b512 => Self::BLAKE_2B_512_STR,
+            HashAlgorithm::Blake2s256 => Self::BLAKE_2S_256_STR,
         }
+    }
 
-        content.push_str(&password);
-        content.push('\n');
-
-        if let Some(ps) = &progress_sender {
-            ps.send(ProgressEvent::InputMd5Hashed)?;
+    /// Algorithm with the given [`HashAlgorithm::name`]
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            Self::MD5_STR => Some(HashAlgorithm::Md5),
+            Self::SHA2_STR => Some(HashAlgorithm::Sha2),
+            Self::SHA_512_STR => Some(HashAlgorithm::Sha512),
+            Self::RIPEMD_160_STR => Some(HashAlgorithm::Ripemd160),
+            Self::RIPEMD_320_STR => Some(HashAlgorithm::Ripemd320),
+            Self::BLAKE_2B_512_STR => Some(HashAlgorithm::Blake2b512),
+            Self::BLAKE_2S_256_STR => Some(HashAlgorithm::Blake2s256),
+            _ => None,
         }
     }
+}
 
-    write_file(file_path, content.as_str())?;
-
-    Ok(())
+pub fn add(left: usize, right: usize) -> usize {
+    left + right
 }
 
-/// Computes hashes of passwords stored in a file and write them to disk.
-pub fn compute_from_file(
-    in_path: &str,
-    out_path: &str,
-    num_threads: NonZeroUsize,
-    progress_sender: Option<Sender<ProgressEvent>>,
-    chunk_size: usize,
-    algorithm: HashAlgorithm,
+/// Number of passwords written to the output file at once by [`save_passwords_to_disk`]
+const SAVE_CHUNK_SIZE: usize = 10_000;
+
+/// Save generated passwords to disk, one per line.
+/// Passwords are streamed through a buffered, optionally compressed, writer so memory use
+/// does not grow with the number of passwords. With `writer_thread`, writing and compressing
+/// happen in a separate thread while the next passwords are generated.
+/// With a checkpointer, `generator` must start right after the checkpoint's position,
+/// and the output file is truncated to the checkpoint's output length.
+/// Once `cancel` is cancelled, no more passwords are generated and the checkpoint is saved.
+/// Generated and written passwords are counted in `stats`.
+pub fn save_passwords_to_disk<I: IntoIterator<Item = String>>(
+    file_path: &str,
+    generator: I,
+    stats: Arc<Stats>,
+    checkpointer: Option<Checkpointer>,
+    compression: Compression,
+    writer_thread: bool,
+    cancel: &CancellationToken,
 ) -> Result<()> {
-    let f_in = File::open(in_path)?;
-    let f_in = BufReader::new(f_in);
-
-    let f_out = File::create(out_path)?;
-    let mut f_out = BufWriter::new(f_out);
-
-    // We are going to spawn N threads pased in command line (or wahtever)
-    // set up a multicomsumer channel
-    // for each input,. main thread sends the input on the channel
-    // whatever thread is available, receives the input, then hashes it
-    let (plain_text_tx, plain_text_rx) = crossbeam_channel::unbounded();
-
-    // all this looping etc., needs to be happening in a separate thread.
-
-    let p = progress_sender.clone();
-    std::thread::spawn(move || -> Result<()> {
-        let progress_sender = p;
-
-        f_in.lines()
-            .chunks(chunk_size)
-            .into_iter()
-            .try_for_each(|password_chunk| {
-                let passwords = password_chunk
-                    .filter_map(|v| {
-                        if let std::result::Result::Ok(password) = v {
-                            Some(password)
-                        } else {
-                            None
-                        }
-                    })
-                    .collect::<Vec<_>>();
-
-                if let Some(progress_sender) = &progress_sender {
-                    let len = passwords.len().try_into()?;
-                    progress_sender.send(ProgressEvent::NewInputs(len))?;
-                }
-
-                plain_text_tx.send(passwords)?;
-
-                Ok(())
-            })?;
-
-        Ok(())
-    });
-
-    // channel to send hashed passords to a thread for writing to disk
-    let (md5_tx, md5_rx) = crossbeam_channel::unbounded();
-
-    // spawn N threads to compute the md5s
-    let handles = start_compute_threads(
-        num_threads,
-        plain_text_rx,
-        md5_tx,
-        progress_sender,
-        algorithm,
-    );
-
-    // now we need a thread that will receive the hashed passwords and write them to disk.
-    // this thread will receive any hashed passwords and then write them to disk
-    let h = std::thread::spawn(move || {
-        while let std::result::Result::Ok(hashed_password) = md5_rx.recv() {
-            // we have gotten a hashed password
-            // now we need to write it to disk
-            f_out.write_all(&hashed_password).unwrap();
+    let position = checkpointer
+        .as_ref()
+        .map_or(0, |checkpointer| checkpointer.checkpoint().position);
+    let mut writer =
+        CheckpointedWriter::create(&[file_path.to_owned()], compression, checkpointer)?;
+
+    let chunks = generator.into_iter().chunks(SAVE_CHUNK_SIZE);
+    let chunks = chunks.into_iter().scan(position, |start, passwords| {
+        let mut lines = Vec::new();
+        let mut len = 0;
+
+        for password in passwords {
+            lines.extend_from_slice(password.as_bytes());
+            lines.push(b'\n');
+            len += 1;
         }
-        trace!("flushing output file");
-        f_out.flush().unwrap();
+
+        let chunk = (*start, len, lines);
+        *start += len;
+        Some(chunk)
     });
 
-    // wait for the compute threads to finish.
-    for h in handles {
-        h.join().unwrap();
-    }
+    let written_stats = stats.clone();
+    let write_chunk = move |writer: &mut CheckpointedWriter,
+                            (start, len, lines): (u128, u128, Vec<u8>)|
+          -> Result<()> {
+        writer.write_chunk(start, len, std::slice::from_ref(&lines))?;
+        written_stats.add_written(len as u64);
 
-    // wait for the file write thread to finish
-    h.join().unwrap();
+        Ok(())
+    };
 
-    Ok(())
-}
+    // passwords written, or sent to the writer thread
+    let mut done = 0;
+    let mut cancelled = false;
 
-fn start_compute_threads(
-    num_threads: NonZeroUsize,
-    plain_text_rx: crossbeam_channel::Receiver<Vec<String>>,
-    md5_tx: Sender<Vec<u8>>,
-    progress_sender: Option<Sender<ProgressEvent>>,
-    algorithm: HashAlgorithm,
-) -> Vec<JoinHandle<()>> {
-    let mut handles = Vec::new();
-    for i in 0..num_threads.into() {
-        let plain_text_rx = plain_text_rx.clone();
-        let md5_tx = md5_tx.clone();
-        let progress_sender = progress_sender.clone();
-        let h = std::thread::spawn(move || {
-            while let std::result::Result::Ok(passwords) = plain_text_rx.recv() {
-                for password in passwords {
-                    let hashed_password = match algorithm {
-                        HashAlgorithm::Md5 => hash_input::<Md5>(HashAlgorithm::Md5, &password),
-                        HashAlgorithm::Sha2 => hash_input::<Sha256>(HashAlgorithm::Sha2, &password),
-                        HashAlgorithm::Sha512 => {
-                            hash_input::<Sha512>(HashAlgorithm::Sha512, &password)
-                        }
-                        HashAlgorithm::Ripemd160 => {
-                            hash_input::<Ripemd160>(HashAlgorithm::Ripemd160, &password)
-                        }
-                        HashAlgorithm::Ripemd320 => {
-                            hash_input::<Ripemd320>(HashAlgorithm::Ripemd320, &password)
-                        }
-                        HashAlgorithm::Blake2b512 => {
-                            hash_input::<Blake2b512>(HashAlgorithm::Blake2b512, &password)
-                        }
-                        HashAlgorithm::Blake2s256 => {
-                            hash_input::<Blake2s256>(HashAlgorithm::Blake2s256, &password)
-                        }
-                    };
-
-                    trace!("{:?}", hashed_password);
-                    md5_tx.send(hashed_password.unwrap()).unwrap();
-
-                    if let Some(progress_sender) = &progress_sender {
-                        // we want to increment the progress bar here
-                        progress_sender.send(ProgressEvent::InputMd5Hashed).unwrap();
-                    }
-                }
+    if !writer_thread {
+        for chunk in chunks {
+            if cancel.is_cancelled() {
+                cancelled = true;
+                break;
             }
-            trace!("Thread {} had finished.", i);
-        });
-
-        handles.push(h);
-    }
-
-    handles
-}
 
-/// Computes an md5 hash for each password generated by
-/// a [`PasswordGenerator`].
-///
-pub fn compute_with_threads_with_password_generator(
-    generator: PasswordGenerator,
-    num_threads: NonZeroUsize,
-    progress_sender: Sender<ProgressEvent>,
-    algorithm: HashAlgorithm,
-) {
-    // We are going to spawn N threads pased in command line (or wahtever)
-    // set up a multicomsumer channel
-    // for each input,. main thread sends the input on the channel
-    // whatever thread is available, receives the input, then hashes it
-    let (tx, rx) = crossbeam_channel::unbounded();
-
-    // all this looping etc., needs to be happening in a separate thread.
-
-    let p = progress_sender.clone();
-    std::thread::spawn(move || {
-        let progress_sender = p;
-        for passwords in &generator.into_iter().chunks(10_000) {
-            // report that we have one more string to hash
-            // or, because we know the length of inputs before hand
-            // we could set it at once.
-            // https://docs.rs/indicatif/latest/indicatif/struct.ProgressBar.html#method.inc_length
-            // for 0..passwords.
-            let passwords = passwords.collect::<Vec<_>>();
-
-            for _i in 0..passwords.len() {
-                progress_sender.send(ProgressEvent::NewInput).unwrap();
-            }
-            // progress_sender
-            //     .send(ProgressEvent::NewInputs(
-            //         passwords.len().try_into().unwrap(),
-            //     ))
-            //     .unwrap();
-
-            // send the input onto the channel
-            tx.send(passwords).unwrap();
+            stats.add_generated(chunk.1 as u64);
+            done += chunk.1 as u64;
+            write_chunk(&mut writer, chunk)?;
         }
-    });
-
-    let mut handles = Vec::new();
-
-    // spawn 4 threads
-    for _i in 0..num_threads.into() {
-        let rx = rx.clone();
-        let progress_sender = progress_sender.clone();
-        let h = std::thread::spawn(move || {
-            while let std::result::Result::Ok(passwords) = rx.recv() {
-                for password in passwords {
-                    let _x = match algorithm {
-                        HashAlgorithm::Md5 => hash_input::<Md5>(HashAlgorithm::Md5, &password),
-                        HashAlgorithm::Sha2 => hash_input::<Sha256>(HashAlgorithm::Sha2, &password),
-                        HashAlgorithm::Sha512 => {
-                            hash_input::<Sha512>(HashAlgorithm::Sha512, &password)
-                        }
-                        HashAlgorithm::Ripemd160 => {
-                            hash_input::<Ripemd160>(HashAlgorithm::Ripemd160, &password)
-                        }
-                        HashAlgorithm::Ripemd320 => {
-                            hash_input::<Ripemd320>(HashAlgorithm::Ripemd320, &password)
-                        }
-                        HashAlgorithm::Blake2b512 => {
-                            hash_input::<Blake2b512>(HashAlgorithm::Blake2b512, &password)
-                        }
-                        HashAlgorithm::Blake2s256 => {
-                            hash_input::<Blake2s256>(HashAlgorithm::Blake2s256, &password)
-                        }
-                    };
-
-                    // we want to increment the progress bar here
-                    progress_sender.send(ProgressEvent::InputMd5Hashed).unwrap();
-                }
-
-                // thread::sleep(Duration::from_millis(1_000));
+    } else {
+        // only a few chunks are buffered, so memory stays bounded when the disk is the bottleneck
+        let (chunk_tx, chunk_rx) = crossbeam_channel::bounded::<(u128, u128, Vec<u8>)>(4);
+        let h = std::thread::spawn(move || -> Result<CheckpointedWriter> {
+            while let std::result::Result::Ok(chunk) = chunk_rx.recv() {
+                write_chunk(&mut writer, chunk)?;
             }
-        });
 
-        handles.push(h);
-    }
+            Ok(writer)
+        });
 
-    for h in handles {
-        h.join().unwrap();
-    }
-}
+        for chunk in chunks {
+            if cancel.is_cancelled() {
+                cancelled = true;
+                break;
+            }
 
-/// DO WE REALLY WANT TO KEEP THIS FUNCTION?
-///
-/// Computes an md5 for each of the strings in input
-/// Sends progress events to a listener.
-pub fn compute_with_threads(
-    inputs: Vec<String>,
-    num_threads: NonZeroUsize,
-    progress_sender: Sender<ProgressEvent>,
-    algorithm: HashAlgorithm,
-) {
-    // We are going to spawn N threads pased in command line (or wahtever)
-    // set up a multicomsumer channel
-    // for each input,. main thread sends the input on the channel
-    // whatever thread is available, receives the input, then hashes it
-    let (tx, rx) = crossbeam_channel::unbounded();
-
-    // all this looping etc., needs to be happening in a separate thread.
-
-    let p = progress_sender.clone();
-    std::thread::spawn(move || {
-        let progress_sender = p;
-        for input in inputs {
-            // report that we have one more string to hash
-            // or, because we know the length of inputs before hand
-            // we could set it at once.
-            // https://docs.rs/indicatif/latest/indicatif/struct.ProgressBar.html#method.inc_length
-            progress_sender.send(ProgressEvent::NewInput).unwrap();
-
-            // send the input onto the channel
-            tx.send(input).unwrap();
-        }
-    });
+            stats.add_generated(chunk.1 as u64);
+            done += chunk.1 as u64;
 
-    let mut handles = Vec::new();
-
-    // spawn 4 threads
-    for _i in 0..num_threads.into() {
-        let rx = rx.clone();
-        let progress_sender = progress_sender.clone();
-        let h = std::thread::spawn(move || {
-            while let std::result::Result::Ok(input) = rx.recv() {
-                let _x = match algorithm {
-                    HashAlgorithm::Md5 => hash_input::<Md5>(HashAlgorithm::Md5, &input),
-                    HashAlgorithm::Sha2 => hash_input::<Sha256>(HashAlgorithm::Sha2, &input),
-                    HashAlgorithm::Sha512 => {
-                        hash_input::<Sha512>(HashAlgorithm::Sha512, &input)
-                    }
-                    HashAlgorithm::Ripemd160 => {
-                        hash_input::<Ripemd160>(HashAlgorithm::Ripemd160, &input)
-                    }
-                    HashAlgorithm::Ripemd320 => {
-                        hash_input::<Ripemd320>(HashAlgorithm::Ripemd320, &input)
-                    }
-                    HashAlgorithm::Blake2b512 => {
-                        hash_input::<Blake2b512>(HashAlgorithm::Blake2b512, &input)
-                    }
-                    HashAlgorithm::Blake2s256 => {
-                        hash_input::<Blake2s256>(HashAlgorithm::Blake2s256, &input)
-                    }
-                };
-
-                // we want to increment the progress bar here
-                progress_sender.send(ProgressEvent::InputMd5Hashed).unwrap();
-                // thread::sleep(Duration::from_millis(1_000));
-                // println!("{i} - {:?}", x)
+            // the writer thread only hangs up when it failed, its error is returned below
+            if chunk_tx.send(chunk).is_err() {
+                break;
             }
-        });
+        }
+        drop(chunk_tx);
 
-        handles.push(h);
+        writer = h.join().map_err(|_| PipelineError::WorkerPanicError)??;
     }
 
-    for h in handles {
-        h.join().unwrap();
+    // keep what was written so far, resuming generates the rest
+    if cancelled {
+        writer.close()?;
+        return Err(PipelineError::CancelledError(done).into());
     }
+
+    writer.finish()
 }
 
+/// Number of generated passwords in a chunk when hashing them with a [`pipeline::Pipeline`]
+pub const GENERATOR_CHUNK_SIZE: usize = 10_000;
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -578,4 +398,18 @@ mod tests {
             hex!("c63813a8f804abece06213a46acd04a2d738c8e7a58fbf94bfe066a9c7f89197")
         );
     }
+
+    #[test]
+    fn test_hash_candidate_encodings() {
+        let x = hash_candidate(HashAlgorithm::Md5, "pä", Encoding::Utf16Le);
+        assert_eq!(x.unwrap(), hex!("5ee19cb018459e4432970a9f5d3ec1a3"));
+
+        let x = hash_candidate(HashAlgorithm::Md5, "pä", Encoding::Latin1);
+        assert_eq!(x.unwrap(), hex!("383105be70d7b06aba28a60b5587225c"));
+
+        let x = hash_candidate(HashAlgorithm::Md5, "$HEX[00ff]", Encoding::Utf8);
+        assert_eq!(x.unwrap(), hex!("d07d34efac6328007ad67c7e0a985e00"));
+
+        assert!(hash_candidate(HashAlgorithm::Md5, "€", Encoding::Latin1).is_err());
+    }
 }
@@ -0,0 +1,398 @@
+//! Markov chain based candidate generation.
+//!
+//! Statistics about which characters appear at every position of a password, and which characters
+//! follow each other, are collected from a wordlist. They are then used to enumerate the same
+//! passwords as [`crate::passwords::PasswordGenerator`], but with the most likely character tried
+//! first at every position.
+use crate::compression::open_input;
+use crate::errors::GenPasswordsError;
+use crate::keyspace::SeekableGenerator;
+use crate::passwords::{brute_force_keyspace, get_chars_vec, size_hint, CharSet};
+use crate::utils::write_file;
+use anyhow::Result;
+use itertools::Itertools;
+use serde::{Deserialize, Serialize};
+use std::cmp::Reverse;
+use std::collections::BTreeMap;
+
+/// Character frequencies collected from a wordlist
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MarkovStats {
+    /// How often every character appears at each position
+    positions: Vec<BTreeMap<char, u64>>,
+    /// How often every character is directly followed by another character
+    transitions: BTreeMap<char, BTreeMap<char, u64>>,
+}
+
+impl MarkovStats {
+    /// Collects statistics from every word of a wordlist
+    pub fn train<I, S>(words: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let mut stats = Self::default();
+
+        for word in words {
+            let mut prev = None;
+
+            for (pos, c) in word.as_ref().chars().enumerate() {
+                if stats.positions.len() <= pos {
+                    stats.positions.push(BTreeMap::new());
+                }
+                *stats.positions[pos].entry(c).or_default() += 1;
+
+                if let Some(prev) = prev {
+                    *stats
+                        .transitions
+                        .entry(prev)
+                        .or_default()
+                        .entry(c)
+                        .or_default() += 1;
+                }
+
+                prev = Some(c);
+            }
+        }
+
+        stats
+    }
+
+    /// Reads statistics from a stats file written by [`MarkovStats::save`], which may be compressed
+    pub fn load(path: &str) -> Result<Self> {
+        let stats = serde_json::from_reader(open_input(path)?)?;
+
+        Ok(stats)
+    }
+
+    /// Writes statistics to a stats file
+    pub fn save(&self, path: &str) -> Result<()> {
+        write_file(path, &serde_json::to_string(self)?)
+    }
+
+    fn position_count(&self, pos: usize, c: char) -> u64 {
+        self.positions
+            .get(pos)
+            .and_then(|counts| counts.get(&c))
+            .copied()
+            .unwrap_or_default()
+    }
+
+    fn transition_count(&self, prev: char, c: char) -> u64 {
+        self.transitions
+            .get(&prev)
+            .and_then(|counts| counts.get(&c))
+            .copied()
+            .unwrap_or_default()
+    }
+}
+
+/// Struct Iterator to iterate over all passwords of a length range and character set,
+/// most likely characters first
+///
+/// Passwords are generated one length at a time, from the minimum to the maximum length.
+/// The first position changes the slowest, so that the most likely prefixes are tried first.
+///
+/// This only approximates trying passwords from most to least likely: passwords of one length
+/// come out in lexicographic order of the rank of the character at every position, not sorted by
+/// their combined probability. A password with an unlikely first character is therefore tried
+/// after every password starting with a more likely one, however likely the rest of it is.
+///
+/// Usage:
+/// ```Rust
+/// let stats = markov::MarkovStats::load("rockyou.stats")?;
+/// let passwords = markov::MarkovGenerator::new(&stats, 1, 4, CharSet::LowerAlpha | CharSet::Numeric)?;
+///
+/// for password in passwords {
+///     println!("Password: {}", password);
+/// }
+/// ```
+pub struct MarkovGenerator {
+    /// Minimum length of passwords
+    min_length: usize,
+    /// Maximum length of passwords
+    max_length: usize,
+    /// Valid characters for passwords
+    valid_chars: Vec<char>,
+    /// Indices into `valid_chars` sorted from most to least likely, for every position and
+    /// previous character. The first position only has one order as there is no previous character.
+    orders: Vec<Vec<Vec<usize>>>,
+    /// Iterator property to keep track of current length of passwords
+    current_length: usize,
+    /// Iterator property to keep track of the rank of the character at every position
+    ranks: Vec<usize>,
+}
+
+impl MarkovGenerator {
+    /// Creates a new MarkovGenerator
+    /// Requires:
+    /// - stats: Statistics used to order characters
+    /// - min_length: Minimum length for passwords
+    /// - max_length: Maximum length for passwords
+    /// - char_set: Valid characters for passwords
+    pub fn new(
+        stats: &MarkovStats,
+        min_length: usize,
+        max_length: usize,
+        char_set: CharSet,
+    ) -> Result<Self, GenPasswordsError> {
+        Self::with_chars(stats, min_length, max_length, get_chars_vec(char_set))
+    }
+
+    /// Creates a new MarkovGenerator from any characters, e.g. non-ASCII ones
+    /// Requires:
+    /// - stats: Statistics used to order characters
+    /// - min_length: Minimum length for passwords
+    /// - max_length: Maximum length for passwords
+    /// - chars: Valid characters for passwords, duplicates are only used once
+    pub fn with_chars(
+        stats: &MarkovStats,
+        min_length: usize,
+        max_length: usize,
+        chars: Vec<char>,
+    ) -> Result<Self, GenPasswordsError> {
+        if min_length == 0 {
+            return Err(GenPasswordsError::InvalidMinLengthError(min_length));
+        }
+
+        if min_length > max_length {
+            return Err(GenPasswordsError::MinGreaterThanMaxError(
+                min_length, max_length,
+            ));
+        }
+
+        let valid_chars = chars.into_iter().unique().collect::<Vec<_>>();
+        let orders = (0..max_length)
+            .map(|pos| {
+                if pos == 0 {
+                    return vec![sorted_chars(&valid_chars, |c| {
+                        (0, stats.position_count(0, c))
+                    })];
+                }
+
+                valid_chars
+                    .iter()
+                    .map(|prev| {
+                        sorted_chars(&valid_chars, |c| {
+                            (
+                                stats.transition_count(*prev, c),
+                                stats.position_count(pos, c),
+                            )
+                        })
+                    })
+                    .collect()
+            })
+            .collect();
+
+        Ok(Self {
+            min_length,
+            max_length: if valid_chars.is_empty() { 0 } else { max_length },
+            valid_chars,
+            orders,
+            current_length: min_length,
+            ranks: vec![0; min_length],
+        })
+    }
+
+    /// Number of passwords this generator produces, the same as a
+    /// [`crate::passwords::PasswordGenerator`] with the same lengths and character set.
+    /// Returns `None` if there are more passwords than fit in a `u128`.
+    pub fn keyspace(&self) -> Option<u128> {
+        brute_force_keyspace(self.valid_chars.len(), self.min_length, self.max_length)
+    }
+
+    /// Index of the next password, `None` if it does not fit in a `u128`
+    fn current_index(&self) -> Option<u128> {
+        if self.current_length > self.max_length {
+            return self.keyspace();
+        }
+
+        let r = self.valid_chars.len() as u128;
+        let shorter = brute_force_keyspace(
+            self.valid_chars.len(),
+            self.min_length,
+            self.current_length - 1,
+        )?;
+
+        self.ranks.iter().try_fold(shorter, |index, rank| {
+            index.checked_mul(r)?.checked_add(*rank as u128)
+        })
+    }
+
+    /// Index into `valid_chars` of the character at `pos`, given the rank of every position
+    fn char_idx(&self, pos: usize, prev: Option<usize>, rank: usize) -> usize {
+        self.orders[pos][prev.unwrap_or_default()][rank]
+    }
+
+    fn build_string(&self) -> String {
+        let mut prev = None;
+
+        self.ranks
+            .iter()
+            .enumerate()
+            .map(|(pos, rank)| {
+                let idx = self.char_idx(pos, prev, *rank);
+                prev = Some(idx);
+                self.valid_chars[idx]
+            })
+            .collect()
+    }
+
+    /// Moves to the next password, returns `true` once every password of the current length
+    /// has been generated
+    fn increment(&mut self) -> bool {
+        for rank in self.ranks.iter_mut().rev() {
+            *rank += 1;
+
+            if *rank < self.valid_chars.len() {
+                return false;
+            }
+
+            *rank = 0;
+        }
+
+        true
+    }
+}
+
+/// Sorts the indices of `chars` from most to least likely. Ties keep the character set order.
+fn sorted_chars<F: Fn(char) -> (u64, u64)>(chars: &[char], likelihood: F) -> Vec<usize> {
+    let mut indices = (0..chars.len()).collect::<Vec<_>>();
+    indices.sort_by_key(|idx| {
+        let (primary, secondary) = likelihood(chars[*idx]);
+        (Reverse(primary), Reverse(secondary), *idx)
+    });
+    indices
+}
+
+impl Iterator for MarkovGenerator {
+    type Item = String;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.current_length > self.max_length {
+            return None;
+        }
+
+        let password = self.build_string();
+
+        if self.increment() {
+            self.current_length += 1;
+            self.ranks = vec![0; self.current_length];
+        }
+
+        Some(password)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = self
+            .keyspace()
+            .and_then(|keyspace| Some(keyspace - self.current_index()?));
+
+        size_hint(remaining)
+    }
+}
+
+impl SeekableGenerator for MarkovGenerator {
+    fn keyspace(&self) -> Option<u128> {
+        MarkovGenerator::keyspace(self)
+    }
+
+    fn seek(&mut self, mut index: u128) {
+        let r = self.valid_chars.len() as u128;
+
+        for length in self.min_length..=self.max_length {
+            match r.checked_pow(length as u32) {
+                Some(count) if index >= count => index -= count,
+                // more passwords of this length than fit in a `u128`, so index is in there
+                _ => {
+                    self.current_length = length;
+                    self.ranks = vec![0; length];
+
+                    for rank in self.ranks.iter_mut().rev() {
+                        *rank = (index % r) as usize;
+                        index /= r;
+                    }
+
+                    return;
+                }
+            }
+        }
+
+        self.current_length = self.max_length + 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::keyspace::{KeyspaceRange, SeekableGenerator};
+    use crate::markov::{MarkovGenerator, MarkovStats};
+    use crate::passwords::{CharSet, PasswordGenerator};
+
+    #[test]
+    fn test_markov_enumerates_same_keyspace() {
+        let stats = MarkovStats::train(["abc", "bca", "cab", "zz9"]);
+        let char_set = CharSet::LowerAlpha | CharSet::Numeric;
+
+        let mut markov = MarkovGenerator::new(&stats, 1, 3, char_set)
+            .unwrap()
+            .collect::<Vec<_>>();
+        let mut brute_force = PasswordGenerator::new(1, 3, char_set)
+            .unwrap()
+            .collect::<Vec<_>>();
+
+        assert_eq!(markov.len(), brute_force.len());
+        assert_eq!(
+            MarkovGenerator::new(&stats, 1, 3, char_set)
+                .unwrap()
+                .size_hint(),
+            (markov.len(), Some(markov.len()))
+        );
+
+        let slice = MarkovGenerator::new(&stats, 1, 3, char_set)
+            .unwrap()
+            .in_range(KeyspaceRange::new(30, Some(1000)))
+            .collect::<Vec<_>>();
+        assert_eq!(slice, markov[30..1030]);
+
+        markov.sort();
+        brute_force.sort();
+        assert_eq!(markov, brute_force);
+    }
+
+    #[test]
+    fn test_markov_likely_passwords_first() {
+        let stats = MarkovStats::train(["zy", "zy", "zx", "yx"]);
+        let passwords = MarkovGenerator::new(&stats, 2, 2, CharSet::LowerAlpha)
+            .unwrap()
+            .take(3)
+            .collect::<Vec<_>>();
+
+        assert_eq!(passwords, vec!["zy", "zx", "za"]);
+    }
+
+    #[test]
+    fn test_markov_with_chars() {
+        let stats = MarkovStats::train(["€ä", "ää"]);
+        let chars = vec!['a', 'ä', '€', 'ä'];
+
+        let mut markov = MarkovGenerator::with_chars(&stats, 1, 2, chars.clone())
+            .unwrap()
+            .collect::<Vec<_>>();
+        let mut brute_force = PasswordGenerator::with_chars(1, 2, chars)
+            .unwrap()
+            .collect::<Vec<_>>();
+        assert_eq!(markov[..2], ["ä", "€"]);
+
+        markov.sort();
+        brute_force.sort();
+        assert_eq!(markov, brute_force);
+    }
+
+    #[test]
+    fn test_markov_stats_round_trip() {
+        let stats = MarkovStats::train(["password", "p4ssw0rd", "letmein"]);
+        let json = serde_json::to_string(&stats).unwrap();
+
+        assert_eq!(serde_json::from_str::<MarkovStats>(&json).unwrap(), stats);
+    }
+}
@@ -0,0 +1,301 @@
+//! Mask based candidate generation, where every position of the password has its own character set.
+use crate::errors::GenPasswordsError;
+use crate::keyspace::SeekableGenerator;
+use crate::passwords::{get_chars_vec, size_hint, CharSet};
+
+/// Struct Iterator to iterate over all passwords matching a mask
+///
+/// A mask is a string where every `?x` placeholder is replaced with every
+/// character of a character set and every other character is kept as is:
+/// - `?l`: lower case alphabets. `a-z`
+/// - `?u`: upper case alphabets. `A-Z`
+/// - `?d`: numbers from `0-9`
+/// - `?s`: printable symbols and space
+/// - `?a`: all of the above
+/// - `??`: a literal `?`
+///
+/// Passwords are generated in the same order as [`crate::passwords::PasswordGenerator`],
+/// i.e. the first position changes the fastest.
+///
+/// Usage:
+/// ```Rust
+/// let passwords = mask::MaskGenerator::new("pass?d?d")?;
+///
+/// for password in passwords {
+///     println!("Password: {}", password);
+/// }
+/// ```
+#[derive(Clone, Debug)]
+pub struct MaskGenerator {
+    /// Valid characters for every position of the mask
+    positions: Vec<Vec<char>>,
+    /// Iterator property to keep track of the current character of every position
+    indices: Vec<usize>,
+    /// Iterator property to keep track of whether every password has been generated
+    finished: bool,
+}
+
+impl MaskGenerator {
+    /// Creates a new MaskGenerator
+    /// Requires:
+    /// - mask: The mask describing the passwords, e.g. `?u?l?l?l?d?d`
+    pub fn new(mask: &str) -> Result<Self, GenPasswordsError> {
+        let positions = parse_mask(mask)?;
+
+        Ok(Self {
+            indices: vec![0; positions.len()],
+            finished: positions.is_empty(),
+            positions,
+        })
+    }
+
+    /// Returns the number of characters in every generated password
+    pub fn password_length(&self) -> usize {
+        self.positions.len()
+    }
+
+    /// Number of passwords matching the mask.
+    /// Returns `None` if there are more passwords than fit in a `u128`.
+    pub fn keyspace(&self) -> Option<u128> {
+        self.positions
+            .iter()
+            .try_fold(1u128, |total, chars| total.checked_mul(chars.len() as u128))
+    }
+
+    /// Number of passwords left to generate, `None` if it does not fit in a `u128`
+    pub(crate) fn remaining(&self) -> Option<u128> {
+        if self.finished {
+            return Some(0);
+        }
+
+        Some(self.keyspace()? - self.current_index())
+    }
+
+    /// Starts generating passwords from the beginning again
+    pub fn reset(&mut self) {
+        self.indices.iter_mut().for_each(|idx| *idx = 0);
+        self.finished = self.positions.is_empty();
+    }
+
+    /// Returns the password at `index`, in the order passwords are generated, without
+    /// moving the iterator. Returns `None` if `index` is past the last password.
+    pub fn nth(&self, mut index: u128) -> Option<String> {
+        let mut password = String::with_capacity(self.positions.len());
+
+        for chars in &self.positions {
+            let len = chars.len() as u128;
+            password.push(chars[(index % len) as usize]);
+            index /= len;
+        }
+
+        // anything left over means `index` is past the last password
+        (index == 0).then_some(password)
+    }
+
+    /// Returns the index of `password` in the order passwords are generated.
+    /// Returns `None` if `password` does not match the mask.
+    pub fn index_of(&self, password: &str) -> Option<u128> {
+        let password = password.chars().collect::<Vec<_>>();
+        if password.len() != self.positions.len() {
+            return None;
+        }
+
+        password
+            .iter()
+            .zip(&self.positions)
+            .rev()
+            .try_fold(0u128, |index, (c, chars)| {
+                let digit = chars.iter().position(|v| v == c)?;
+                index
+                    .checked_mul(chars.len() as u128)?
+                    .checked_add(digit as u128)
+            })
+    }
+
+    /// Index of the next password
+    fn current_index(&self) -> u128 {
+        self.indices
+            .iter()
+            .zip(&self.positions)
+            .rev()
+            .fold(0u128, |index, (idx, chars)| {
+                index
+                    .saturating_mul(chars.len() as u128)
+                    .saturating_add(*idx as u128)
+            })
+    }
+
+    fn build_string(&self) -> String {
+        self.indices
+            .iter()
+            .zip(&self.positions)
+            .map(|(idx, chars)| chars[*idx])
+            .collect()
+    }
+
+    /// Moves to the next password, returns `true` once every password has been generated
+    fn increment(&mut self) -> bool {
+        for (idx, chars) in self.indices.iter_mut().zip(&self.positions) {
+            *idx += 1;
+
+            if *idx < chars.len() {
+                return false;
+            }
+
+            *idx = 0;
+        }
+
+        true
+    }
+}
+
+impl Iterator for MaskGenerator {
+    type Item = String;
+
+    /// Iterator over passwords matching the mask
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.finished {
+            return None;
+        }
+
+        let password = self.build_string();
+
+        if self.increment() {
+            self.finished = true;
+        }
+
+        Some(password)
+    }
+
+    /// Jumps straight to the password `n` positions ahead instead of generating
+    /// every password in between.
+    fn nth(&mut self, n: usize) -> Option<Self::Item> {
+        if self.finished {
+            return None;
+        }
+
+        self.seek(self.current_index().saturating_add(n as u128));
+        self.next()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        size_hint(self.remaining())
+    }
+}
+
+impl SeekableGenerator for MaskGenerator {
+    fn keyspace(&self) -> Option<u128> {
+        MaskGenerator::keyspace(self)
+    }
+
+    fn seek(&mut self, mut index: u128) {
+        for (idx, chars) in self.indices.iter_mut().zip(&self.positions) {
+            let len = chars.len() as u128;
+            *idx = (index % len) as usize;
+            index /= len;
+        }
+
+        self.finished = index != 0;
+    }
+}
+
+/// Turns a mask into the valid characters of each of its positions
+fn parse_mask(mask: &str) -> Result<Vec<Vec<char>>, GenPasswordsError> {
+    if mask.is_empty() {
+        return Err(GenPasswordsError::EmptyMaskError);
+    }
+
+    let mut positions = vec![];
+    let mut chars = mask.chars();
+
+    while let Some(c) = chars.next() {
+        if c != '?' {
+            positions.push(vec![c]);
+            continue;
+        }
+
+        let char_set = match chars.next() {
+            Some('l') => CharSet::LowerAlpha,
+            Some('u') => CharSet::UpperAlpha,
+            Some('d') => CharSet::Numeric,
+            Some('s') => CharSet::Special,
+            Some('a') => {
+                CharSet::LowerAlpha | CharSet::UpperAlpha | CharSet::Numeric | CharSet::Special
+            }
+            Some('?') => {
+                positions.push(vec!['?']);
+                continue;
+            }
+            Some(other) => return Err(GenPasswordsError::InvalidMaskPlaceholderError(other)),
+            None => return Err(GenPasswordsError::IncompleteMaskError),
+        };
+
+        positions.push(get_chars_vec(char_set));
+    }
+
+    Ok(positions)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::errors::GenPasswordsError;
+    use crate::mask::MaskGenerator;
+    use crate::passwords::{CharSet, PasswordGenerator};
+
+    #[test]
+    fn test_mask_matches_password_generator() {
+        let mask = MaskGenerator::new("?l?d").unwrap();
+        let password_gen = PasswordGenerator::new(2, 2, CharSet::LowerAlpha).unwrap();
+
+        assert_eq!(mask.count(), 26 * 10);
+
+        let mask = MaskGenerator::new("?l?l").unwrap();
+        assert!(mask.eq(password_gen));
+    }
+
+    #[test]
+    fn test_mask_literals() {
+        let passwords = MaskGenerator::new("a??b?d").unwrap().collect::<Vec<_>>();
+
+        assert_eq!(passwords.len(), 10);
+        assert_eq!(passwords[0], "a?b0");
+        assert_eq!(passwords[9], "a?b9");
+    }
+
+    #[test]
+    fn test_mask_random_access() {
+        let mask = MaskGenerator::new("?u-?d?l").unwrap();
+        let passwords = mask.clone().collect::<Vec<_>>();
+
+        for (index, password) in passwords.iter().enumerate() {
+            assert_eq!(mask.nth(index as u128).as_ref(), Some(password));
+            assert_eq!(mask.index_of(password), Some(index as u128));
+        }
+
+        assert_eq!(mask.keyspace(), Some(passwords.len() as u128));
+        assert_eq!(mask.nth(passwords.len() as u128), None);
+        assert_eq!(mask.index_of("A_0a"), None);
+
+        let skipped = mask.clone().skip(1234).collect::<Vec<_>>();
+        assert_eq!(skipped, passwords[1234..]);
+
+        let mut mask = mask;
+        assert_eq!(Iterator::nth(&mut mask, passwords.len()), None);
+    }
+
+    #[test]
+    fn test_invalid_masks_should_fail() {
+        assert!(matches!(
+            MaskGenerator::new("").err(),
+            Some(GenPasswordsError::EmptyMaskError)
+        ));
+        assert!(matches!(
+            MaskGenerator::new("?x").err(),
+            Some(GenPasswordsError::InvalidMaskPlaceholderError('x'))
+        ));
+        assert!(matches!(
+            MaskGenerator::new("abc?").err(),
+            Some(GenPasswordsError::IncompleteMaskError)
+        ));
+    }
+}
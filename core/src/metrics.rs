@@ -0,0 +1,149 @@
+//! The counters of a running job in the OpenMetrics text format, for Prometheus and the like.
+//!
+//! Counters only go up during a job, the rate of a period is left to the monitoring system.
+//! The hash rate is also exported as an average since the job started, like the progress bar's.
+use crate::db::CacheStats;
+use crate::stats::{ProgressReport, StatsSnapshot};
+use std::fmt::Display;
+use std::time::Duration;
+
+/// Content type of the text returned by [`render`]
+pub const CONTENT_TYPE: &str = "application/openmetrics-text; version=1.0.0; charset=utf-8";
+
+/// Renders the counters of a job that started `elapsed` ago, and of the hash cache
+pub fn render(snapshot: &StatsSnapshot, cache: &CacheStats, elapsed: Duration) -> String {
+    let mut out = String::new();
+    let report = ProgressReport::new(snapshot, elapsed, None, 0);
+
+    counter(
+        &mut out,
+        "hashassin_candidates_generated",
+        "Candidates read or generated",
+        snapshot.generated,
+    );
+    counter(
+        &mut out,
+        "hashassin_candidates_hashed",
+        "Candidates hashed",
+        snapshot.hashed,
+    );
+    counter(
+        &mut out,
+        "hashassin_candidates_written",
+        "Candidates whose hashes were written",
+        snapshot.written,
+    );
+    counter(
+        &mut out,
+        "hashassin_candidates_skipped",
+        "Candidates skipped because they could not be read",
+        snapshot.skipped,
+    );
+    counter(
+        &mut out,
+        "hashassin_matches",
+        "Hashes found among the ones looked for",
+        snapshot.matched,
+    );
+    out.push_str("# UNIT hashassin_input_bytes bytes\n");
+    counter(
+        &mut out,
+        "hashassin_input_bytes",
+        "Bytes read from the input, after decompression",
+        snapshot.bytes_read,
+    );
+    gauge(
+        &mut out,
+        "hashassin_hash_rate",
+        "Candidates written per second since the job started",
+        report.rate,
+    );
+    gauge(
+        &mut out,
+        "hashassin_queued_chunks",
+        "Chunks waiting for a worker thread",
+        snapshot.queued,
+    );
+    gauge(
+        &mut out,
+        "hashassin_inflight_chunks",
+        "Chunks read but not written yet",
+        snapshot.in_flight,
+    );
+
+    out.push_str(concat!(
+        "# TYPE hashassin_cache_lookups counter\n",
+        "# HELP hashassin_cache_lookups Lookups in the hash cache\n",
+    ));
+    out.push_str(&format!(
+        "hashassin_cache_lookups_total{{result=\"hit\"}} {}\n",
+        cache.hits
+    ));
+    out.push_str(&format!(
+        "hashassin_cache_lookups_total{{result=\"miss\"}} {}\n",
+        cache.misses
+    ));
+    // a ratio is meaningless before the first lookup, the metric is left out until then
+    if let Some(hit_ratio) = cache.hit_ratio() {
+        gauge(
+            &mut out,
+            "hashassin_cache_hit_ratio",
+            "Share of the lookups in the hash cache that found their hash",
+            hit_ratio,
+        );
+    }
+
+    counter(
+        &mut out,
+        "hashassin_errors",
+        "Jobs that failed",
+        snapshot.errors,
+    );
+    out.push_str("# EOF\n");
+
+    out
+}
+
+fn counter(out: &mut String, name: &str, help: &str, value: u64) {
+    out.push_str(&format!(
+        "# TYPE {name} counter\n# HELP {name} {help}\n{name}_total {value}\n"
+    ));
+}
+
+fn gauge(out: &mut String, name: &str, help: &str, value: impl Display) {
+    out.push_str(&format!(
+        "# TYPE {name} gauge\n# HELP {name} {help}\n{name} {value}\n"
+    ));
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::db::CacheStats;
+    use crate::metrics::render;
+    use crate::stats::StatsSnapshot;
+    use std::time::Duration;
+
+    #[test]
+    fn test_render_metrics() {
+        let snapshot = StatsSnapshot {
+            generated: 120,
+            written: 100,
+            queued: 3,
+            ..StatsSnapshot::default()
+        };
+        let cache = CacheStats { hits: 1, misses: 3 };
+
+        let metrics = render(&snapshot, &cache, Duration::from_secs(4));
+        let lines = metrics.lines().collect::<Vec<_>>();
+
+        assert!(lines.contains(&"hashassin_candidates_generated_total 120"));
+        assert!(lines.contains(&"hashassin_hash_rate 25"));
+        assert!(lines.contains(&"hashassin_queued_chunks 3"));
+        assert!(lines.contains(&"hashassin_cache_lookups_total{result=\"miss\"} 3"));
+        assert!(lines.contains(&"hashassin_cache_hit_ratio 0.25"));
+        assert_eq!(lines.last(), Some(&"# EOF"));
+
+        let metrics = render(&snapshot, &CacheStats::default(), Duration::ZERO);
+        assert!(!metrics.contains("hashassin_cache_hit_ratio"));
+    }
+}
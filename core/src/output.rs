@@ -0,0 +1,374 @@
+//! Buffered, optionally compressed, output files.
+//!
+//! Compressed files are written as a series of independent gzip members or zstd frames, one per
+//! checkpoint, so that a file truncated back to a checkpoint is still a valid compressed file.
+use crate::checkpoint::Checkpointer;
+pub use crate::compression::Compression;
+use crate::format::OutputFormat;
+use crate::HashAlgorithm;
+use anyhow::Result;
+use flate2::write::GzEncoder;
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{BufWriter, Seek, Write};
+
+/// How the output file of a hashing job is written
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct OutputOptions {
+    /// Format of every record
+    pub format: OutputFormat,
+    /// Compression of the whole file
+    pub compression: Compression,
+}
+
+/// Path of the output file of `algorithm` when hashing with several algorithms into separate files.
+/// The name of the algorithm is added before the extensions, e.g. `hashes.md5.txt.gz`.
+pub fn algorithm_path(path: &str, algorithm: HashAlgorithm) -> String {
+    let name_start = path.rfind('/').map_or(0, |i| i + 1);
+    // a leading dot is part of the name of a hidden file, not an extension
+    let extension = path[name_start..]
+        .char_indices()
+        .skip(1)
+        .find(|(_, c)| *c == '.');
+
+    match extension {
+        Some((i, _)) => {
+            let i = name_start + i;
+            format!("{}.{}{}", &path[..i], algorithm.name(), &path[i..])
+        }
+        None => format!("{}.{}", path, algorithm.name()),
+    }
+}
+
+/// Puts chunks that finish out of order back in sequence.
+///
+/// Every chunk gets a sequence number when it is read, chunks are only released once every
+/// chunk with a lower sequence number has been released.
+pub(crate) struct ReorderBuffer<T> {
+    /// Sequence number of the next chunk to release
+    next: u64,
+    /// Chunks that finished before the chunks preceding them
+    pending: BTreeMap<u64, T>,
+}
+
+impl<T> ReorderBuffer<T> {
+    pub(crate) fn new() -> Self {
+        Self {
+            next: 0,
+            pending: BTreeMap::new(),
+        }
+    }
+
+    /// Adds the chunk with sequence number `seq`
+    pub(crate) fn push(&mut self, seq: u64, chunk: T) {
+        self.pending.insert(seq, chunk);
+    }
+
+    /// Releases the next chunk in sequence, if it has been pushed already
+    pub(crate) fn pop(&mut self) -> Option<T> {
+        let chunk = self.pending.remove(&self.next)?;
+        self.next += 1;
+        Some(chunk)
+    }
+}
+
+enum Encoder {
+    Plain(BufWriter<File>),
+    Gzip(GzEncoder<BufWriter<File>>),
+    Zstd(zstd::Encoder<'static, BufWriter<File>>),
+}
+
+/// A buffered output file, see [`OutputWriter::create`]
+pub struct OutputWriter {
+    /// `None` only while switching to a new compressed member
+    encoder: Option<Encoder>,
+}
+
+impl OutputWriter {
+    /// Creates the output file at `path`
+    pub fn create(path: &str, compression: Compression) -> Result<Self> {
+        Self::new(File::create(path)?, compression)
+    }
+
+    /// Writes to an already open file, e.g. one truncated to its checkpointed length by
+    /// [`Checkpointer::open_output`]
+    pub fn new(file: File, compression: Compression) -> Result<Self> {
+        Ok(Self {
+            encoder: Some(Self::encoder(BufWriter::new(file), compression)?),
+        })
+    }
+
+    fn encoder(file: BufWriter<File>, compression: Compression) -> Result<Encoder> {
+        Ok(match compression {
+            Compression::None => Encoder::Plain(file),
+            Compression::Gzip => {
+                Encoder::Gzip(GzEncoder::new(file, flate2::Compression::default()))
+            }
+            Compression::Zstd => Encoder::Zstd(zstd::Encoder::new(file, 0)?),
+        })
+    }
+
+    fn encoder_mut(&mut self) -> &mut Encoder {
+        self.encoder
+            .as_mut()
+            .expect("output writer is always left with an encoder")
+    }
+
+    /// Makes everything written so far durable in the file, ending the current compressed
+    /// member. Returns the length of the file, which is a valid point to truncate it to.
+    pub fn sync(&mut self) -> Result<u64> {
+        let (mut file, compression) = self.end_member()?;
+        let len = file.get_mut().stream_position()?;
+        self.encoder = Some(Self::encoder(file, compression)?);
+
+        Ok(len)
+    }
+
+    /// Flushes and closes the file, returns its length
+    pub fn finish(mut self) -> Result<u64> {
+        // unlike `sync`, no new member is started, it would end up as an empty one in the file
+        let (mut file, _) = self.end_member()?;
+        let len = file.get_mut().stream_position()?;
+
+        Ok(len)
+    }
+
+    /// Ends the current compressed member, flushes the file and writes it through to the disk,
+    /// so that a length recorded in a checkpoint survives a crash of the machine
+    fn end_member(&mut self) -> Result<(BufWriter<File>, Compression)> {
+        let (mut file, compression) = match self.encoder.take() {
+            Some(Encoder::Plain(file)) => (file, Compression::None),
+            Some(Encoder::Gzip(encoder)) => (encoder.finish()?, Compression::Gzip),
+            Some(Encoder::Zstd(encoder)) => (encoder.finish()?, Compression::Zstd),
+            None => unreachable!("output writer is always left with an encoder"),
+        };
+        file.flush()?;
+        file.get_ref().sync_data()?;
+
+        Ok((file, compression))
+    }
+}
+
+impl Write for OutputWriter {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        match self.encoder_mut() {
+            Encoder::Plain(file) => file.write(buf),
+            Encoder::Gzip(encoder) => encoder.write(buf),
+            Encoder::Zstd(encoder) => encoder.write(buf),
+        }
+    }
+
+    fn write_all(&mut self, buf: &[u8]) -> std::io::Result<()> {
+        match self.encoder_mut() {
+            Encoder::Plain(file) => file.write_all(buf),
+            Encoder::Gzip(encoder) => encoder.write_all(buf),
+            Encoder::Zstd(encoder) => encoder.write_all(buf),
+        }
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        match self.encoder_mut() {
+            Encoder::Plain(file) => file.flush(),
+            Encoder::Gzip(encoder) => encoder.flush(),
+            Encoder::Zstd(encoder) => encoder.flush(),
+        }
+    }
+}
+
+/// Writes chunks of a job to its [`OutputWriter`]s and keeps the job's checkpoint in sync with
+/// them. Every chunk has some output for each of the files.
+pub(crate) struct CheckpointedWriter {
+    outs: Vec<OutputWriter>,
+    checkpointer: Option<Checkpointer>,
+}
+
+impl CheckpointedWriter {
+    /// Creates the output files at `paths`, truncated to the checkpoint when resuming
+    pub(crate) fn create(
+        paths: &[String],
+        compression: Compression,
+        checkpointer: Option<Checkpointer>,
+    ) -> Result<Self> {
+        let outs = match &checkpointer {
+            Some(checkpointer) => {
+                checkpointer.check_outputs(paths.len())?;
+                paths
+                    .iter()
+                    .enumerate()
+                    .map(|(i, path)| {
+                        OutputWriter::new(checkpointer.open_output(i, path)?, compression)
+                    })
+                    .collect::<Result<_>>()?
+            }
+            None => paths
+                .iter()
+                .map(|path| OutputWriter::create(path, compression))
+                .collect::<Result<_>>()?,
+        };
+
+        Ok(Self { outs, checkpointer })
+    }
+
+    /// Number of output files
+    pub(crate) fn outputs(&self) -> usize {
+        self.outs.len()
+    }
+
+    /// Writes bytes that do not belong to any chunk, e.g. a header, to every output file that
+    /// is still empty, so that resuming does not repeat them
+    pub(crate) fn write_header(&mut self, bytes: &[u8]) -> Result<()> {
+        for (i, out) in self.outs.iter_mut().enumerate() {
+            let output_len = self.checkpointer.as_ref().map_or(0, |checkpointer| {
+                checkpointer
+                    .checkpoint()
+                    .output_lens
+                    .get(i)
+                    .copied()
+                    .unwrap_or_default()
+            });
+
+            if output_len == 0 {
+                out.write_all(bytes)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Writes the output of the chunk of `len` passwords starting at `start`, one entry of
+    /// `bytes` per output file, saving the checkpoint when it is due
+    pub(crate) fn write_chunk(&mut self, start: u128, len: u128, bytes: &[Vec<u8>]) -> Result<()> {
+        for (out, bytes) in self.outs.iter_mut().zip(bytes) {
+            out.write_all(bytes)?;
+        }
+
+        if let Some(checkpointer) = &mut self.checkpointer {
+            checkpointer.complete(start, len);
+
+            if checkpointer.is_due() {
+                let output_lens = self
+                    .outs
+                    .iter_mut()
+                    .map(OutputWriter::sync)
+                    .collect::<Result<_>>()?;
+                checkpointer.save(output_lens)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Flushes the output files and saves the checkpoint without marking it as finished,
+    /// so that the job can be resumed
+    pub(crate) fn close(mut self) -> Result<()> {
+        let output_lens = self
+            .outs
+            .iter_mut()
+            .map(OutputWriter::sync)
+            .collect::<Result<_>>()?;
+
+        if let Some(checkpointer) = &mut self.checkpointer {
+            checkpointer.save(output_lens)?;
+        }
+
+        Ok(())
+    }
+
+    /// Flushes the output files and marks the checkpoint as finished
+    pub(crate) fn finish(self) -> Result<()> {
+        let output_lens = self
+            .outs
+            .into_iter()
+            .map(OutputWriter::finish)
+            .collect::<Result<_>>()?;
+
+        if let Some(checkpointer) = self.checkpointer {
+            checkpointer.finish(output_lens)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::checkpoint::Checkpointer;
+    use crate::output::{algorithm_path, Compression, OutputWriter, ReorderBuffer};
+    use crate::HashAlgorithm;
+    use flate2::read::MultiGzDecoder;
+    use flate2::write::GzEncoder;
+    use std::io::{Read, Write};
+    use std::time::Duration;
+
+    #[test]
+    fn test_gzip_output_resumes_at_member_boundary() {
+        let dir = tempfile::tempdir().unwrap();
+        let checkpoint_path = dir.path().join("job.checkpoint");
+        let checkpoint_path = checkpoint_path.to_str().unwrap();
+        let out_path = dir.path().join("out.txt.gz");
+        let out_path = out_path.to_str().unwrap();
+
+        let mut checkpointer =
+            Checkpointer::new(checkpoint_path, Duration::ZERO, true, 1, "").unwrap();
+        let file = checkpointer.open_output(0, out_path).unwrap();
+        let mut out = OutputWriter::new(file, Compression::Gzip).unwrap();
+        out.write_all(b"a\nb\n").unwrap();
+        checkpointer.complete(0, 2);
+        checkpointer.save(vec![out.sync().unwrap()]).unwrap();
+
+        // lost when resuming
+        out.write_all(b"c\n").unwrap();
+        out.finish().unwrap();
+
+        let checkpointer = Checkpointer::new(checkpoint_path, Duration::ZERO, true, 1, "").unwrap();
+        let file = checkpointer.open_output(0, out_path).unwrap();
+        let mut out = OutputWriter::new(file, Compression::Gzip).unwrap();
+        out.write_all(b"x\n").unwrap();
+        out.finish().unwrap();
+
+        let mut content = String::new();
+        MultiGzDecoder::new(std::fs::File::open(out_path).unwrap())
+            .read_to_string(&mut content)
+            .unwrap();
+        assert_eq!(content, "a\nb\nx\n");
+    }
+
+    #[test]
+    fn test_finished_gzip_output_has_no_empty_member() {
+        let dir = tempfile::tempdir().unwrap();
+        let out_path = dir.path().join("out.txt.gz");
+        let out_path = out_path.to_str().unwrap();
+
+        let mut out = OutputWriter::create(out_path, Compression::Gzip).unwrap();
+        out.write_all(b"a\nb\n").unwrap();
+        let len = out.finish().unwrap();
+
+        let mut expected = GzEncoder::new(vec![], flate2::Compression::default());
+        expected.write_all(b"a\nb\n").unwrap();
+        let expected = expected.finish().unwrap();
+        assert_eq!(std::fs::read(out_path).unwrap(), expected);
+        assert_eq!(len, expected.len() as u64);
+    }
+
+    #[test]
+    fn test_reorder_buffer() {
+        let mut buffer = ReorderBuffer::new();
+        buffer.push(1, "b");
+        buffer.push(2, "c");
+        assert_eq!(buffer.pop(), None);
+
+        buffer.push(0, "a");
+        assert_eq!(buffer.pop(), Some("a"));
+        assert_eq!(buffer.pop(), Some("b"));
+        assert_eq!(buffer.pop(), Some("c"));
+        assert_eq!(buffer.pop(), None);
+    }
+
+    #[test]
+    fn test_algorithm_path() {
+        let md5 = HashAlgorithm::Md5;
+        assert_eq!(algorithm_path("hashes.txt.gz", md5), "hashes.md5.txt.gz");
+        assert_eq!(algorithm_path("out/hashes", md5), "out/hashes.md5");
+        assert_eq!(algorithm_path("a.b/.hashes", md5), "a.b/.hashes.md5");
+    }
+}
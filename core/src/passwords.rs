@@ -1,7 +1,8 @@
 //! Functions that take a character set and password length range, and produces a list of all possible passwords
 use crate::errors::GenPasswordsError;
+use crate::keyspace::SeekableGenerator;
 use bitmask_enum::bitmask;
-// use itertools::Itertools;
+use itertools::Itertools;
 
 /// From https://stackoverflow.com/a/71420578
 // Note(saumi): Initially tried using the itertools crate... But it did not have `permutations_with_replacement` so
@@ -34,6 +35,16 @@ impl<I: Copy> PermutationsReplacementIter<I> {
         }
     }
 
+    /// Jumps to the permutation at `index`, where the first item changes the fastest
+    fn set_index(&mut self, mut index: u128) {
+        let len = self.items.len() as u128;
+
+        for idx in self.permutation.iter_mut() {
+            *idx = (index % len) as usize;
+            index /= len;
+        }
+    }
+
     fn build_vec(&self) -> Vec<I> {
         let mut vec = Vec::with_capacity(self.group_len);
 
@@ -91,10 +102,12 @@ pub enum CharSet {
     UpperAlpha,
     /// Numbers from `0-9`
     Numeric,
+    /// Printable ASCII symbols and space. `` !"#$%&'()*+,-./:;<=>?@[\]^_`{|}~``
+    Special,
 }
 
 /// Returns a Vec containing all valid characters
-fn get_chars_vec(val: CharSet) -> Vec<char> {
+pub(crate) fn get_chars_vec(val: CharSet) -> Vec<char> {
     let mut chars = vec![];
 
     if val.contains(CharSet::LowerAlpha) {
@@ -109,6 +122,10 @@ fn get_chars_vec(val: CharSet) -> Vec<char> {
         chars.extend('0'..='9');
     }
 
+    if val.contains(CharSet::Special) {
+        chars.extend((' '..='~').filter(|c| !c.is_ascii_alphanumeric()));
+    }
+
     chars
 }
 
@@ -156,6 +173,8 @@ pub struct PasswordGenerator {
     current_length: usize,
     /// Iterator property to keep track of current permutations
     current_permutations_iter: PermutationsReplacementIter<char>,
+    /// Iterator property to keep track of the index of the next password
+    position: u128,
 }
 
 impl PasswordGenerator {
@@ -168,6 +187,19 @@ impl PasswordGenerator {
         min_length: usize,
         max_length: usize,
         char_set: CharSet,
+    ) -> Result<Self, GenPasswordsError> {
+        Self::with_chars(min_length, max_length, get_chars_vec(char_set))
+    }
+
+    /// Creates a new PasswordGenerator from any characters, e.g. non-ASCII ones
+    /// Requires:
+    /// - min_length: Minimum length for passwords
+    /// - max_length: Maximum length for passwords
+    /// - chars: Valid characters for passwords, duplicates are only used once
+    pub fn with_chars(
+        min_length: usize,
+        max_length: usize,
+        chars: Vec<char>,
     ) -> Result<Self, GenPasswordsError> {
         if min_length == 0 {
             return Err(GenPasswordsError::InvalidMinLengthError(min_length));
@@ -179,7 +211,7 @@ impl PasswordGenerator {
             ));
         }
 
-        let chars = get_chars_vec(char_set);
+        let chars = chars.into_iter().unique().collect::<Vec<_>>();
 
         Ok(Self {
             min_length,
@@ -187,32 +219,132 @@ impl PasswordGenerator {
             valid_chars: chars.clone(),
             current_length: min_length,
             current_permutations_iter: chars.into_iter().permutations_with_replacement(min_length),
+            position: 0,
+        })
+    }
+
+    /// Characters of the passwords, without duplicates
+    pub fn chars(&self) -> &[char] {
+        &self.valid_chars
+    }
+
+    /// Returns the password at `index`, in the order passwords are generated, without
+    /// moving the iterator. Returns `None` if `index` is past the last password.
+    pub fn nth(&self, index: u128) -> Option<String> {
+        let (length, mut offset) = self.locate(index)?;
+        let r = self.valid_chars.len() as u128;
+
+        Some(
+            (0..length)
+                .map(|_| {
+                    let c = self.valid_chars[(offset % r) as usize];
+                    offset /= r;
+                    c
+                })
+                .collect(),
+        )
+    }
+
+    /// Returns the index of `password` in the order passwords are generated.
+    /// Returns `None` if this generator never generates `password`.
+    pub fn index_of(&self, password: &str) -> Option<u128> {
+        let length = password.chars().count();
+        if length < self.min_length || length > self.max_length {
+            return None;
+        }
+
+        let r = self.valid_chars.len() as u128;
+        let offset = password.chars().rev().try_fold(0u128, |offset, c| {
+            let digit = self.valid_chars.iter().position(|v| *v == c)?;
+            offset.checked_mul(r)?.checked_add(digit as u128)
+        })?;
+
+        // skip over every password shorter than `password`
+        (self.min_length..length).try_fold(offset, |index, l| {
+            index.checked_add(r.checked_pow(l as u32)?)
         })
     }
 
+    /// Returns the length of the password at `index` and its offset among the passwords
+    /// of that length.
+    fn locate(&self, index: u128) -> Option<(usize, u128)> {
+        locate(
+            self.valid_chars.len(),
+            self.min_length,
+            self.max_length,
+            index,
+        )
+    }
+
     /// Used MATH to calculate how many passwords will be generated! LUL
     ///
-    /// *NB:* This method is unreliable if there are more than a `u32` worth
-    /// passwords to be generator.
-    ///
-    /// Also, technically speaking, we can generate more than a `usize` worth
-    /// of passwords.
-    #[deprecated]
-    pub fn len(&self) -> usize {
-        let r = self.valid_chars.len();
-        let a = r.pow(self.min_length as u32);
-        let n = (self.max_length - self.min_length + 1) as u32; // Could possibly lose some bits of info
-
-        a * ((r.pow(n) - 1) / (r - 1))
+    /// Returns `None` if there are more passwords than fit in a `u128`.
+    pub fn keyspace(&self) -> Option<u128> {
+        brute_force_keyspace(self.valid_chars.len(), self.min_length, self.max_length)
+    }
+
+    /// Number of passwords left to generate, `None` if it does not fit in a `u128`
+    fn remaining(&self) -> Option<u128> {
+        Some(self.keyspace()?.saturating_sub(self.position))
+    }
+}
+
+impl SeekableGenerator for PasswordGenerator {
+    fn keyspace(&self) -> Option<u128> {
+        PasswordGenerator::keyspace(self)
+    }
+
+    fn seek(&mut self, index: u128) {
+        self.position = index;
+
+        if let Some((length, offset)) = self.locate(index) {
+            self.current_length = length;
+            self.current_permutations_iter = self
+                .valid_chars
+                .clone()
+                .into_iter()
+                .permutations_with_replacement(length);
+            self.current_permutations_iter.set_index(offset);
+        } else {
+            self.current_length = self.max_length + 1;
+        }
+    }
+}
+
+/// Number of passwords of every length between `min_length` and `max_length` made of `r`
+/// characters. Returns `None` if there are more passwords than fit in a `u128`.
+pub(crate) fn brute_force_keyspace(r: usize, min_length: usize, max_length: usize) -> Option<u128> {
+    (min_length..=max_length).try_fold(0u128, |total, length| {
+        total.checked_add((r as u128).checked_pow(length.try_into().ok()?)?)
+    })
+}
+
+/// Returns the length of the password at `index` and its offset among the passwords of that
+/// length, when passwords made of `r` characters are generated from the shortest to the longest.
+pub(crate) fn locate(
+    r: usize,
+    min_length: usize,
+    max_length: usize,
+    mut index: u128,
+) -> Option<(usize, u128)> {
+    let r = r as u128;
+
+    for length in min_length..=max_length {
+        match r.checked_pow(length as u32) {
+            Some(count) if index >= count => index -= count,
+            // more passwords of this length than fit in a `u128`, so index is in there
+            _ => return Some((length, index)),
+        }
     }
 
-    /// Returns whether or not this [`PasswordGenerator`] is empty.
-    /// This function exists because `len()` exists, but `len()` is not
-    /// really safe/normal behavior.
-    #[deprecated]
-    #[allow(deprecated)]
-    pub fn is_empty(&self) -> bool {
-        self.len() == 0
+    None
+}
+
+/// Turns the number of passwords a generator has left into an [`Iterator::size_hint`]
+pub(crate) fn size_hint(remaining: Option<u128>) -> (usize, Option<usize>) {
+    match remaining.map(usize::try_from) {
+        Some(Ok(remaining)) => (remaining, Some(remaining)),
+        _ => (usize::MAX, None),
     }
 }
 
@@ -225,7 +357,7 @@ impl Iterator for PasswordGenerator {
             return None;
         }
 
-        if let Some(next_item) = self.current_permutations_iter.next() {
+        let password = if let Some(next_item) = self.current_permutations_iter.next() {
             Some(next_item.into_iter().collect())
         } else {
             self.current_length += 1;
@@ -249,27 +381,110 @@ impl Iterator for PasswordGenerator {
             self.current_permutations_iter
                 .next()
                 .map(|next_item| next_item.into_iter().collect())
+        };
+
+        // the position only moves for passwords that are actually produced
+        if password.is_some() {
+            self.position += 1;
         }
+        password
+    }
+
+    /// Jumps straight to the password `n` positions ahead instead of generating
+    /// every password in between.
+    fn nth(&mut self, n: usize) -> Option<Self::Item> {
+        self.seek(self.position.saturating_add(n as u128));
+        self.next()
+    }
+
+    /// Exact as long as the number of passwords left fits in a `usize`. This is also why
+    /// [`ExactSizeIterator`] is not implemented, use [`PasswordGenerator::keyspace`] instead.
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        size_hint(self.remaining())
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::errors::GenPasswordsError;
     use crate::passwords::{CharSet, PasswordGenerator};
 
     #[test]
-    #[allow(deprecated)]
-    fn test_password_generator_len() {
+    fn test_password_generator_keyspace() {
         let password_gen =
             PasswordGenerator::new(2, 4, CharSet::LowerAlpha | CharSet::Numeric).unwrap();
 
-        let len = password_gen.len();
+        let keyspace = password_gen.keyspace();
         let mut counter = 0;
         for _ in password_gen {
             counter += 1;
         }
-        assert_eq!(len, counter);
+        assert_eq!(keyspace, Some(counter));
+    }
+
+    #[test]
+    fn test_password_generator_keyspace_overflow() {
+        let password_gen = PasswordGenerator::new(1, 100, CharSet::all_bits()).unwrap();
+        assert_eq!(password_gen.keyspace(), None);
+        assert_eq!(password_gen.size_hint(), (usize::MAX, None));
+    }
+
+    #[test]
+    fn test_password_generator_size_hint() {
+        let mut password_gen = PasswordGenerator::new(1, 2, CharSet::Numeric).unwrap();
+        assert_eq!(password_gen.size_hint(), (110, Some(110)));
+
+        Iterator::nth(&mut password_gen, 9);
+        assert_eq!(password_gen.size_hint(), (100, Some(100)));
+
+        password_gen.by_ref().for_each(drop);
+        assert_eq!(password_gen.size_hint(), (0, Some(0)));
+    }
+
+    #[test]
+    fn test_password_generator_random_access() {
+        let password_gen =
+            PasswordGenerator::new(1, 3, CharSet::LowerAlpha | CharSet::Numeric).unwrap();
+        let passwords = PasswordGenerator::new(1, 3, CharSet::LowerAlpha | CharSet::Numeric)
+            .unwrap()
+            .collect::<Vec<_>>();
+
+        for (index, password) in passwords.iter().enumerate() {
+            assert_eq!(password_gen.nth(index as u128).as_ref(), Some(password));
+            assert_eq!(password_gen.index_of(password), Some(index as u128));
+        }
+
+        assert_eq!(password_gen.nth(passwords.len() as u128), None);
+        assert_eq!(password_gen.index_of("abcd"), None);
+        assert_eq!(password_gen.index_of("A"), None);
+    }
+
+    #[test]
+    fn test_password_generator_skip() {
+        let passwords = PasswordGenerator::new(2, 3, CharSet::LowerAlpha)
+            .unwrap()
+            .collect::<Vec<_>>();
+
+        let mut password_gen = PasswordGenerator::new(2, 3, CharSet::LowerAlpha).unwrap();
+        assert_eq!(
+            Iterator::nth(&mut password_gen, 700),
+            Some(passwords[700].clone())
+        );
+        assert_eq!(password_gen.next(), Some(passwords[701].clone()));
+
+        let skipped = PasswordGenerator::new(2, 3, CharSet::LowerAlpha)
+            .unwrap()
+            .skip(600)
+            .collect::<Vec<_>>();
+        assert_eq!(skipped, passwords[600..]);
+    }
+
+    #[test]
+    fn test_password_generator_unicode_chars() {
+        let passwords = PasswordGenerator::with_chars(1, 2, vec!['ä', '€', 'ä'])
+            .unwrap()
+            .collect::<Vec<_>>();
+
+        assert_eq!(passwords, vec!["ä", "€", "ää", "€ä", "ä€", "€€"]);
     }
 
     // TODO fix these tests
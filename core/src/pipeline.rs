@@ -0,0 +1,821 @@
+//! A hashing job as a pipeline: candidates are read in chunks from a [`Source`], hashed by a
+//! [`Hasher`] on several threads, and the hashes of every chunk are handed to a [`Sink`].
+//!
+//! Every stage is connected by bounded channels, so memory use does not grow when a stage cannot
+//! keep up. The first stage to fail stops the others and its error is returned, a cancelled
+//! pipeline stops at the next candidate and returns [`PipelineError::CancelledError`].
+//!
+//! Usage:
+//! ```Rust
+//! let hasher = CandidateHasher::new(vec![HashAlgorithm::Md5], Encoding::Utf8);
+//! let source = FileSource::open("words.txt", range)?;
+//! let matches = Pipeline::new(source, hasher, MatcherSink::new(targets))
+//!     .threads(threads)
+//!     .run(&cancel)?;
+//! ```
+use crate::abort::Abort;
+use crate::cancel::CancellationToken;
+use crate::checkpoint::{Checkpoint, Checkpointer};
+use crate::encoding::Encoding;
+use crate::errors::{CheckpointError, PipelineError};
+use crate::output::ReorderBuffer;
+use crate::stats::Stats;
+use crate::{hash_candidate, HashAlgorithm};
+use anyhow::Result;
+use crossbeam_channel::{Receiver, Sender};
+use rayon::prelude::{IndexedParallelIterator, IntoParallelIterator, ParallelIterator};
+use rayon::{ThreadPool, ThreadPoolBuilder};
+use std::num::NonZeroUsize;
+use std::sync::Arc;
+use std::thread::JoinHandle;
+use tracing::trace;
+
+/// Where the candidates of a [`Pipeline`] come from, see [`crate::source`]
+pub trait Source: Send {
+    /// Reads the next `len` candidates, fewer only at the end of the source and none once it is
+    /// exhausted. `None` stands for a candidate the source could not read: it is skipped, but
+    /// still counts for the position of the following ones.
+    fn read_chunk(&mut self, len: usize) -> Result<Vec<Option<String>>>;
+
+    /// What a candidate of this source is called in error messages, e.g. `line` for a file
+    fn candidate_name(&self) -> &'static str {
+        "candidate"
+    }
+
+    /// Number of bytes read so far, for sources reading from a file or a stream
+    fn bytes_read(&self) -> u64 {
+        0
+    }
+}
+
+impl<S: Source + ?Sized> Source for Box<S> {
+    fn read_chunk(&mut self, len: usize) -> Result<Vec<Option<String>>> {
+        (**self).read_chunk(len)
+    }
+
+    fn candidate_name(&self) -> &'static str {
+        (**self).candidate_name()
+    }
+
+    fn bytes_read(&self) -> u64 {
+        (**self).bytes_read()
+    }
+}
+
+/// Turns candidates into hashes, shared by every worker thread of a [`Pipeline`]
+pub trait Hasher: Send + Sync {
+    /// Algorithms every candidate is hashed with
+    fn algorithms(&self) -> &[HashAlgorithm];
+
+    /// Hashes `candidate` with every algorithm, in the order of [`Hasher::algorithms`]
+    fn hash(&self, candidate: &str) -> Result<Vec<Vec<u8>>>;
+}
+
+/// Where the hashes of a [`Pipeline`] go, see [`crate::sink`]
+pub trait Sink {
+    /// What the sink produces once every chunk is written
+    type Output;
+
+    /// Called once before the first chunk. With a checkpointer, the sink records every chunk it
+    /// is done with and saves the checkpoint when it is due.
+    fn open(
+        &mut self,
+        algorithms: &[HashAlgorithm],
+        checkpointer: Option<Checkpointer>,
+    ) -> Result<()>;
+
+    /// Takes the hashes of a chunk
+    fn write_chunk(&mut self, chunk: HashedChunk) -> Result<()>;
+
+    /// Number of candidates matched so far, for sinks looking for hashes
+    fn matched(&self) -> u64 {
+        0
+    }
+
+    /// Called after the last chunk
+    fn finish(self) -> Result<Self::Output>;
+
+    /// Called instead of [`Sink::finish`] when the pipeline stopped early. What was written so
+    /// far is kept and the checkpoint is saved, so that the job can be resumed.
+    fn close(self) -> Result<()>;
+}
+
+/// The hashes of a chunk of candidates
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HashedChunk {
+    /// Position of the first candidate of the chunk in the source
+    pub start: u128,
+    /// Number of candidates in the chunk, including the ones that were skipped
+    pub len: u128,
+    /// Every candidate that was hashed with its hashes, in the order of [`Hasher::algorithms`]
+    pub hashes: Vec<(String, Vec<Vec<u8>>)>,
+}
+
+/// How the candidates of a [`Pipeline`] are hashed in parallel
+#[derive(Debug, Clone, Default)]
+pub enum Executor {
+    /// One worker thread per thread of the pipeline, each hashing whole chunks
+    #[default]
+    ThreadPool,
+
+    /// The candidates of every chunk are hashed in parallel by a rayon thread pool with the
+    /// number of threads of the pipeline, which only lives as long as the pipeline runs
+    Rayon,
+
+    /// The candidates of every chunk are hashed in parallel by the caller's rayon thread pool,
+    /// e.g. to share it between jobs. The number of threads of the pipeline is ignored.
+    RayonPool(Arc<ThreadPool>),
+}
+
+/// Hashes candidates with [`hash_candidate`]
+#[derive(Debug, Clone)]
+pub struct CandidateHasher {
+    algorithms: Vec<HashAlgorithm>,
+    encoding: Encoding,
+}
+
+impl CandidateHasher {
+    /// Creates a new CandidateHasher
+    /// Requires:
+    /// - algorithms: Algorithms every candidate is hashed with
+    /// - encoding: How candidates are turned into bytes before hashing
+    pub fn new(algorithms: Vec<HashAlgorithm>, encoding: Encoding) -> Self {
+        Self {
+            algorithms,
+            encoding,
+        }
+    }
+}
+
+impl Hasher for CandidateHasher {
+    fn algorithms(&self) -> &[HashAlgorithm] {
+        &self.algorithms
+    }
+
+    fn hash(&self, candidate: &str) -> Result<Vec<Vec<u8>>> {
+        self.algorithms
+            .iter()
+            .map(|algorithm| hash_candidate(*algorithm, candidate, self.encoding))
+            .collect()
+    }
+}
+
+/// A chunk of candidates: sequence number of the chunk, position of its first candidate and
+/// every candidate
+type CandidateChunk = (u64, u128, Vec<Option<String>>);
+
+/// Builds and runs a hashing job, see the [module documentation](self)
+pub struct Pipeline<S> {
+    source: Box<dyn Source>,
+    hasher: Arc<dyn Hasher>,
+    sink: S,
+    executor: Executor,
+    threads: NonZeroUsize,
+    chunk_size: NonZeroUsize,
+    max_inflight_chunks: NonZeroUsize,
+    ordered: bool,
+    first_index: u128,
+    checkpointer: Option<Checkpointer>,
+    stats: Arc<Stats>,
+}
+
+impl<S: Sink> Pipeline<S> {
+    /// Number of candidates in a chunk, unless set with [`Pipeline::chunk_size`]
+    pub const DEFAULT_CHUNK_SIZE: usize = 1000;
+
+    /// Number of chunks in flight, unless set with [`Pipeline::max_inflight_chunks`]
+    pub const DEFAULT_MAX_INFLIGHT_CHUNKS: usize = 16;
+
+    /// Creates a new Pipeline, hashing on one thread per CPU
+    /// Requires:
+    /// - source: Where the candidates come from
+    /// - hasher: How every candidate is hashed
+    /// - sink: Where the hashes go
+    pub fn new(source: impl Source + 'static, hasher: impl Hasher + 'static, sink: S) -> Self {
+        Self {
+            source: Box::new(source),
+            hasher: Arc::new(hasher),
+            sink,
+            executor: Executor::default(),
+            threads: std::thread::available_parallelism().unwrap_or(NonZeroUsize::MIN),
+            chunk_size: NonZeroUsize::new(Self::DEFAULT_CHUNK_SIZE).unwrap_or(NonZeroUsize::MIN),
+            max_inflight_chunks: NonZeroUsize::new(Self::DEFAULT_MAX_INFLIGHT_CHUNKS)
+                .unwrap_or(NonZeroUsize::MIN),
+            ordered: true,
+            first_index: 1,
+            checkpointer: None,
+            stats: Arc::default(),
+        }
+    }
+
+    /// How candidates are hashed in parallel
+    pub fn executor(mut self, executor: Executor) -> Self {
+        self.executor = executor;
+        self
+    }
+
+    /// Number of threads hashing candidates
+    pub fn threads(mut self, threads: NonZeroUsize) -> Self {
+        self.threads = threads;
+        self
+    }
+
+    /// Number of candidates read from the source and sent to a worker thread at once.
+    /// Must be the chunk size of the checkpoint when resuming.
+    pub fn chunk_size(mut self, chunk_size: NonZeroUsize) -> Self {
+        self.chunk_size = chunk_size;
+        self
+    }
+
+    /// Maximum number of chunks read from the source but not written to the sink yet.
+    /// Reading waits when hashing or writing cannot keep up, which bounds memory use.
+    pub fn max_inflight_chunks(mut self, max_inflight_chunks: NonZeroUsize) -> Self {
+        self.max_inflight_chunks = max_inflight_chunks;
+        self
+    }
+
+    /// Whether chunks are written in the order of the source, on by default.
+    /// Writing chunks as soon as they are hashed is faster with many threads.
+    pub fn ordered(mut self, ordered: bool) -> Self {
+        self.ordered = ordered;
+        self
+    }
+
+    /// Number of the first candidate of the source in error messages, e.g. the line of the file
+    /// it was read from. Defaults to 1.
+    pub fn first_index(mut self, first_index: u128) -> Self {
+        self.first_index = first_index;
+        self
+    }
+
+    /// Checkpoints the job. The source must start right after the checkpoint's position,
+    /// chunks after it that are already done are skipped.
+    pub fn checkpointer(mut self, checkpointer: Option<Checkpointer>) -> Self {
+        self.checkpointer = checkpointer;
+        self
+    }
+
+    /// Counters updated as the job runs, to watch its progress
+    pub fn stats(mut self, stats: Arc<Stats>) -> Self {
+        self.stats = stats;
+        self
+    }
+
+    /// Runs the job until the source is exhausted, returns what the sink produced.
+    /// Once `cancel` is cancelled, no more candidates are read, hashes that are done are written
+    /// and the checkpoint is saved.
+    pub fn run(self, cancel: &CancellationToken) -> Result<S::Output> {
+        let Pipeline {
+            source,
+            hasher,
+            mut sink,
+            executor,
+            threads,
+            chunk_size,
+            max_inflight_chunks,
+            ordered,
+            first_index,
+            checkpointer,
+            stats,
+        } = self;
+
+        // candidates before the checkpoint's position are done, chunks are numbered from there
+        let checkpoint = checkpointer
+            .as_ref()
+            .map(|checkpointer| checkpointer.checkpoint().clone())
+            .unwrap_or_default();
+        if checkpointer.is_some() && checkpoint.chunk_size != chunk_size.get() as u64 {
+            return Err(CheckpointError::ChunkSizeMismatchError(
+                checkpoint.chunk_size,
+                chunk_size.get() as u64,
+            )
+            .into());
+        }
+
+        // rayon's global pool is left alone, so that jobs do not depend on how the embedding
+        // application configured it
+        let pool = match executor {
+            Executor::ThreadPool => None,
+            Executor::Rayon => Some(Arc::new(
+                ThreadPoolBuilder::new()
+                    .num_threads(threads.into())
+                    .build()?,
+            )),
+            Executor::RayonPool(pool) => Some(pool),
+        };
+
+        sink.open(hasher.algorithms(), checkpointer)?;
+        let candidate_name = source.candidate_name();
+
+        let max_inflight_chunks = max_inflight_chunks.get();
+        let (candidates_tx, candidates_rx) = crossbeam_channel::bounded(max_inflight_chunks);
+        // one slot per chunk that is read but not written yet, the reader waits for a free slot.
+        // this also bounds the chunks waiting for the ones before them when the output is ordered.
+        let (slot_tx, slot_rx) = crossbeam_channel::bounded(max_inflight_chunks);
+        let (hashed_tx, hashed_rx) = crossbeam_channel::bounded(max_inflight_chunks);
+
+        // the first thread to fail stops the others, its error is returned
+        let abort = Abort::new(cancel);
+
+        let reader = abort.spawn({
+            let abort = abort.clone();
+            let stats = stats.clone();
+            move || {
+                read_chunks(
+                    source,
+                    chunk_size.get(),
+                    checkpoint,
+                    candidates_tx,
+                    slot_tx,
+                    &stats,
+                    &abort,
+                )
+            }
+        });
+
+        let workers = start_workers(
+            pool,
+            threads,
+            hasher,
+            candidates_rx,
+            hashed_tx,
+            stats.clone(),
+            candidate_name,
+            first_index,
+            &abort,
+        );
+
+        // the sink is written from this thread, so that it does not need to be sent anywhere
+        let written = write_chunks(&mut sink, &hashed_rx, &slot_rx, ordered, &stats, &abort);
+        let write_failed = written.is_err();
+        if let Err(error) = written {
+            abort.fail(error);
+        }
+
+        // wait for the reader and the compute threads to finish
+        abort.join([reader]);
+        abort.join(workers);
+        stats.set_queue_depth(0, 0);
+
+        if abort.is_aborted() {
+            // keep what was written so far, resuming redoes the rest. a sink that failed to
+            // write a chunk may be left half way through it, so its checkpoint is not saved.
+            if !write_failed {
+                trace!("pipeline stopped, closing the sink");
+                if let Err(error) = sink.close() {
+                    abort.fail(error);
+                }
+            }
+
+            // a pipeline is only aborted by an error or by cancelling it, which `result` returns
+            let error = abort
+                .result()
+                .expect_err("an aborted pipeline has an error");
+            if !matches!(error.downcast_ref(), Some(PipelineError::CancelledError(_))) {
+                stats.add_error();
+            }
+
+            return Err(error);
+        }
+
+        trace!("finishing the sink");
+        sink.finish()
+    }
+}
+
+/// Reads chunks of candidates from `source` until it is exhausted
+fn read_chunks(
+    mut source: Box<dyn Source>,
+    chunk_size: usize,
+    checkpoint: Checkpoint,
+    candidates_tx: Sender<CandidateChunk>,
+    slot_tx: Sender<()>,
+    stats: &Stats,
+    abort: &Abort,
+) -> Result<()> {
+    let mut seq = 0;
+    let mut position = checkpoint.position;
+
+    while !abort.is_aborted() {
+        let candidates = source.read_chunk(chunk_size)?;
+        if candidates.is_empty() {
+            break;
+        }
+
+        let start = position;
+        position += candidates.len() as u128;
+
+        if checkpoint.is_completed(start) {
+            continue;
+        }
+
+        let valid = candidates.iter().flatten().count();
+        stats.add_generated(valid as u64);
+        stats.add_skipped((candidates.len() - valid) as u64);
+        stats.set_bytes_read(source.bytes_read());
+
+        if !abort.send_unless_cancelled(&slot_tx, ())?
+            || !abort.send_unless_cancelled(&candidates_tx, (seq, start, candidates))?
+        {
+            break;
+        }
+        seq += 1;
+
+        stats.set_queue_depth(candidates_tx.len(), slot_tx.len());
+    }
+
+    Ok(())
+}
+
+/// Spawns the threads hashing the chunks of `candidates_rx`, with `pool` if any
+#[allow(clippy::too_many_arguments)]
+fn start_workers(
+    pool: Option<Arc<ThreadPool>>,
+    threads: NonZeroUsize,
+    hasher: Arc<dyn Hasher>,
+    candidates_rx: Receiver<CandidateChunk>,
+    hashed_tx: Sender<(u64, HashedChunk)>,
+    stats: Arc<Stats>,
+    candidate_name: &'static str,
+    first_index: u128,
+    abort: &Arc<Abort>,
+) -> Vec<JoinHandle<()>> {
+    // rayon spreads the candidates of a chunk over its own threads
+    let workers = if pool.is_some() { 1 } else { threads.get() };
+
+    (0..workers)
+        .map(|i| {
+            let hasher = hasher.clone();
+            let candidates_rx = candidates_rx.clone();
+            let hashed_tx = hashed_tx.clone();
+            let stats = stats.clone();
+            let thread_abort = abort.clone();
+            let pool = pool.clone();
+
+            abort.spawn(move || {
+                while let Some((seq, start, candidates)) = thread_abort.recv(&candidates_rx) {
+                    let len = candidates.len() as u128;
+                    let hash = |(offset, candidate): (usize, Option<String>)| {
+                        // a partly hashed chunk is dropped, it is hashed again when resuming
+                        if thread_abort.is_failed() {
+                            return None;
+                        }
+
+                        let index = first_index + start + offset as u128;
+                        let hashed = hash_one(&*hasher, candidate_name, index, candidate?);
+                        Some(hashed)
+                    };
+
+                    let hashes = match &pool {
+                        None => candidates
+                            .into_iter()
+                            .enumerate()
+                            .filter_map(hash)
+                            .collect::<Result<Vec<_>>>()?,
+                        Some(pool) => pool.install(|| {
+                            candidates
+                                .into_par_iter()
+                                .enumerate()
+                                .filter_map(hash)
+                                .collect::<Result<Vec<_>>>()
+                        })?,
+                    };
+
+                    if thread_abort.is_failed() {
+                        break;
+                    }
+                    stats.add_hashed(hashes.len() as u64);
+
+                    let chunk = HashedChunk { start, len, hashes };
+                    if !thread_abort.send(&hashed_tx, (seq, chunk))? {
+                        break;
+                    }
+                }
+                trace!("Thread {} had finished.", i);
+
+                Ok(())
+            })
+        })
+        .collect()
+}
+
+/// Hashes the candidate at `index` of the source, called `candidate_name` in errors
+fn hash_one(
+    hasher: &dyn Hasher,
+    candidate_name: &'static str,
+    index: u128,
+    candidate: String,
+) -> Result<(String, Vec<Vec<u8>>)> {
+    let hashes = match hasher.hash(&candidate) {
+        Ok(hashes) => hashes,
+        Err(e) => {
+            let error =
+                PipelineError::InvalidCandidateError(candidate_name, index, candidate, e.into());
+            return Err(error.into());
+        }
+    };
+    trace!("{:?}", hashes);
+
+    Ok((candidate, hashes))
+}
+
+/// Hands the hashed chunks to the sink, in the order of the source if `ordered`
+fn write_chunks<S: Sink>(
+    sink: &mut S,
+    hashed_rx: &Receiver<(u64, HashedChunk)>,
+    slot_rx: &Receiver<()>,
+    ordered: bool,
+    stats: &Stats,
+    abort: &Abort,
+) -> Result<()> {
+    let mut reorder = ReorderBuffer::new();
+
+    while let Some((seq, chunk)) = abort.recv(hashed_rx) {
+        if !ordered {
+            write_chunk(sink, chunk, slot_rx, stats, abort)?;
+            continue;
+        }
+
+        reorder.push(seq, chunk);
+        while let Some(chunk) = reorder.pop() {
+            write_chunk(sink, chunk, slot_rx, stats, abort)?;
+        }
+    }
+
+    Ok(())
+}
+
+fn write_chunk<S: Sink>(
+    sink: &mut S,
+    chunk: HashedChunk,
+    slot_rx: &Receiver<()>,
+    stats: &Stats,
+    abort: &Abort,
+) -> Result<()> {
+    let len = chunk.len as u64;
+    let hashed = chunk.hashes.len() as u64;
+    sink.write_chunk(chunk)?;
+    slot_rx.recv()?;
+
+    abort.completed(len);
+    stats.add_written(hashed);
+    stats.set_matched(sink.matched());
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::cancel::CancellationToken;
+    use crate::checkpoint::Checkpointer;
+    use crate::encoding::Encoding;
+    use crate::errors::PipelineError;
+    use crate::format::OutputFormat;
+    use crate::keyspace::KeyspaceRange;
+    use crate::output::OutputOptions;
+    use crate::pipeline::{CandidateHasher, Executor, HashedChunk, Pipeline, Sink, Source};
+    use crate::sink::{FileSink, MatcherSink, TableSink};
+    use crate::source::{FileSource, VecSource};
+    use crate::stats::Stats;
+    use crate::utils::vec_to_hex_string;
+    use crate::HashAlgorithm;
+    use anyhow::Result;
+    use hex_literal::hex;
+    use rayon::ThreadPoolBuilder;
+    use std::num::NonZeroUsize;
+    use std::sync::Arc;
+
+    /// Collects the chunks it receives
+    #[derive(Default)]
+    struct ChunkSink(Vec<HashedChunk>);
+
+    impl Sink for ChunkSink {
+        type Output = Vec<HashedChunk>;
+
+        fn open(&mut self, _: &[HashAlgorithm], _: Option<Checkpointer>) -> Result<()> {
+            Ok(())
+        }
+
+        fn write_chunk(&mut self, chunk: HashedChunk) -> Result<()> {
+            self.0.push(chunk);
+            Ok(())
+        }
+
+        fn finish(self) -> Result<Self::Output> {
+            Ok(self.0)
+        }
+
+        fn close(self) -> Result<()> {
+            Ok(())
+        }
+    }
+
+    /// Every other candidate cannot be read
+    struct GappySource(std::ops::Range<usize>);
+
+    impl Source for GappySource {
+        fn read_chunk(&mut self, len: usize) -> Result<Vec<Option<String>>> {
+            Ok((&mut self.0)
+                .take(len)
+                .map(|i| (i % 2 == 0).then(|| i.to_string()))
+                .collect())
+        }
+    }
+
+    fn md5() -> CandidateHasher {
+        CandidateHasher::new(vec![HashAlgorithm::Md5], Encoding::Latin1)
+    }
+
+    fn threads(n: usize) -> NonZeroUsize {
+        NonZeroUsize::new(n).unwrap()
+    }
+
+    #[test]
+    fn test_chunks_are_written_in_order() {
+        let stats = Arc::new(Stats::new());
+        let chunks = Pipeline::new(GappySource(0..100), md5(), ChunkSink::default())
+            .threads(threads(8))
+            .chunk_size(threads(7))
+            .max_inflight_chunks(threads(2))
+            .stats(stats.clone())
+            .run(&CancellationToken::new())
+            .unwrap();
+
+        let snapshot = stats.snapshot();
+        assert_eq!(
+            (snapshot.generated, snapshot.hashed, snapshot.written),
+            (50, 50, 50)
+        );
+        assert_eq!(snapshot.skipped, 50);
+
+        let starts = chunks.iter().map(|chunk| chunk.start).collect::<Vec<_>>();
+        assert_eq!(starts, (0..100).step_by(7).collect::<Vec<_>>());
+        assert_eq!(chunks.iter().map(|chunk| chunk.len).sum::<u128>(), 100);
+
+        let candidates = chunks
+            .iter()
+            .flat_map(|chunk| &chunk.hashes)
+            .map(|(candidate, _)| candidate.clone())
+            .collect::<Vec<_>>();
+        let expected = (0..100)
+            .step_by(2)
+            .map(|i| i.to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(candidates, expected);
+    }
+
+    #[test]
+    fn test_rayon_executor_runs_repeatedly() {
+        let pool = Arc::new(ThreadPoolBuilder::new().num_threads(2).build().unwrap());
+        let executors = [
+            Executor::Rayon,
+            Executor::Rayon,
+            Executor::RayonPool(pool.clone()),
+            Executor::RayonPool(pool),
+        ];
+
+        for executor in executors {
+            let chunks = Pipeline::new(GappySource(0..50), md5(), ChunkSink::default())
+                .executor(executor)
+                .threads(threads(3))
+                .chunk_size(threads(8))
+                .run(&CancellationToken::new())
+                .unwrap();
+
+            let hashed = chunks.iter().map(|chunk| chunk.hashes.len()).sum::<usize>();
+            assert_eq!(hashed, 25);
+        }
+    }
+
+    #[test]
+    fn test_table_and_matcher_sinks() {
+        let passwords = vec!["abc".to_owned(), "password".to_owned()];
+
+        let table = Pipeline::new(VecSource::new(passwords.clone()), md5(), TableSink::new())
+            .run(&CancellationToken::new())
+            .unwrap();
+        assert_eq!(table.len(), 2);
+        assert_eq!(
+            table.lookup(
+                HashAlgorithm::Md5,
+                &hex!("900150983cd24fb0d6963f7d28e17f72")
+            ),
+            Some("abc")
+        );
+
+        let targets = [hex!("5f4dcc3b5aa765d61d8327deb882cf99").to_vec()];
+        let matches = Pipeline::new(VecSource::new(passwords), md5(), MatcherSink::new(targets))
+            .run(&CancellationToken::new())
+            .unwrap();
+        assert_eq!(matches.len(), 1);
+        assert_eq!(matches[0].password, "password");
+        assert_eq!(matches[0].algorithm, HashAlgorithm::Md5);
+    }
+
+    #[test]
+    fn test_every_line_gets_a_record() {
+        let dir = tempfile::tempdir().unwrap();
+        let in_path = dir.path().join("words.txt");
+        let in_path = in_path.to_str().unwrap();
+        std::fs::write(in_path, b"a\n\xff\xfe\nb\n").unwrap();
+
+        let md5s = [
+            hex!("0cc175b9c0f1b6a831c399e269772661"),
+            hex!("f3b25701fe362ec84616a93a45ce9998"),
+            hex!("92eb5ffee6ae2fec3ad71c777531578f"),
+        ];
+
+        for format in [OutputFormat::Raw, OutputFormat::Hex] {
+            let out_path = dir.path().join("hashes.md5");
+            let out_path = out_path.to_str().unwrap();
+            let options = OutputOptions {
+                format,
+                ..OutputOptions::default()
+            };
+
+            let source = FileSource::open(in_path, KeyspaceRange::new(0, None)).unwrap();
+            Pipeline::new(source, md5(), FileSink::new(out_path, options))
+                .run(&CancellationToken::new())
+                .unwrap();
+
+            let expected = match format {
+                OutputFormat::Raw => md5s.concat(),
+                _ => md5s
+                    .iter()
+                    .map(|md5| format!("{}\n", vec_to_hex_string(md5)))
+                    .collect::<String>()
+                    .into_bytes(),
+            };
+            assert_eq!(std::fs::read(out_path).unwrap(), expected);
+        }
+    }
+
+    #[test]
+    fn test_invalid_candidate_stops_the_pipeline() {
+        let passwords = (0..1000).map(|i| format!("{i}€")).collect();
+        let hasher = CandidateHasher::new(vec![HashAlgorithm::Md5], Encoding::Latin1);
+        let stats = Arc::new(Stats::new());
+
+        let error = Pipeline::new(VecSource::new(passwords), hasher, ChunkSink::default())
+            .threads(threads(4))
+            .chunk_size(threads(10))
+            .stats(stats.clone())
+            .run(&CancellationToken::new())
+            .unwrap_err();
+
+        assert!(matches!(
+            error.downcast_ref::<PipelineError>(),
+            Some(PipelineError::InvalidCandidateError("candidate", ..))
+        ));
+        assert_eq!(stats.snapshot().errors, 1);
+    }
+
+    #[test]
+    fn test_cancelled_pipeline() {
+        let cancel = CancellationToken::new();
+        cancel.cancel();
+
+        let error = Pipeline::new(GappySource(0..100), md5(), ChunkSink::default())
+            .run(&cancel)
+            .unwrap_err();
+
+        assert!(matches!(
+            error.downcast_ref::<PipelineError>(),
+            Some(PipelineError::CancelledError(0))
+        ));
+    }
+
+    /// Cancels the pipeline while reading its second chunk
+    struct CancellingSource(CancellationToken, std::ops::Range<usize>);
+
+    impl Source for CancellingSource {
+        fn read_chunk(&mut self, len: usize) -> Result<Vec<Option<String>>> {
+            if self.1.start > 0 {
+                self.0.cancel();
+            }
+
+            Ok((&mut self.1).take(len).map(|i| Some(i.to_string())).collect())
+        }
+    }
+
+    #[test]
+    fn test_cancelled_pipeline_writes_chunks_read() {
+        let cancel = CancellationToken::new();
+        let stats = Arc::new(Stats::new());
+        let source = CancellingSource(cancel.clone(), 0..100);
+
+        let error = Pipeline::new(source, md5(), ChunkSink::default())
+            .chunk_size(threads(10))
+            .stats(stats.clone())
+            .run(&cancel)
+            .unwrap_err();
+
+        // the first chunk was read before cancelling, so it is hashed and written
+        let written = stats.snapshot().written;
+        assert!(written >= 10);
+        assert!(matches!(
+            error.downcast_ref::<PipelineError>(),
+            Some(PipelineError::CancelledError(done)) if *done == written
+        ));
+    }
+}
@@ -0,0 +1,482 @@
+//! Password policies, e.g. "8 to 12 characters with at least one digit and one upper case letter".
+//!
+//! Any candidate stream can be filtered with [`PolicyFilterExt::with_policy`]. For brute force,
+//! [`PolicyGenerator`] skips whole regions of the keyspace that cannot conform to the policy
+//! instead of generating and rejecting every password in them.
+use crate::encoding::from_hex_notation;
+use crate::errors::GenPasswordsError;
+use crate::keyspace::SeekableGenerator;
+use crate::passwords::{get_chars_vec, size_hint, CharSet};
+use itertools::Itertools;
+
+/// Classes of characters a policy can require, in the order they are numbered internally
+const CLASSES: [CharSet; 4] = [
+    CharSet::LowerAlpha,
+    CharSet::UpperAlpha,
+    CharSet::Numeric,
+    CharSet::Special,
+];
+
+/// Class of a character: lower case, upper case, digit or special.
+/// Letters that are neither lower nor upper case (e.g. most CJK characters) have no class.
+pub fn char_class(c: char) -> CharSet {
+    if c.is_lowercase() {
+        CharSet::LowerAlpha
+    } else if c.is_uppercase() {
+        CharSet::UpperAlpha
+    } else if c.is_numeric() {
+        CharSet::Numeric
+    } else if !c.is_alphanumeric() {
+        CharSet::Special
+    } else {
+        CharSet::none()
+    }
+}
+
+/// Rules a password must follow to be accepted by a target system
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Policy {
+    /// Minimum number of characters
+    pub min_length: usize,
+    /// Maximum number of characters
+    pub max_length: usize,
+    /// Classes of characters that must all appear at least once
+    pub required: CharSet,
+}
+
+impl Default for Policy {
+    fn default() -> Self {
+        Self {
+            min_length: 0,
+            max_length: usize::MAX,
+            required: CharSet::none(),
+        }
+    }
+}
+
+impl Policy {
+    /// Creates a new Policy
+    /// Requires:
+    /// - min_length: Minimum number of characters
+    /// - max_length: Maximum number of characters
+    /// - required: Classes of characters that must all appear at least once
+    pub fn new(min_length: usize, max_length: usize, required: CharSet) -> Self {
+        Self {
+            min_length,
+            max_length,
+            required,
+        }
+    }
+
+    /// Whether `password` follows this policy
+    pub fn allows(&self, password: &str) -> bool {
+        let length = password.chars().count();
+        let present = password
+            .chars()
+            .fold(CharSet::none(), |present, c| present | char_class(c));
+
+        self.follows(length, present)
+    }
+
+    /// Whether the raw bytes of a candidate follow this policy. Every byte counts as one
+    /// character, only ASCII bytes belong to a class.
+    pub fn allows_bytes(&self, bytes: &[u8]) -> bool {
+        let present = bytes
+            .iter()
+            .filter(|b| b.is_ascii())
+            .fold(CharSet::none(), |present, b| {
+                present | char_class(char::from(*b))
+            });
+
+        self.follows(bytes.len(), present)
+    }
+
+    /// Whether a line of a wordlist follows this policy, `$HEX[...]` lines are checked on the
+    /// bytes that get hashed, see [`Policy::allows_bytes`]
+    pub fn allows_candidate(&self, candidate: &str) -> bool {
+        match from_hex_notation(candidate) {
+            Some(bytes) => self.allows_bytes(&bytes),
+            None => self.allows(candidate),
+        }
+    }
+
+    /// Whether `length` characters of the classes `present` follow this policy
+    fn follows(&self, length: usize, present: CharSet) -> bool {
+        length >= self.min_length && length <= self.max_length && present.contains(self.required)
+    }
+}
+
+/// Adds [`PolicyFilterExt::with_policy`] to every candidate iterator
+pub trait PolicyFilterExt: Iterator<Item = String> + Sized {
+    /// Only keeps the candidates that follow `policy`, see [`Policy::allows_candidate`]
+    fn with_policy(self, policy: Policy) -> PolicyFilter<Self> {
+        PolicyFilter {
+            candidates: self,
+            policy,
+        }
+    }
+}
+
+impl<I: Iterator<Item = String>> PolicyFilterExt for I {}
+
+/// Iterator over the candidates that follow a policy, see [`PolicyFilterExt::with_policy`]
+pub struct PolicyFilter<I> {
+    candidates: I,
+    policy: Policy,
+}
+
+impl<I: Iterator<Item = String>> Iterator for PolicyFilter<I> {
+    type Item = String;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let policy = self.policy;
+        self.candidates
+            .find(|password| policy.allows_candidate(password))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (0, self.candidates.size_hint().1)
+    }
+}
+
+/// Struct Iterator to iterate over the passwords of a [`crate::passwords::PasswordGenerator`]
+/// that follow a policy, in the same order.
+///
+/// Usage:
+/// ```Rust
+/// let policy = Policy::new(8, 12, CharSet::UpperAlpha | CharSet::Numeric);
+/// let passwords = policy::PolicyGenerator::new(1, 12, CharSet::all_bits(), policy)?;
+///
+/// for password in passwords {
+///     println!("Password: {}", password);
+/// }
+/// ```
+pub struct PolicyGenerator {
+    /// Minimum length of passwords, within the policy
+    min_length: usize,
+    /// Maximum length of passwords, within the policy
+    max_length: usize,
+    /// Valid characters for passwords
+    valid_chars: Vec<char>,
+    /// Index into the required classes of every valid character, `None` if it is not required
+    classes: Vec<Option<usize>>,
+    /// Number of classes the policy requires
+    required: usize,
+    /// Iterator property to keep track of current length of passwords
+    current_length: usize,
+    /// Iterator property to keep track of the index into `valid_chars` of every position,
+    /// the first position changes the fastest
+    digits: Vec<usize>,
+    /// Iterator property to keep track of the index of the next password that follows the policy
+    position: u128,
+}
+
+impl PolicyGenerator {
+    /// Creates a new PolicyGenerator
+    /// Requires:
+    /// - min_length: Minimum length for passwords
+    /// - max_length: Maximum length for passwords
+    /// - char_set: Valid characters for passwords
+    /// - policy: Policy passwords must follow
+    pub fn new(
+        min_length: usize,
+        max_length: usize,
+        char_set: CharSet,
+        policy: Policy,
+    ) -> Result<Self, GenPasswordsError> {
+        Self::with_chars(min_length, max_length, get_chars_vec(char_set), policy)
+    }
+
+    /// Creates a new PolicyGenerator from any characters, e.g. non-ASCII ones
+    /// Requires:
+    /// - min_length: Minimum length for passwords
+    /// - max_length: Maximum length for passwords
+    /// - chars: Valid characters for passwords, duplicates are only used once
+    /// - policy: Policy passwords must follow
+    pub fn with_chars(
+        min_length: usize,
+        max_length: usize,
+        chars: Vec<char>,
+        policy: Policy,
+    ) -> Result<Self, GenPasswordsError> {
+        if min_length == 0 {
+            return Err(GenPasswordsError::InvalidMinLengthError(min_length));
+        }
+
+        if min_length > max_length {
+            return Err(GenPasswordsError::MinGreaterThanMaxError(
+                min_length, max_length,
+            ));
+        }
+
+        let valid_chars = chars.into_iter().unique().collect::<Vec<_>>();
+        let required = CLASSES
+            .into_iter()
+            .filter(|class| policy.required.contains(*class))
+            .collect::<Vec<_>>();
+        let classes = valid_chars
+            .iter()
+            .map(|c| required.iter().position(|class| *class == char_class(*c)))
+            .collect();
+
+        let min_length = min_length.max(policy.min_length);
+        let max_length = if valid_chars.is_empty() {
+            0
+        } else {
+            max_length.min(policy.max_length)
+        };
+
+        let mut generator = Self {
+            min_length,
+            max_length,
+            valid_chars,
+            classes,
+            required: required.len(),
+            current_length: min_length,
+            digits: vec![0; min_length],
+            position: 0,
+        };
+        generator.seek(0);
+
+        Ok(generator)
+    }
+
+    /// Number of passwords that follow the policy.
+    /// Returns `None` if there are more passwords than fit in a `u128`.
+    pub fn keyspace(&self) -> Option<u128> {
+        let all = (1 << self.required) - 1;
+
+        (self.min_length..=self.max_length).try_fold(0u128, |total, length| {
+            total.checked_add(self.conforming(length, all)?)
+        })
+    }
+
+    /// Number of passwords of `length` characters containing every class of `missing`, a bitmask
+    /// over the required classes. Returns `None` if it does not fit in a `u128`.
+    fn conforming(&self, length: usize, missing: u8) -> Option<u128> {
+        let r = self.valid_chars.len() as u128;
+        let (mut positive, mut negative) = (0u128, 0u128);
+
+        // inclusion-exclusion over the classes that are left out
+        for excluded in 0..=missing {
+            if excluded & !missing != 0 {
+                continue;
+            }
+
+            let left_out = self
+                .classes
+                .iter()
+                .filter(|class| class.is_some_and(|class| excluded & (1 << class) != 0))
+                .count() as u128;
+            let count = (r - left_out).checked_pow(length.try_into().ok()?)?;
+
+            if excluded.count_ones() % 2 == 0 {
+                positive = positive.checked_add(count)?;
+            } else {
+                negative = negative.checked_add(count)?;
+            }
+        }
+
+        Some(positive - negative)
+    }
+
+    /// Bitmask of the required classes missing from `digits`
+    fn missing(&self, digits: &[usize]) -> u8 {
+        let all = (1 << self.required) - 1;
+
+        digits
+            .iter()
+            .fold(all, |missing, digit| match self.classes[*digit] {
+                Some(class) => missing & !(1 << class),
+                None => missing,
+            })
+    }
+
+    /// Skips the `r^k` passwords that share every position from `k` on with the current password,
+    /// moving on to the next length once every password of the current length was skipped
+    fn skip_block(&mut self, k: usize) {
+        for digit in &mut self.digits[..k] {
+            *digit = 0;
+        }
+
+        for digit in &mut self.digits[k..] {
+            *digit += 1;
+
+            if *digit < self.valid_chars.len() {
+                return;
+            }
+
+            *digit = 0;
+        }
+
+        self.current_length += 1;
+        self.digits = vec![0; self.current_length];
+    }
+
+    /// Largest `k` such that the positions from `k` on leave more required classes missing
+    /// than the `k` positions before them can fill. `None` if the current password follows
+    /// the policy.
+    fn unreachable_block(&self) -> Option<usize> {
+        (0..=self.current_length)
+            .rev()
+            .find(|k| self.missing(&self.digits[*k..]).count_ones() as usize > *k)
+    }
+
+    fn build_string(&self) -> String {
+        self.digits.iter().map(|d| self.valid_chars[*d]).collect()
+    }
+}
+
+impl Iterator for PolicyGenerator {
+    type Item = String;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if self.current_length > self.max_length {
+                return None;
+            }
+
+            match self.unreachable_block() {
+                Some(k) => self.skip_block(k),
+                None => {
+                    let password = self.build_string();
+                    self.skip_block(0);
+                    self.position += 1;
+
+                    return Some(password);
+                }
+            }
+        }
+    }
+
+    fn nth(&mut self, n: usize) -> Option<Self::Item> {
+        self.seek(self.position.saturating_add(n as u128));
+        self.next()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        size_hint(
+            self.keyspace()
+                .map(|keyspace| keyspace.saturating_sub(self.position)),
+        )
+    }
+}
+
+impl SeekableGenerator for PolicyGenerator {
+    fn keyspace(&self) -> Option<u128> {
+        PolicyGenerator::keyspace(self)
+    }
+
+    fn seek(&mut self, mut index: u128) {
+        let all = (1 << self.required) - 1;
+        self.position = index;
+
+        for length in self.min_length..=self.max_length {
+            match self.conforming(length, all) {
+                Some(count) if index >= count => index -= count,
+                // the password at index has this length, pick every character from the
+                // slowest changing position to the fastest
+                _ => {
+                    self.current_length = length;
+                    self.digits = vec![0; length];
+                    let mut missing = all;
+
+                    for pos in (0..length).rev() {
+                        for digit in 0..self.valid_chars.len() {
+                            let left = missing & self.missing(&[digit]);
+
+                            match self.conforming(pos, left) {
+                                Some(count) if index >= count => index -= count,
+                                _ => {
+                                    self.digits[pos] = digit;
+                                    missing = left;
+                                    break;
+                                }
+                            }
+                        }
+                    }
+
+                    return;
+                }
+            }
+        }
+
+        self.current_length = self.max_length + 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::bytes::ByteGenerator;
+    use crate::keyspace::{KeyspaceRange, SeekableGenerator};
+    use crate::passwords::{CharSet, PasswordGenerator};
+    use crate::policy::{Policy, PolicyFilterExt, PolicyGenerator};
+
+    #[test]
+    fn test_policy_allows() {
+        let policy = Policy::new(4, 6, CharSet::UpperAlpha | CharSet::Numeric);
+
+        assert!(policy.allows("Abc1"));
+        assert!(policy.allows("ÄBC1"));
+        assert!(!policy.allows("Ab1"));
+        assert!(!policy.allows("Abcdef1"));
+        assert!(!policy.allows("abc1"));
+        assert!(!policy.allows("Abcd"));
+    }
+
+    #[test]
+    fn test_policy_allows_hex_candidates() {
+        let policy = Policy::new(2, 2, CharSet::UpperAlpha);
+
+        assert!(policy.allows_candidate("$HEX[41ff]"));
+        assert!(policy.allows_candidate("A!"));
+        // `ä` is two bytes in UTF-8, neither of them an upper case letter
+        assert!(!policy.allows_candidate("$HEX[c3a4]"));
+        assert!(!policy.allows_candidate("$HEX[41]"));
+
+        let candidates = ByteGenerator::new(1, 1, (0..=255).collect())
+            .unwrap()
+            .hex_encoded()
+            .with_policy(Policy::new(0, usize::MAX, CharSet::UpperAlpha))
+            .collect::<Vec<_>>();
+        let expected = ('A'..='Z').map(String::from).collect::<Vec<_>>();
+        assert_eq!(candidates, expected);
+    }
+
+    #[test]
+    fn test_policy_generator_matches_filter() {
+        let char_set = CharSet::LowerAlpha | CharSet::UpperAlpha | CharSet::Numeric;
+        let policy = Policy::new(2, 3, CharSet::UpperAlpha | CharSet::Numeric);
+
+        let filtered = PasswordGenerator::new(1, 3, char_set)
+            .unwrap()
+            .with_policy(policy)
+            .collect::<Vec<_>>();
+        let generator = PolicyGenerator::new(1, 3, char_set, policy).unwrap();
+
+        assert_eq!(generator.keyspace(), Some(filtered.len() as u128));
+        assert_eq!(generator.collect::<Vec<_>>(), filtered);
+
+        let slice = PolicyGenerator::new(1, 3, char_set, policy)
+            .unwrap()
+            .in_range(KeyspaceRange::new(1000, Some(500)))
+            .collect::<Vec<_>>();
+        assert_eq!(slice, filtered[1000..1500]);
+    }
+
+    #[test]
+    fn test_policy_generator_unreachable_lengths() {
+        let policy = Policy::new(
+            0,
+            usize::MAX,
+            CharSet::LowerAlpha | CharSet::UpperAlpha | CharSet::Numeric,
+        );
+        let passwords = PolicyGenerator::new(1, 3, CharSet::all_bits(), policy)
+            .unwrap()
+            .take(3)
+            .collect::<Vec<_>>();
+
+        // every password of one or two characters is skipped without being generated
+        assert_eq!(passwords, vec!["0Aa", "1Aa", "2Aa"]);
+    }
+}
@@ -0,0 +1,412 @@
+//! PRINCE (PRobability INfinite Chained Elements) candidate generation.
+//!
+//! Passwords are built by chaining words of a single wordlist together. Every possible chain of
+//! word lengths whose total length is in the requested range is generated, one total length at a
+//! time from the shortest, with the chains made of the most common word lengths first.
+//!
+//! Only the chains of the current total length are kept in memory: the number of chains grows
+//! exponentially with the length, the number of passwords of every length is counted without them.
+use crate::errors::GenPasswordsError;
+use crate::keyspace::SeekableGenerator;
+use crate::passwords::size_hint;
+use std::cmp::Ordering;
+
+/// A sequence of word lengths that make up passwords
+struct Chain {
+    /// Length of every word in the chain
+    lengths: Vec<usize>,
+    /// Number of passwords generated by this chain, saturated at `u128::MAX`
+    keyspace: u128,
+    /// How likely a password with this chain is according to the wordlist
+    probability: f64,
+}
+
+/// Struct Iterator to iterate over all chains of words of a wordlist
+///
+/// Usage:
+/// ```Rust
+/// let words = utils::read_lines("wordlist.txt")?;
+/// let passwords = prince::PrinceGenerator::new(words, 6, 12, 4)?;
+///
+/// for password in passwords {
+///     println!("Password: {}", password);
+/// }
+/// ```
+pub struct PrinceGenerator {
+    /// Words of the wordlist, grouped by their number of characters
+    words_by_length: Vec<Vec<String>>,
+    /// Number of words of the wordlist that fit in a password
+    total_words: usize,
+    /// Maximum length of passwords
+    max_length: usize,
+    /// Maximum number of words chained in a single password
+    max_elements: usize,
+    /// Number of passwords of every total length, `None` if it does not fit in a `u128`
+    length_keyspaces: Vec<Option<u128>>,
+    /// Total number of passwords, `None` if it does not fit in a `u128`
+    keyspace: Option<u128>,
+    /// Iterator property to keep track of the total length of the current chains
+    current_length: usize,
+    /// Every chain of word lengths of the current total length, most likely first
+    chains: Vec<Chain>,
+    /// Iterator property to keep track of the current chain
+    chain_idx: usize,
+    /// Iterator property to keep track of the index of the first password of the current chain
+    chain_start: u128,
+    /// Iterator property to keep track of the index of the next password
+    position: u128,
+}
+
+impl PrinceGenerator {
+    /// Creates a new PrinceGenerator
+    /// Requires:
+    /// - words: Wordlist to chain words from
+    /// - min_length: Minimum length for passwords
+    /// - max_length: Maximum length for passwords
+    /// - max_elements: Maximum number of words chained in a single password
+    pub fn new(
+        words: Vec<String>,
+        min_length: usize,
+        max_length: usize,
+        max_elements: usize,
+    ) -> Result<Self, GenPasswordsError> {
+        if min_length == 0 {
+            return Err(GenPasswordsError::InvalidMinLengthError(min_length));
+        }
+
+        if min_length > max_length {
+            return Err(GenPasswordsError::MinGreaterThanMaxError(
+                min_length, max_length,
+            ));
+        }
+
+        let mut words_by_length = vec![vec![]; max_length + 1];
+        let mut total_words = 0;
+        for word in words {
+            let len = word.chars().count();
+            if len > 0 && len <= max_length {
+                words_by_length[len].push(word);
+                total_words += 1;
+            }
+        }
+
+        let mut length_keyspaces = length_keyspaces(&words_by_length, max_elements);
+        length_keyspaces[..min_length].fill(Some(0));
+        let keyspace = length_keyspaces
+            .iter()
+            .try_fold(0u128, |total, keyspace| total.checked_add((*keyspace)?));
+
+        let mut generator = Self {
+            words_by_length,
+            total_words,
+            max_length,
+            max_elements,
+            length_keyspaces,
+            keyspace,
+            current_length: min_length,
+            chains: vec![],
+            chain_idx: 0,
+            chain_start: 0,
+            position: 0,
+        };
+        generator.chains = generator.chains_of(min_length);
+
+        Ok(generator)
+    }
+
+    /// Total number of passwords this generator can produce.
+    /// Returns `None` if that number does not fit in a `u128`.
+    pub fn keyspace(&self) -> Option<u128> {
+        self.keyspace
+    }
+
+    /// Every chain of available word lengths adding up to `length`, most likely first
+    fn chains_of(&self, length: usize) -> Vec<Chain> {
+        let mut chains = vec![];
+        self.build_chains(length, &mut vec![], &mut chains);
+
+        chains.sort_by(|a, b| {
+            b.probability
+                .partial_cmp(&a.probability)
+                .unwrap_or(Ordering::Equal)
+                .then_with(|| a.lengths.cmp(&b.lengths))
+        });
+
+        chains
+    }
+
+    /// Recursively collects every chain of available word lengths starting with `lengths` that
+    /// adds up to `length`
+    fn build_chains(&self, length: usize, lengths: &mut Vec<usize>, chains: &mut Vec<Chain>) {
+        let current_length: usize = lengths.iter().sum();
+
+        if current_length == length {
+            chains.push(Chain {
+                lengths: lengths.clone(),
+                keyspace: lengths.iter().fold(1u128, |total, len| {
+                    total.saturating_mul(self.words_by_length[*len].len() as u128)
+                }),
+                probability: lengths
+                    .iter()
+                    .map(|len| self.words_by_length[*len].len() as f64 / self.total_words as f64)
+                    .product(),
+            });
+            return;
+        }
+
+        if lengths.len() >= self.max_elements {
+            return;
+        }
+
+        for len in 1..=(length - current_length) {
+            if self.words_by_length[len].is_empty() {
+                continue;
+            }
+
+            lengths.push(len);
+            self.build_chains(length, lengths, chains);
+            lengths.pop();
+        }
+    }
+
+    /// Moves to the chains of the next total length, `false` once there is none left
+    fn next_length(&mut self) -> bool {
+        if self.current_length >= self.max_length {
+            self.current_length = self.max_length + 1;
+            self.chains = vec![];
+            return false;
+        }
+
+        self.current_length += 1;
+        self.chains = self.chains_of(self.current_length);
+        self.chain_idx = 0;
+        true
+    }
+
+    /// Builds the password at `offset` of a chain. The first word of the chain changes the slowest.
+    fn build_string(&self, chain: &Chain, mut offset: u128) -> String {
+        let mut words = Vec::with_capacity(chain.lengths.len());
+
+        for len in chain.lengths.iter().rev() {
+            let candidates = &self.words_by_length[*len];
+            let count = candidates.len() as u128;
+            words.push(candidates[(offset % count) as usize].as_str());
+            offset /= count;
+        }
+
+        words.into_iter().rev().collect()
+    }
+}
+
+/// Number of passwords of every total length up to the length of `words_by_length`, chaining at
+/// most `max_elements` words, `None` for the lengths whose number does not fit in a `u128`
+fn length_keyspaces(words_by_length: &[Vec<String>], max_elements: usize) -> Vec<Option<u128>> {
+    let max_length = words_by_length.len() - 1;
+
+    // passwords of every total length chaining exactly as many words as the current iteration
+    let mut chained = vec![Some(0u128); max_length + 1];
+    chained[0] = Some(1);
+    let mut keyspaces = vec![Some(0u128); max_length + 1];
+
+    // every word has at least one character
+    for _ in 0..max_elements.min(max_length) {
+        chained = (0..=max_length)
+            .map(|length| {
+                (1..=length).try_fold(0u128, |total, len| {
+                    let words = words_by_length[len].len() as u128;
+                    if words == 0 {
+                        return Some(total);
+                    }
+
+                    total.checked_add(chained[length - len]?.checked_mul(words)?)
+                })
+            })
+            .collect();
+
+        for (keyspace, chained) in keyspaces.iter_mut().zip(&chained) {
+            *keyspace = keyspace.zip(*chained).and_then(|(a, b)| a.checked_add(b));
+        }
+    }
+
+    keyspaces
+}
+
+impl Iterator for PrinceGenerator {
+    type Item = String;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.chain_idx >= self.chains.len() {
+            if !self.next_length() {
+                return None;
+            }
+        }
+
+        let chain = &self.chains[self.chain_idx];
+        let password = self.build_string(chain, self.position - self.chain_start);
+
+        // keyspaces saturate at `u128::MAX`, so do the positions past them
+        self.position = self.position.saturating_add(1);
+        if self.position - self.chain_start >= chain.keyspace {
+            self.chain_start = self.chain_start.saturating_add(chain.keyspace);
+            self.chain_idx += 1;
+        }
+
+        Some(password)
+    }
+
+    fn nth(&mut self, n: usize) -> Option<Self::Item> {
+        self.seek(self.position.saturating_add(n as u128));
+        self.next()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        size_hint(
+            self.keyspace
+                .map(|keyspace| keyspace.saturating_sub(self.position)),
+        )
+    }
+}
+
+impl SeekableGenerator for PrinceGenerator {
+    fn keyspace(&self) -> Option<u128> {
+        self.keyspace
+    }
+
+    fn seek(&mut self, index: u128) {
+        self.position = index;
+
+        // only the chains of the length `index` is in are built
+        let mut length_start = 0u128;
+        let length = (0..=self.max_length).find(|length| {
+            let keyspace = self.length_keyspaces[*length].unwrap_or(u128::MAX);
+            if index - length_start < keyspace {
+                return true;
+            }
+
+            length_start = length_start.saturating_add(keyspace);
+            false
+        });
+        let Some(length) = length else {
+            self.current_length = self.max_length + 1;
+            self.chains = vec![];
+            self.chain_idx = 0;
+            return;
+        };
+
+        if length != self.current_length {
+            self.current_length = length;
+            self.chains = self.chains_of(length);
+        }
+        self.chain_idx = 0;
+        self.chain_start = length_start;
+
+        while let Some(chain) = self.chains.get(self.chain_idx) {
+            if index - self.chain_start < chain.keyspace {
+                break;
+            }
+
+            self.chain_start = self.chain_start.saturating_add(chain.keyspace);
+            self.chain_idx += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::errors::GenPasswordsError;
+    use crate::keyspace::{KeyspaceRange, SeekableGenerator};
+    use crate::prince::PrinceGenerator;
+
+    fn words(words: &[&str]) -> Vec<String> {
+        words.iter().map(|w| w.to_string()).collect()
+    }
+
+    #[test]
+    fn test_prince_chains() {
+        let passwords = PrinceGenerator::new(words(&["a", "b", "cd"]), 2, 3, 2)
+            .unwrap()
+            .collect::<Vec<_>>();
+
+        // chains [1, 1] (4 passwords), then [2] , then [1, 2] and [2, 1]
+        assert_eq!(
+            passwords,
+            words(&["aa", "ab", "ba", "bb", "cd", "acd", "bcd", "cda", "cdb"])
+        );
+    }
+
+    #[test]
+    fn test_prince_skip_limit() {
+        let generator = PrinceGenerator::new(words(&["a", "b", "cd"]), 2, 3, 2).unwrap();
+        assert_eq!(generator.keyspace(), Some(9));
+
+        let all = generator.collect::<Vec<_>>();
+        let part = PrinceGenerator::new(words(&["a", "b", "cd"]), 2, 3, 2)
+            .unwrap()
+            .in_range(KeyspaceRange::new(3, Some(4)))
+            .collect::<Vec<_>>();
+
+        assert_eq!(part, all[3..7]);
+        assert_eq!(
+            PrinceGenerator::new(words(&["a", "b", "cd"]), 2, 3, 2)
+                .unwrap()
+                .in_range(KeyspaceRange::new(5, None))
+                .size_hint(),
+            (4, Some(4))
+        );
+
+        // ranges starting in every chain of every length
+        let all = PrinceGenerator::new(words(&["a", "b", "cd", "efg"]), 2, 6, 3)
+            .unwrap()
+            .collect::<Vec<_>>();
+        for skip in 0..all.len() {
+            let part = PrinceGenerator::new(words(&["a", "b", "cd", "efg"]), 2, 6, 3)
+                .unwrap()
+                .in_range(KeyspaceRange::new(skip as u128, Some(3)))
+                .collect::<Vec<_>>();
+            assert_eq!(part, all[skip..all.len().min(skip + 3)]);
+        }
+    }
+
+    #[test]
+    fn test_prince_large_max_length_starts_promptly() {
+        // words of 12 lengths chained into up to 64 characters make far too many chains to build
+        let lengths = ('a'..='l')
+            .enumerate()
+            .map(|(i, c)| c.to_string().repeat(i + 1))
+            .collect();
+        let mut generator = PrinceGenerator::new(lengths, 1, 64, 8).unwrap();
+        assert!(generator.keyspace().is_some());
+        assert_eq!(
+            generator.by_ref().take(3).collect::<Vec<_>>(),
+            ["a", "bb", "aa"]
+        );
+
+        let generator = PrinceGenerator::new(words(&["a", "b", "cd", "efg"]), 2, 7, 3).unwrap();
+        let keyspace = generator.keyspace();
+        assert_eq!(keyspace, Some(generator.count() as u128));
+    }
+
+    #[test]
+    fn test_prince_invalid_lengths_should_fail() {
+        assert!(matches!(
+            PrinceGenerator::new(words(&["a"]), 0, 4, 2).err(),
+            Some(GenPasswordsError::InvalidMinLengthError(0))
+        ));
+        assert!(matches!(
+            PrinceGenerator::new(words(&["a"]), 5, 4, 2).err(),
+            Some(GenPasswordsError::MinGreaterThanMaxError(5, 4))
+        ));
+    }
+
+    #[test]
+    fn test_prince_saturated_keyspace() {
+        // 10^39 and 10^40 passwords, both chains saturate at `u128::MAX`
+        let digits = (0..10).map(|d| d.to_string()).collect();
+        let mut generator = PrinceGenerator::new(digits, 39, 40, 40).unwrap();
+        assert_eq!(generator.keyspace(), None);
+
+        generator.seek(u128::MAX);
+        assert_eq!(generator.next(), Some("0".repeat(40)));
+        assert!(generator.next().is_some());
+    }
+}
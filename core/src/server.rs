@@ -0,0 +1,254 @@
+//! A line protocol to hash candidates and look passwords up from other tools on the network.
+//!
+//! Every request is a single line, answered with a single line:
+//! - `HASH <algorithm> <input>`: `OK <hex digest>` of the rest of the line encoded as UTF-8,
+//!   or of the raw bytes of a `$HEX[...]` input. The hash cache is not used, so that clients
+//!   cannot grow it without bound.
+//! - `LOOKUP <algorithm> <hex digest>`: `OK <password>` if the digest is in the loaded table,
+//!   `NOTFOUND` otherwise
+//! - `STATS`: `OK` followed by `name=value` counters of the server
+//!
+//! Algorithms are named like in output files, e.g. `md5`. A request that cannot be answered
+//! gets `ERR <message>`, and the connection stays open for the next one, also when it is not
+//! valid UTF-8. A request longer than
+//! [`MAX_REQUEST_LEN`] bytes gets `ERR` too, but the connection is closed.
+use crate::encoding::Encoding;
+use crate::errors::ServerError;
+use crate::table::Table;
+use crate::utils::{hex_string_to_vec, vec_to_hex_string};
+use crate::{hash_candidate_uncached, HashAlgorithm};
+use anyhow::Result;
+use std::io::{BufRead, Read, Write};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Longest request line read, without its line break. Requests are far shorter, the limit keeps
+/// a client from filling the memory with a line that never ends.
+pub const MAX_REQUEST_LEN: usize = 64 * 1024;
+
+/// Answers the requests of every connection, share it between connections with an `Arc`
+#[derive(Debug, Default)]
+pub struct Server {
+    table: Table,
+    requests: AtomicU64,
+    hashed: AtomicU64,
+    lookups: AtomicU64,
+    found: AtomicU64,
+    errors: AtomicU64,
+}
+
+impl Server {
+    /// Creates a new Server
+    /// Requires:
+    /// - table: Passwords to answer `LOOKUP` requests with
+    pub fn new(table: Table) -> Self {
+        Self {
+            table,
+            ..Self::default()
+        }
+    }
+
+    /// Hash of `input` encoded as UTF-8, or of the bytes of a `$HEX[...]` input
+    pub fn hash(&self, algorithm: HashAlgorithm, input: &str) -> Result<Vec<u8>> {
+        let hash = hash_candidate_uncached(algorithm, input, Encoding::Utf8)?;
+        self.hashed.fetch_add(1, Ordering::Relaxed);
+
+        Ok(hash)
+    }
+
+    /// Password that hashes to `digest` with `algorithm`, if it is in the table
+    pub fn lookup(&self, algorithm: HashAlgorithm, digest: &[u8]) -> Option<&str> {
+        self.lookups.fetch_add(1, Ordering::Relaxed);
+
+        let password = self.table.lookup(algorithm, digest);
+        if password.is_some() {
+            self.found.fetch_add(1, Ordering::Relaxed);
+        }
+
+        password
+    }
+
+    /// Answers the requests read from `reader` on `writer` until the connection is closed,
+    /// or a request is longer than [`MAX_REQUEST_LEN`]
+    pub fn handle_connection(
+        &self,
+        mut reader: impl BufRead,
+        mut writer: impl Write,
+    ) -> Result<()> {
+        let mut line = Vec::new();
+
+        loop {
+            line.clear();
+            // room for the line break, and one more byte to tell that the line is too long
+            let limit = MAX_REQUEST_LEN as u64 + 2;
+            if (&mut reader).take(limit).read_until(b'\n', &mut line)? == 0 {
+                return Ok(());
+            }
+
+            if line.last() == Some(&b'\n') {
+                line.pop();
+            }
+            if line.last() == Some(&b'\r') {
+                line.pop();
+            }
+
+            if line.len() > MAX_REQUEST_LEN {
+                let response = self.reject(ServerError::RequestTooLongError(MAX_REQUEST_LEN));
+                writeln!(writer, "{response}")?;
+                writer.flush()?;
+                return Ok(());
+            }
+
+            let response = match std::str::from_utf8(&line) {
+                Ok(request) => self.respond(request),
+                Err(_) => self.reject(ServerError::InvalidUtf8Error),
+            };
+            writeln!(writer, "{response}")?;
+            writer.flush()?;
+        }
+    }
+
+    /// Answers a single request line, without the line break
+    pub fn respond(&self, request: &str) -> String {
+        self.requests.fetch_add(1, Ordering::Relaxed);
+
+        self.try_respond(request).unwrap_or_else(|e| {
+            self.errors.fetch_add(1, Ordering::Relaxed);
+            format!("ERR {e}")
+        })
+    }
+
+    /// Answers a request that could not be read with `error`
+    fn reject(&self, error: ServerError) -> String {
+        self.requests.fetch_add(1, Ordering::Relaxed);
+        self.errors.fetch_add(1, Ordering::Relaxed);
+
+        format!("ERR {error}")
+    }
+
+    fn try_respond(&self, request: &str) -> Result<String> {
+        let (command, arguments) = request.split_once(' ').unwrap_or((request, ""));
+
+        match command {
+            "HASH" => {
+                let (algorithm, input) = arguments.split_once(' ').unwrap_or((arguments, ""));
+                let hash = self.hash(parse_algorithm(algorithm)?, input)?;
+
+                Ok(format!("OK {}", vec_to_hex_string(&hash)))
+            }
+            "LOOKUP" => {
+                let (algorithm, digest) = arguments.split_once(' ').unwrap_or((arguments, ""));
+                let algorithm = parse_algorithm(algorithm)?;
+                let digest = parse_digest(digest.trim())?;
+
+                Ok(match self.lookup(algorithm, &digest) {
+                    Some(password) => format!("OK {password}"),
+                    None => "NOTFOUND".to_owned(),
+                })
+            }
+            "STATS" => Ok(format!(
+                "OK requests={} hashed={} lookups={} found={} errors={} table_entries={}",
+                self.requests.load(Ordering::Relaxed),
+                self.hashed.load(Ordering::Relaxed),
+                self.lookups.load(Ordering::Relaxed),
+                self.found.load(Ordering::Relaxed),
+                self.errors.load(Ordering::Relaxed),
+                self.table.len(),
+            )),
+            _ => Err(ServerError::UnknownCommandError(command.to_owned()).into()),
+        }
+    }
+}
+
+fn parse_algorithm(name: &str) -> Result<HashAlgorithm> {
+    if name.is_empty() {
+        return Err(ServerError::MissingArgumentError("algorithm").into());
+    }
+
+    HashAlgorithm::from_name(name)
+        .ok_or_else(|| ServerError::UnknownAlgorithmError(name.to_owned()).into())
+}
+
+pub(crate) fn parse_digest(hex: &str) -> Result<Vec<u8>> {
+    if hex.is_empty() {
+        return Err(ServerError::MissingArgumentError("hex digest").into());
+    }
+
+    if !hex.len().is_multiple_of(2) || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(ServerError::InvalidDigestError(hex.to_owned()).into());
+    }
+
+    Ok(hex_string_to_vec(hex))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::server::{Server, MAX_REQUEST_LEN};
+    use crate::table::Table;
+    use crate::HashAlgorithm;
+    use hex_literal::hex;
+
+    #[test]
+    fn test_requests() {
+        let mut table = Table::new();
+        table.insert(
+            HashAlgorithm::Md5,
+            hex!("900150983cd24fb0d6963f7d28e17f72").to_vec(),
+            "abc def".to_owned(),
+        );
+        let server = Server::new(table);
+
+        // `$HEX[...]` candidates are hashed without the cache, which lives in the working directory
+        let requests = b"HASH md5 $HEX[616263]\n\
+                         LOOKUP md5 900150983CD24FB0D6963F7D28E17F72\r\n\
+                         LOOKUP md5 00\n\
+                         LOOKUP sha3 00\n\
+                         LOOKUP md5 0g\n\
+                         FETCH\n\
+                         HASH md5 caf\xe9\n\
+                         STATS\n";
+        let mut responses = Vec::new();
+        server
+            .handle_connection(&requests[..], &mut responses)
+            .unwrap();
+
+        let responses = String::from_utf8(responses).unwrap();
+        let lines = responses.lines().collect::<Vec<_>>();
+        assert_eq!(lines[0], "OK 900150983cd24fb0d6963f7d28e17f72");
+        assert_eq!(lines[1], "OK abc def");
+        assert_eq!(lines[2], "NOTFOUND");
+        assert_eq!(lines[3], "ERR Unknown algorithm `sha3`");
+        assert_eq!(lines[4], "ERR Invalid hex digest `0g`");
+        assert!(lines[5].starts_with("ERR Unknown command `FETCH`"));
+        assert!(lines[6].starts_with("ERR Request is not valid UTF-8"));
+        assert_eq!(
+            lines[7],
+            "OK requests=8 hashed=1 lookups=2 found=1 errors=4 table_entries=1"
+        );
+    }
+
+    #[test]
+    fn test_request_too_long_closes_the_connection() {
+        let server = Server::new(Table::new());
+
+        let mut requests = format!("HASH md5 {}\n", "a".repeat(MAX_REQUEST_LEN));
+        requests.push_str("STATS\n");
+        let mut responses = Vec::new();
+        server
+            .handle_connection(requests.as_bytes(), &mut responses)
+            .unwrap();
+
+        let responses = String::from_utf8(responses).unwrap();
+        assert_eq!(
+            responses,
+            format!("ERR Request is longer than {MAX_REQUEST_LEN} bytes\n")
+        );
+
+        // a request of exactly the maximum length is still answered
+        let request = format!("HASH md5 {}\r\n", "a".repeat(MAX_REQUEST_LEN - 9));
+        let mut responses = Vec::new();
+        server
+            .handle_connection(request.as_bytes(), &mut responses)
+            .unwrap();
+        assert!(responses.starts_with(b"OK "));
+    }
+}
@@ -0,0 +1,364 @@
+//! Where the hashes of a [`Pipeline`](crate::pipeline::Pipeline) go.
+use crate::checkpoint::Checkpointer;
+use crate::errors::CheckpointError;
+use crate::format::OutputFormat;
+use crate::output::{self, CheckpointedWriter, OutputOptions};
+use crate::pipeline::{HashedChunk, Sink};
+use crate::table::Table;
+use crate::HashAlgorithm;
+use anyhow::Result;
+use std::collections::HashSet;
+
+/// Drops the hashes, e.g. to benchmark hashing
+#[derive(Debug, Default)]
+pub struct DiscardSink {
+    checkpointer: Option<Checkpointer>,
+}
+
+impl DiscardSink {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl Sink for DiscardSink {
+    type Output = ();
+
+    fn open(&mut self, _: &[HashAlgorithm], checkpointer: Option<Checkpointer>) -> Result<()> {
+        self.checkpointer = checkpointer;
+        Ok(())
+    }
+
+    fn write_chunk(&mut self, chunk: HashedChunk) -> Result<()> {
+        if let Some(checkpointer) = &mut self.checkpointer {
+            checkpointer.complete(chunk.start, chunk.len);
+
+            // nothing is written to disk
+            if checkpointer.is_due() {
+                checkpointer.save(Vec::new())?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn finish(self) -> Result<()> {
+        if let Some(checkpointer) = self.checkpointer {
+            checkpointer.finish(Vec::new())?;
+        }
+
+        Ok(())
+    }
+
+    fn close(self) -> Result<()> {
+        if let Some(mut checkpointer) = self.checkpointer {
+            checkpointer.save(Vec::new())?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Writes the hashes to a file, one record per candidate.
+/// With several algorithms, the hashes of every algorithm are written to a separate file, see
+/// [`output::algorithm_path`], unless the output format combines them into a single record.
+pub struct FileSink {
+    path: String,
+    options: OutputOptions,
+    algorithms: Vec<HashAlgorithm>,
+    /// `None` until the pipeline opens the sink
+    writer: Option<CheckpointedWriter>,
+}
+
+impl FileSink {
+    /// Creates a new FileSink, the output files are created when the pipeline starts
+    /// Requires:
+    /// - path: Path of the output file
+    /// - options: How the output file is formatted and compressed
+    pub fn new(path: &str, options: OutputOptions) -> Self {
+        Self {
+            path: path.to_owned(),
+            options,
+            algorithms: Vec::new(),
+            writer: None,
+        }
+    }
+}
+
+impl Sink for FileSink {
+    type Output = ();
+
+    fn open(
+        &mut self,
+        algorithms: &[HashAlgorithm],
+        checkpointer: Option<Checkpointer>,
+    ) -> Result<()> {
+        let format = self.options.format;
+        let out_paths = if format.is_combined(algorithms) {
+            vec![self.path.clone()]
+        } else {
+            algorithms
+                .iter()
+                .map(|algorithm| output::algorithm_path(&self.path, *algorithm))
+                .collect()
+        };
+
+        let mut writer =
+            CheckpointedWriter::create(&out_paths, self.options.compression, checkpointer)?;
+        if let Some(header) = format.header() {
+            writer.write_header(header.as_bytes())?;
+        }
+
+        self.writer = Some(writer);
+        self.algorithms = algorithms.to_vec();
+        Ok(())
+    }
+
+    fn write_chunk(&mut self, chunk: HashedChunk) -> Result<()> {
+        let format = self.options.format;
+        let writer = self
+            .writer
+            .as_mut()
+            .expect("file sink is opened before the first chunk");
+        let combined = self.algorithms.len() > 1 && writer.outputs() == 1;
+        let mut records = vec![Vec::new(); writer.outputs()];
+
+        for (password, hashes) in &chunk.hashes {
+            if combined {
+                OutputFormat::write_combined_record(
+                    &mut records[0],
+                    &self.algorithms,
+                    hashes,
+                    password,
+                );
+                continue;
+            }
+
+            for (records, (algorithm, hash)) in
+                records.iter_mut().zip(self.algorithms.iter().zip(hashes))
+            {
+                format.write_record(records, *algorithm, hash, password);
+            }
+        }
+
+        writer.write_chunk(chunk.start, chunk.len, &records)
+    }
+
+    fn finish(self) -> Result<()> {
+        self.writer.map_or(Ok(()), CheckpointedWriter::finish)
+    }
+
+    fn close(self) -> Result<()> {
+        self.writer.map_or(Ok(()), CheckpointedWriter::close)
+    }
+}
+
+/// Collects the hashes into a [`Table`], to look passwords up by hash
+#[derive(Debug, Default)]
+pub struct TableSink {
+    algorithms: Vec<HashAlgorithm>,
+    table: Table,
+}
+
+impl TableSink {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl Sink for TableSink {
+    type Output = Table;
+
+    fn open(
+        &mut self,
+        algorithms: &[HashAlgorithm],
+        checkpointer: Option<Checkpointer>,
+    ) -> Result<()> {
+        if checkpointer.is_some() {
+            return Err(CheckpointError::NotResumableError.into());
+        }
+
+        self.algorithms = algorithms.to_vec();
+        Ok(())
+    }
+
+    fn write_chunk(&mut self, chunk: HashedChunk) -> Result<()> {
+        for (password, hashes) in chunk.hashes {
+            for (algorithm, hash) in self.algorithms.iter().zip(hashes) {
+                self.table.insert(*algorithm, hash, password.clone());
+            }
+        }
+
+        Ok(())
+    }
+
+    fn finish(self) -> Result<Table> {
+        Ok(self.table)
+    }
+
+    fn close(self) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// A candidate that hashes to one of the hashes a [`MatcherSink`] looks for
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Match {
+    pub algorithm: HashAlgorithm,
+    pub hash: Vec<u8>,
+    pub password: String,
+}
+
+/// Looks for candidates that hash to one of a set of hashes, with any of the algorithms
+#[derive(Debug)]
+pub struct MatcherSink {
+    algorithms: Vec<HashAlgorithm>,
+    targets: HashSet<Vec<u8>>,
+    matches: Vec<Match>,
+}
+
+impl MatcherSink {
+    /// Creates a new MatcherSink
+    /// Requires:
+    /// - targets: Hashes to find the passwords of
+    pub fn new(targets: impl IntoIterator<Item = Vec<u8>>) -> Self {
+        Self {
+            algorithms: Vec::new(),
+            targets: targets.into_iter().collect(),
+            matches: Vec::new(),
+        }
+    }
+}
+
+impl Sink for MatcherSink {
+    type Output = Vec<Match>;
+
+    fn open(
+        &mut self,
+        algorithms: &[HashAlgorithm],
+        checkpointer: Option<Checkpointer>,
+    ) -> Result<()> {
+        if checkpointer.is_some() {
+            return Err(CheckpointError::NotResumableError.into());
+        }
+
+        self.algorithms = algorithms.to_vec();
+        Ok(())
+    }
+
+    fn write_chunk(&mut self, chunk: HashedChunk) -> Result<()> {
+        for (password, hashes) in chunk.hashes {
+            for (algorithm, hash) in self.algorithms.iter().zip(hashes) {
+                if self.targets.contains(&hash) {
+                    self.matches.push(Match {
+                        algorithm: *algorithm,
+                        hash,
+                        password: password.clone(),
+                    });
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn matched(&self) -> u64 {
+        self.matches.len() as u64
+    }
+
+    fn finish(self) -> Result<Vec<Match>> {
+        Ok(self.matches)
+    }
+
+    fn close(self) -> Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::checkpoint::Checkpointer;
+    use crate::format::OutputFormat;
+    use crate::output::OutputOptions;
+    use crate::pipeline::{HashedChunk, Sink};
+    use crate::sink::FileSink;
+    use crate::HashAlgorithm;
+    use std::io::Write;
+    use std::time::Duration;
+
+    #[test]
+    fn test_file_sink_writes_a_file_per_algorithm() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("hashes.txt");
+        let options = OutputOptions {
+            format: OutputFormat::Potfile,
+            ..OutputOptions::default()
+        };
+
+        let mut sink = FileSink::new(path.to_str().unwrap(), options);
+        sink.open(&[HashAlgorithm::Md5, HashAlgorithm::Sha2], None)
+            .unwrap();
+        sink.write_chunk(HashedChunk {
+            start: 0,
+            len: 2,
+            hashes: vec![("a".to_owned(), vec![vec![0x0a], vec![0x0b, 0x0c]])],
+        })
+        .unwrap();
+        sink.finish().unwrap();
+
+        let md5 = std::fs::read_to_string(dir.path().join("hashes.md5.txt")).unwrap();
+        let sha2 = std::fs::read_to_string(dir.path().join("hashes.sha2.txt")).unwrap();
+        assert_eq!(md5, "0a:a\n");
+        assert_eq!(sha2, "0b0c:a\n");
+    }
+
+    #[test]
+    fn test_file_sink_resumes_every_output() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("hashes.txt");
+        let checkpoint_path = dir.path().join("job.checkpoint");
+        let checkpoint_path = checkpoint_path.to_str().unwrap();
+        let options = OutputOptions {
+            format: OutputFormat::Potfile,
+            ..OutputOptions::default()
+        };
+        let algorithms = [HashAlgorithm::Md5, HashAlgorithm::Sha2];
+        let chunk = |start, password: &str, hash| HashedChunk {
+            start,
+            len: 1,
+            hashes: vec![(password.to_owned(), vec![vec![hash], vec![hash, hash]])],
+        };
+
+        let checkpointer = Checkpointer::new(checkpoint_path, Duration::ZERO, true, 1, "").unwrap();
+        let mut sink = FileSink::new(path.to_str().unwrap(), options);
+        sink.open(&algorithms, Some(checkpointer)).unwrap();
+        sink.write_chunk(chunk(0, "a", 0x0a)).unwrap();
+        sink.close().unwrap();
+
+        // written after the checkpoint, lost when resuming
+        for name in ["hashes.md5.txt", "hashes.sha2.txt"] {
+            let mut file = std::fs::OpenOptions::new()
+                .append(true)
+                .open(dir.path().join(name))
+                .unwrap();
+            file.write_all(b"ff:lost\n").unwrap();
+        }
+
+        let checkpointer = Checkpointer::new(checkpoint_path, Duration::ZERO, true, 1, "").unwrap();
+        assert_eq!(checkpointer.checkpoint().output_lens, vec![5, 7]);
+        let mut sink = FileSink::new(path.to_str().unwrap(), options);
+        sink.open(&algorithms, Some(checkpointer)).unwrap();
+        sink.write_chunk(chunk(1, "b", 0x0b)).unwrap();
+        sink.finish().unwrap();
+
+        let md5 = std::fs::read_to_string(dir.path().join("hashes.md5.txt")).unwrap();
+        let sha2 = std::fs::read_to_string(dir.path().join("hashes.sha2.txt")).unwrap();
+        assert_eq!(md5, "0a:a\n0b:b\n");
+        assert_eq!(sha2, "0a0a:a\n0b0b:b\n");
+
+        // a single algorithm writes a single output file, the checkpoint records two
+        let checkpointer = Checkpointer::new(checkpoint_path, Duration::ZERO, true, 1, "").unwrap();
+        let mut sink = FileSink::new(path.to_str().unwrap(), options);
+        assert!(sink.open(&algorithms[..1], Some(checkpointer)).is_err());
+    }
+}
@@ -0,0 +1,217 @@
+//! Where the candidates of a [`Pipeline`](crate::pipeline::Pipeline) come from.
+use crate::compression::open_input;
+use crate::encoding::to_hex_notation;
+use crate::keyspace::KeyspaceRange;
+use crate::pipeline::Source;
+use anyhow::Result;
+use std::io::{BufRead, BufReader, Read};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+/// Candidates of a [`crate::passwords::PasswordGenerator`], or any other candidate generator
+pub struct GeneratorSource<G> {
+    generator: G,
+}
+
+impl<G: Iterator<Item = String> + Send> GeneratorSource<G> {
+    pub fn new(generator: G) -> Self {
+        Self { generator }
+    }
+}
+
+impl<G: Iterator<Item = String> + Send> Source for GeneratorSource<G> {
+    fn read_chunk(&mut self, len: usize) -> Result<Vec<Option<String>>> {
+        Ok(self.generator.by_ref().take(len).map(Some).collect())
+    }
+}
+
+/// Candidates that are already in memory
+pub struct VecSource {
+    candidates: std::vec::IntoIter<String>,
+}
+
+impl VecSource {
+    pub fn new(candidates: Vec<String>) -> Self {
+        Self {
+            candidates: candidates.into_iter(),
+        }
+    }
+}
+
+impl Source for VecSource {
+    fn read_chunk(&mut self, len: usize) -> Result<Vec<Option<String>>> {
+        Ok(self.candidates.by_ref().take(len).map(Some).collect())
+    }
+}
+
+/// Lines of a wordlist, decompressed if needed
+pub struct FileSource {
+    lines: Lines,
+}
+
+impl FileSource {
+    /// Opens the wordlist at `path`, only the lines in `range` are read
+    pub fn open(path: &str, range: KeyspaceRange) -> Result<Self> {
+        Ok(Self {
+            lines: Lines::new(open_input(path)?, range),
+        })
+    }
+}
+
+impl Source for FileSource {
+    fn read_chunk(&mut self, len: usize) -> Result<Vec<Option<String>>> {
+        self.lines.read_chunk(len)
+    }
+
+    fn candidate_name(&self) -> &'static str {
+        "line"
+    }
+
+    fn bytes_read(&self) -> u64 {
+        self.lines.bytes_read.load(Ordering::Relaxed)
+    }
+}
+
+/// Lines of the standard input, e.g. piped from another cracking tool
+pub struct StdinSource {
+    lines: Lines,
+}
+
+impl StdinSource {
+    /// Only the lines in `range` are read
+    pub fn new(range: KeyspaceRange) -> Self {
+        Self {
+            lines: Lines::new(BufReader::new(std::io::stdin()), range),
+        }
+    }
+}
+
+impl Source for StdinSource {
+    fn read_chunk(&mut self, len: usize) -> Result<Vec<Option<String>>> {
+        self.lines.read_chunk(len)
+    }
+
+    fn candidate_name(&self) -> &'static str {
+        "line"
+    }
+
+    fn bytes_read(&self) -> u64 {
+        self.lines.bytes_read.load(Ordering::Relaxed)
+    }
+}
+
+/// Lines of a reader. Lines that are not valid UTF-8 are turned into `$HEX[...]` candidates,
+/// so that their raw bytes are hashed and every line still gets a record.
+struct Lines {
+    lines: Box<dyn Iterator<Item = std::io::Result<String>> + Send>,
+    /// Shared with the reader inside `lines`
+    bytes_read: Arc<AtomicU64>,
+}
+
+impl Lines {
+    fn new(reader: impl BufRead + Send + 'static, range: KeyspaceRange) -> Self {
+        let skip = usize::try_from(range.skip).unwrap_or(usize::MAX);
+        let limit = range.limit.map_or(usize::MAX, |limit| {
+            usize::try_from(limit).unwrap_or(usize::MAX)
+        });
+
+        let bytes_read = Arc::new(AtomicU64::new(0));
+        let reader = CountingReader {
+            reader,
+            bytes_read: bytes_read.clone(),
+        };
+
+        Self {
+            lines: Box::new(
+                reader
+                    .split(b'\n')
+                    .skip(skip)
+                    .take(limit)
+                    .map(|line| line.map(line_candidate)),
+            ),
+            bytes_read,
+        }
+    }
+
+    fn read_chunk(&mut self, len: usize) -> Result<Vec<Option<String>>> {
+        let lines = self
+            .lines
+            .by_ref()
+            .take(len)
+            .map(|line| line.map(Some))
+            .collect::<std::io::Result<Vec<_>>>()?;
+
+        Ok(lines)
+    }
+}
+
+/// Candidate of a line without its `\n`, like [`BufRead::lines`] a trailing `\r` is dropped too
+fn line_candidate(mut line: Vec<u8>) -> String {
+    if line.last() == Some(&b'\r') {
+        line.pop();
+    }
+
+    String::from_utf8(line).unwrap_or_else(|e| to_hex_notation(e.as_bytes()))
+}
+
+/// Counts the bytes consumed from a reader
+struct CountingReader<R> {
+    reader: R,
+    bytes_read: Arc<AtomicU64>,
+}
+
+impl<R: BufRead> Read for CountingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let n = self.reader.read(buf)?;
+        self.bytes_read.fetch_add(n as u64, Ordering::Relaxed);
+        Ok(n)
+    }
+}
+
+impl<R: BufRead> BufRead for CountingReader<R> {
+    fn fill_buf(&mut self) -> std::io::Result<&[u8]> {
+        self.reader.fill_buf()
+    }
+
+    fn consume(&mut self, amt: usize) {
+        self.reader.consume(amt);
+        self.bytes_read.fetch_add(amt as u64, Ordering::Relaxed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::keyspace::KeyspaceRange;
+    use crate::pipeline::Source;
+    use crate::source::{FileSource, GeneratorSource};
+
+    #[test]
+    fn test_file_source_hex_encodes_invalid_lines() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("words.txt");
+        std::fs::write(&path, b"skipped\na\r\n\xff\nb\nc\nlimited\n").unwrap();
+
+        let mut source =
+            FileSource::open(path.to_str().unwrap(), KeyspaceRange::new(1, Some(4))).unwrap();
+        assert_eq!(
+            source.read_chunk(3).unwrap(),
+            [
+                Some("a".to_owned()),
+                Some("$HEX[ff]".to_owned()),
+                Some("b".to_owned())
+            ]
+        );
+        assert_eq!(source.read_chunk(3).unwrap(), [Some("c".to_owned())]);
+        assert!(source.read_chunk(3).unwrap().is_empty());
+        assert_eq!(source.bytes_read(), 17);
+    }
+
+    #[test]
+    fn test_generator_source_chunks() {
+        let mut source = GeneratorSource::new((0..5).map(|i| i.to_string()));
+
+        assert_eq!(source.read_chunk(3).unwrap().len(), 3);
+        assert_eq!(source.read_chunk(3).unwrap().len(), 2);
+        assert!(source.read_chunk(3).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,244 @@
+//! Counters of a running job, for progress bars and monitoring.
+//!
+//! The threads of a job add to the counters once per chunk without locking, so that reporting
+//! progress does not slow hashing down. Readers take a [`StatsSnapshot`] whenever they need one,
+//! or get one periodically with [`Stats::watch`].
+use serde::Serialize;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Shared by the threads of a job and whoever watches it
+#[derive(Debug, Default)]
+pub struct Stats {
+    generated: AtomicU64,
+    hashed: AtomicU64,
+    written: AtomicU64,
+    matched: AtomicU64,
+    bytes_read: AtomicU64,
+    skipped: AtomicU64,
+    errors: AtomicU64,
+    queued: AtomicUsize,
+    in_flight: AtomicUsize,
+}
+
+/// The counters of a job at one point in time
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct StatsSnapshot {
+    /// Candidates read or generated
+    pub generated: u64,
+    /// Candidates hashed
+    pub hashed: u64,
+    /// Candidates written to the output, or handed to whatever consumes the hashes
+    pub written: u64,
+    /// Hashes found among the ones looked for
+    pub matched: u64,
+    /// Bytes read from the input, after decompression
+    pub bytes_read: u64,
+    /// Candidates skipped because their source could not read them
+    pub skipped: u64,
+    /// Jobs that failed
+    pub errors: u64,
+    /// Chunks waiting for a worker thread
+    pub queued: usize,
+    /// Chunks read but not written yet
+    pub in_flight: usize,
+}
+
+impl Stats {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records that `n` more candidates were read or generated
+    pub fn add_generated(&self, n: u64) {
+        self.generated.fetch_add(n, Ordering::Relaxed);
+    }
+
+    /// Records that `n` more candidates were hashed
+    pub fn add_hashed(&self, n: u64) {
+        self.hashed.fetch_add(n, Ordering::Relaxed);
+    }
+
+    /// Records that `n` more candidates were written
+    pub fn add_written(&self, n: u64) {
+        self.written.fetch_add(n, Ordering::Relaxed);
+    }
+
+    /// Records that `n` more candidates were skipped
+    pub fn add_skipped(&self, n: u64) {
+        self.skipped.fetch_add(n, Ordering::Relaxed);
+    }
+
+    /// Records that a job failed
+    pub fn add_error(&self) {
+        self.errors.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Sets the number of hashes found so far
+    pub fn set_matched(&self, matched: u64) {
+        self.matched.store(matched, Ordering::Relaxed);
+    }
+
+    /// Sets the number of bytes read so far
+    pub fn set_bytes_read(&self, bytes_read: u64) {
+        self.bytes_read.store(bytes_read, Ordering::Relaxed);
+    }
+
+    /// Sets the number of chunks waiting for a worker thread, and of chunks read but not
+    /// written yet
+    pub fn set_queue_depth(&self, queued: usize, in_flight: usize) {
+        self.queued.store(queued, Ordering::Relaxed);
+        self.in_flight.store(in_flight, Ordering::Relaxed);
+    }
+
+    pub fn snapshot(&self) -> StatsSnapshot {
+        StatsSnapshot {
+            generated: self.generated.load(Ordering::Relaxed),
+            hashed: self.hashed.load(Ordering::Relaxed),
+            written: self.written.load(Ordering::Relaxed),
+            matched: self.matched.load(Ordering::Relaxed),
+            bytes_read: self.bytes_read.load(Ordering::Relaxed),
+            skipped: self.skipped.load(Ordering::Relaxed),
+            errors: self.errors.load(Ordering::Relaxed),
+            queued: self.queued.load(Ordering::Relaxed),
+            in_flight: self.in_flight.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Calls `report` with a snapshot every `interval` until `done` returns true, then once more
+    /// so that the last snapshot has the final counts
+    pub fn watch(
+        &self,
+        interval: Duration,
+        done: impl Fn() -> bool,
+        mut report: impl FnMut(&StatsSnapshot),
+    ) {
+        while !done() {
+            report(&self.snapshot());
+            std::thread::sleep(interval);
+        }
+
+        report(&self.snapshot());
+    }
+}
+
+/// Progress of a job, for scripts and monitoring rather than people
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct ProgressReport {
+    /// Seconds since the Unix epoch
+    pub timestamp: f64,
+    /// Seconds since the job started
+    pub elapsed: f64,
+    /// Candidates processed, including skipped ones and the ones processed before the job was
+    /// resumed
+    pub processed: u64,
+    /// Candidates of the whole job, if known
+    pub total: Option<u64>,
+    /// Candidates processed per second since the job started
+    pub rate: f64,
+    /// Seconds until the job is done at the current rate, if the total is known
+    pub eta: Option<f64>,
+    /// Hashes found among the ones looked for
+    pub matches: u64,
+}
+
+impl ProgressReport {
+    /// Creates a new ProgressReport
+    /// Requires:
+    /// - snapshot: Counters of the job
+    /// - elapsed: Time since the job started
+    /// - total: Candidates of the whole job, if known
+    /// - done_before: Candidates processed before the job was resumed from a checkpoint,
+    ///   including chunks that were completed out of order
+    pub fn new(
+        snapshot: &StatsSnapshot,
+        elapsed: Duration,
+        total: Option<u64>,
+        done_before: u64,
+    ) -> Self {
+        let elapsed = elapsed.as_secs_f64();
+        // skipped candidates are done too, otherwise a job with invalid lines never finishes
+        let processed_now = snapshot.written + snapshot.skipped;
+        let rate = if elapsed > 0.0 {
+            processed_now as f64 / elapsed
+        } else {
+            0.0
+        };
+        let processed = done_before + processed_now;
+        let eta = total
+            .filter(|_| rate > 0.0)
+            .map(|total| total.saturating_sub(processed) as f64 / rate);
+
+        Self {
+            timestamp: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs_f64(),
+            elapsed,
+            processed,
+            total,
+            rate,
+            eta,
+            matches: snapshot.matched,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::stats::{ProgressReport, Stats, StatsSnapshot};
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    #[test]
+    fn test_watch_reports_final_counts() {
+        let stats = Arc::new(Stats::new());
+
+        let job = {
+            let stats = stats.clone();
+            std::thread::spawn(move || {
+                for _ in 0..10 {
+                    stats.add_generated(100);
+                    stats.add_hashed(100);
+                    stats.add_written(100);
+                }
+                stats.set_matched(3);
+            })
+        };
+
+        let mut last = StatsSnapshot::default();
+        stats.watch(
+            Duration::from_millis(1),
+            || job.is_finished(),
+            |snapshot| last = *snapshot,
+        );
+
+        assert_eq!(last.generated, 1000);
+        assert_eq!(last.written, 1000);
+        assert_eq!(last.matched, 3);
+    }
+
+    #[test]
+    fn test_progress_report_rate_and_eta() {
+        let snapshot = StatsSnapshot {
+            written: 180,
+            skipped: 20,
+            matched: 2,
+            ..StatsSnapshot::default()
+        };
+
+        let report = ProgressReport::new(&snapshot, Duration::from_secs(4), Some(1000), 100);
+        assert_eq!(report.processed, 300);
+        assert_eq!(report.rate, 50.0);
+        assert_eq!(report.eta, Some(14.0));
+        assert_eq!(report.matches, 2);
+
+        let report = ProgressReport::new(&snapshot, Duration::from_secs(4), Some(1000), 800);
+        assert_eq!(report.processed, 1000);
+        assert_eq!(report.eta, Some(0.0));
+
+        let report = ProgressReport::new(&snapshot, Duration::ZERO, None, 0);
+        assert_eq!(report.rate, 0.0);
+        assert_eq!(report.eta, None);
+    }
+}
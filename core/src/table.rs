@@ -0,0 +1,74 @@
+//! Lookup tables from hashes back to the passwords they were computed from.
+use crate::HashAlgorithm;
+use std::collections::HashMap;
+
+/// Passwords by hash, for every algorithm they were hashed with
+#[derive(Debug, Clone, Default)]
+pub struct Table {
+    entries: HashMap<HashAlgorithm, HashMap<Vec<u8>, String>>,
+}
+
+impl Table {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records that `password` hashes to `hash` with `algorithm`
+    pub fn insert(&mut self, algorithm: HashAlgorithm, hash: Vec<u8>, password: String) {
+        self.entries
+            .entry(algorithm)
+            .or_default()
+            .insert(hash, password);
+    }
+
+    /// Adds every entry of `other`, replacing the passwords of hashes that are in both
+    pub fn merge(&mut self, other: Table) {
+        for (algorithm, hashes) in other.entries {
+            self.entries.entry(algorithm).or_default().extend(hashes);
+        }
+    }
+
+    /// The password that hashes to `hash` with `algorithm`, if it is in the table
+    pub fn lookup(&self, algorithm: HashAlgorithm, hash: &[u8]) -> Option<&str> {
+        self.entries
+            .get(&algorithm)
+            .and_then(|hashes| hashes.get(hash))
+            .map(String::as_str)
+    }
+
+    /// Number of hashes in the table, over every algorithm
+    pub fn len(&self) -> usize {
+        self.entries.values().map(HashMap::len).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::table::Table;
+    use crate::HashAlgorithm;
+
+    #[test]
+    fn test_lookup_by_algorithm() {
+        let mut table = Table::new();
+        assert!(table.is_empty());
+
+        table.insert(HashAlgorithm::Md5, vec![1, 2], "a".to_owned());
+        table.insert(HashAlgorithm::Sha2, vec![1, 2], "b".to_owned());
+
+        assert_eq!(table.len(), 2);
+        assert_eq!(table.lookup(HashAlgorithm::Md5, &[1, 2]), Some("a"));
+        assert_eq!(table.lookup(HashAlgorithm::Sha2, &[1, 2]), Some("b"));
+        assert_eq!(table.lookup(HashAlgorithm::Sha512, &[1, 2]), None);
+        assert_eq!(table.lookup(HashAlgorithm::Md5, &[3]), None);
+
+        let mut other = Table::new();
+        other.insert(HashAlgorithm::Md5, vec![3], "c".to_owned());
+        table.merge(other);
+        assert_eq!(table.len(), 3);
+        assert_eq!(table.lookup(HashAlgorithm::Md5, &[3]), Some("c"));
+    }
+}
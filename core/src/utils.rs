@@ -1,5 +1,12 @@
+use crate::compression::open_input;
 use anyhow::{Ok, Result};
-use std::{fs::File, io::Write, path::Path, process};
+use std::{
+    fs::{self, File},
+    io::{BufRead, Write},
+    path::Path,
+    process,
+    sync::atomic::{AtomicU64, Ordering},
+};
 
 pub fn file_exists(path: &str) -> bool {
     Path::new(path).exists()
@@ -16,26 +23,63 @@ pub fn write_file(path: &str, content: &str) -> Result<()> {
     Ok(())
 }
 
+/// Writes to a temporary file and renames it to `path`, so that anyone reading `path`
+/// at the same time sees either the old or the new content, never half of it.
+/// The content is on the disk before the rename, so a crash does not leave an empty file behind.
+pub fn write_file_atomic(path: &str, content: &str) -> Result<()> {
+    static TMP_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+    let tmp_path = format!(
+        "{}.{}.{}.tmp",
+        path,
+        process::id(),
+        TMP_COUNTER.fetch_add(1, Ordering::Relaxed)
+    );
+    let mut file = File::create(&tmp_path)?;
+    file.write_all(content.as_bytes())?;
+    file.sync_data()?;
+    fs::rename(&tmp_path, path)?;
+
+    Ok(())
+}
+
+/// Reads every line of a file, e.g. a wordlist, into memory.
+/// Gzip and zstd compressed files are decompressed on the fly.
+pub fn read_lines(path: &str) -> Result<Vec<String>> {
+    let file = open_input(path)?;
+    let lines = file.lines().collect::<std::io::Result<Vec<_>>>()?;
+
+    Ok(lines)
+}
+
+/// Counts the lines of a file without keeping them in memory.
+/// Gzip and zstd compressed files are decompressed on the fly.
+pub fn count_lines(path: &str) -> Result<u128> {
+    let file = open_input(path)?;
+    let mut count = 0;
+
+    for line in file.split(b'\n') {
+        line?;
+        count += 1;
+    }
+
+    Ok(count)
+}
+
 pub fn write_bytes_to_file(path: &str, content: Vec<u8>) -> Result<()> {
     let mut file = File::create(path)?;
     file.write_all(&content)?;
     Ok(())
 }
 
+/// Decodes UTF-8 bytes, invalid sequences are replaced with `U+FFFD`
 pub fn vec_to_string(vec: &[u8]) -> String {
-    let mut s = String::new();
-    for byte in vec {
-        s.push(*byte as char);
-    }
-    s
+    String::from_utf8_lossy(vec).into_owned()
 }
 
+/// UTF-8 bytes of a string, see [`crate::encoding::Encoding`] for other encodings
 pub fn string_to_vec(s: &str) -> Vec<u8> {
-    let mut v = Vec::new();
-    for c in s.chars() {
-        v.push(c as u8);
-    }
-    v
+    s.as_bytes().to_vec()
 }
 
 pub fn vec_to_hex_string(vec: &[u8]) -> String {